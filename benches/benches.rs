@@ -38,18 +38,27 @@ fn src_code() -> SourceCode {
         items: vec![],
         crates: vec![],
         stmts: vec![
-            StmtGrp(vec![Statement {
-                expr: LOREM_IPSUM.to_string(),
-                semi: false,
-            }]),
-            StmtGrp(vec![Statement {
-                expr: LOREM_IPSUM.to_string(),
-                semi: true,
-            }]),
-            StmtGrp(vec![Statement {
-                expr: LOREM_IPSUM.to_string(),
-                semi: false,
-            }]),
+            StmtGrp(
+                vec![Statement {
+                    expr: LOREM_IPSUM.to_string(),
+                    semi: false,
+                }],
+                false,
+            ),
+            StmtGrp(
+                vec![Statement {
+                    expr: LOREM_IPSUM.to_string(),
+                    semi: true,
+                }],
+                false,
+            ),
+            StmtGrp(
+                vec![Statement {
+                    expr: LOREM_IPSUM.to_string(),
+                    semi: false,
+                }],
+                false,
+            ),
         ],
     }
 }
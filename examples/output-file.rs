@@ -81,6 +81,11 @@ fn write_outoput_to_file(rx: Receiver) -> std::thread::JoinHandle<()> {
                     output.push('\n');
                     pos = output.len();
                 }
+                OutputChange::Clear => {
+                    output.clear();
+                    pos = 0;
+                    std::fs::write("repl-output.txt", &output).unwrap();
+                }
             }
         }
     })
@@ -109,6 +109,10 @@ fn write_output_to_stdout(rx: Receiver) -> std::thread::JoinHandle<()> {
                     lock.flush().unwrap();
                 }
                 OutputChange::NewLine => writeln!(&mut stdout, "").unwrap(),
+                OutputChange::Clear => {
+                    write!(&mut stdout, "\x1b[2J\x1b[H").unwrap();
+                    stdout.flush().unwrap();
+                }
             }
         }
     })
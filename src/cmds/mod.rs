@@ -42,6 +42,31 @@
 //! glob patterns matching module paths, for example `:mod clear test/**` will clear all inputs under
 //! the module path `test/`. _`:mod clear` clears all previous REPL input in the **current module**._
 //!
+//! ## Editing
+//! The `edit` command edits previous input. `:edit stmt alter 0` loads the first statement into the
+//! input so it can be changed in place. `:edit last` loads the last input, which is useful for
+//! fixing a typo; the changed input is evaluated as a new input.
+//!
+//! ## Clearing Output
+//! The `clear` command clears the output, discarding all previous lines. Output listeners receive an
+//! [`OutputChange::Clear`](crate::output::OutputChange::Clear) change, which the terminal REPL
+//! handles by clearing the screen.
+//!
+//! ## Clearing Results
+//! The `clearout` command clears the previous results (`out#`) in the current module while keeping
+//! the defined items, crates, and other statements such as `let` bindings. Inputs which were only a
+//! result are removed, if none of the previous inputs had other statements the next result will be
+//! `out0`.
+//!
+//! ## Version
+//! The `version` command prints the papyrus version along with the `rustc` version, host triple,
+//! and edition that evaluations are compiled with. This is useful information for bug reports, as
+//! linking failures are often due to toolchain mismatches.
+//!
+//! ## Compile Command
+//! The `cmd` command prints the command line that was used for the last compilation, including the
+//! flags for linked libraries. Run it from a shell to reproduce a compilation failure by hand.
+//!
 //! ## Static Files
 //! The `static-files` command allows the importing of file-system based rust documents into the REPL
 //! compilation. Rust files must be relative to the REPL working directory, and will be imported using
@@ -206,6 +231,27 @@
 //! [lib] custom-cmds-app=> app_data.as_str()
 //! custom-cmds-app [out2]: "hello, world!"
 //! ```
+//!
+//! ## Registering commands
+//!
+//! Commands which only need the `ReplData` can be registered through the [`CommandRegistry`]
+//! extension trait. The handler receives the REPL data and the arguments after the command name,
+//! and returns the output to print. The built-in commands such as `clearout` are registered this
+//! way.
+//!
+//! ```rust
+//! # extern crate papyrus;
+//! # use papyrus::cmds::{CommandRegistry, CommandResult};
+//! # type Builder = papyrus::cmdtree::Builder<CommandResult<String>>;
+//! Builder::new("custom-cmds-app")
+//!     .register_command(
+//!         "stmts",
+//!         "count the statements in the current module",
+//!         Box::new(|repldata, _args| repldata.current_src().stmts.len().to_string()),
+//!     )
+//!     .unwrap()
+//! # ;
+//! ```
 use super::*;
 use crate::repl::{Editing, EditingIndex, ReplData};
 use cmdtree::{BuildError, Builder, BuilderChain, Commander};
@@ -213,6 +259,7 @@ use std::{
     fs,
     io::Write,
     path::{Path, PathBuf},
+    sync::Arc,
 };
 
 pub use cmdtree::Builder as CommandBuilder;
@@ -242,6 +289,8 @@ pub enum CommandResult<D> {
     EditReplace(EditingIndex, String),
     /// Switch to a module.
     SwitchModule(PathBuf),
+    /// Clear the output.
+    ClearOutput,
     /// Take an action on the `ReplData`.
     ActionOnReplData(ReplDataAction<D>),
     /// Take an action on data `D` and/or `ReplData`.
@@ -270,6 +319,46 @@ impl<D> CommandResult<D> {
     }
 }
 
+/// A command handler, taking the REPL data and the arguments after the command name, returning
+/// the output to print.
+pub type CommandHandler<D> = Box<dyn Fn(&mut ReplData<D>, &str) -> String + Send + Sync>;
+
+/// Extension trait to register commands with a handler acting on the `ReplData`.
+///
+/// This is a shorthand for `add_action` returning a
+/// [`CommandResult::repl_data_fn`](CommandResult::repl_data_fn). Commands are added to the current
+/// class of the builder, so they appear in `:help` and command completion as the built-in commands
+/// do (which are registered the same way).
+pub trait CommandRegistry<D> {
+    /// Register the command `name` with the `help` message.
+    fn register_command(
+        self,
+        name: &str,
+        help: &str,
+        handler: CommandHandler<D>,
+    ) -> Result<Builder<CommandResult<D>>, BuildError>;
+}
+
+impl<D, B> CommandRegistry<D> for B
+where
+    D: 'static,
+    B: BuilderChain<CommandResult<D>>,
+{
+    fn register_command(
+        self,
+        name: &str,
+        help: &str,
+        handler: CommandHandler<D>,
+    ) -> Result<Builder<CommandResult<D>>, BuildError> {
+        let handler = Arc::new(handler);
+        self.add_action(name, help, move |_, args| {
+            let handler = Arc::clone(&handler);
+            let args = args.join(" ");
+            CommandResult::repl_data_fn(move |data, _| handler(data, &args))
+        })
+    }
+}
+
 impl<D> ReplData<D> {
     /// Uses the given `Builder` as the root of the command tree.
     ///
@@ -291,7 +380,15 @@ fn papyrus_cmdr<D>(
         .add_action("mut", "Begin a mutable block of code", |_, _| {
             CommandResult::BeginMutBlock
         })
+        .add_action("clear", "Clear the output", |_, _| {
+            CommandResult::ClearOutput
+        })
         .begin_class("edit", "Edit previous input")
+        .add_action(
+            "last",
+            "Edit the last input, it replaces the input if it was kept",
+            |_, _| CommandResult::repl_data_fn(edit_last),
+        )
         .begin_class("stmt", "Edit previous statements")
         .add_action(
             "alter",
@@ -330,9 +427,138 @@ fn papyrus_cmdr<D>(
         )
         .add_action("ls", "List imported static files", |_, _| ls_static_files())
         .end_class()
+        .add_action(
+            "variant",
+            "Print the enum variant of an expression, without keeping it. args: expr",
+            |_, args| variant(args.join(" ")),
+        )
+        .register_command(
+            "clearout",
+            "Clear previous results, keeping items, crates, and bindings",
+            Box::new(|data, _| format!("cleared {} results", data.reset_outputs())),
+        )
+        .register_command(
+            "version",
+            "Print version and toolchain information",
+            Box::new(|data, _| version(data)),
+        )
+        .register_command(
+            "disk",
+            "Print the disk space used by the session",
+            Box::new(|data, _| disk_usage(data)),
+        )
+        .register_command(
+            "cmd",
+            "Print the command line of the last compilation",
+            Box::new(|data, _| {
+                data.last_compile_command()
+                    .unwrap_or("nothing has been compiled yet")
+                    .to_string()
+            }),
+        )
         .into_commander()
 }
 
+fn variant<D>(expr: String) -> CommandResult<D> {
+    CommandResult::app_data_fn(move |app_data, data, _| {
+        let (value, ty) = match data.eval_discarded(&expr, app_data) {
+            Ok(x) => x,
+            Err(e) => return e,
+        };
+
+        let not_variant = || format!("`{}` is not an enum variant", expr);
+
+        let (variant, ty) = match (outer_variant(&value), ty) {
+            (Some(variant), Some(ty)) => (variant, ty),
+            _ => return not_variant(),
+        };
+
+        // structs format the same way as variants, so check the type has such a variant by type
+        // checking a match against it
+        let probe = format!(
+            "match &({}) {{ x => {{ let _ = matches!(x, {}::{} {{ .. }}); }} }}",
+            expr,
+            type_path(&ty),
+            variant
+        );
+        match data.eval_check(&probe) {
+            Ok(()) => variant.to_string(),
+            Err(_) => not_variant(),
+        }
+    })
+}
+
+/// The outermost variant of a formatted enum value, such as `Some` in `Some(Ok(1))`. Values are
+/// formatted with `Debug`, which is output as a quoted string.
+fn outer_variant(value: &str) -> Option<&str> {
+    let value = value.trim_start_matches('"');
+    let end = value
+        .find(|c: char| !(c.is_alphanumeric() || c == '_'))
+        .unwrap_or(value.len());
+    let ident = &value[..end];
+    match ident.chars().next() {
+        Some(c) if c.is_uppercase() => Some(ident),
+        _ => None,
+    }
+}
+
+/// The path to name a type by in the REPL code, given its `type_name`. Generic arguments and
+/// references are removed, such as `core::option::Option` for `&core::option::Option<i32>`.
+fn type_path(ty: &str) -> String {
+    let ty = ty.trim_start_matches(|c| c == '&' || c == ' ');
+    let ty = if ty.starts_with("mut ") { &ty[4..] } else { ty };
+    let ty = &ty[..ty.find('<').unwrap_or(ty.len())];
+
+    let (krate, rest) = match ty.find("::") {
+        Some(i) => (&ty[..i], &ty[i..]),
+        None => return ty.to_string(),
+    };
+
+    // `alloc` is not in the extern prelude and the REPL code is compiled as its own crate
+    match krate {
+        "alloc" => format!("std{}", rest),
+        crate::compile::LIBRARY_NAME => format!("crate{}", rest),
+        _ => ty.to_string(),
+    }
+}
+
+fn version<D>(data: &mut ReplData<D>) -> String {
+    let papyrus = format!("papyrus {}", env!("CARGO_PKG_VERSION"));
+    match data.toolchain_info() {
+        Ok(info) => format!(
+            "{}\n{}\nhost: {}\nedition: {}",
+            papyrus, info.rustc_version, info.host_triple, info.edition
+        ),
+        Err(e) => format!("{}\nfailed to query toolchain: {}", papyrus, e),
+    }
+}
+
+fn disk_usage<D>(data: &ReplData<D>) -> String {
+    match (data.session_disk_usage(), data.max_disk_usage()) {
+        (Ok(used), Some(max)) => format!("{} used, capped to {}", fmt_bytes(used), fmt_bytes(max)),
+        (Ok(used), None) => format!("{} used", fmt_bytes(used)),
+        (Err(e), _) => format!("failed to measure disk usage: {}", e),
+    }
+}
+
+/// Formats a number of bytes with a binary unit, such as `1.5 MiB`.
+fn fmt_bytes(bytes: u64) -> String {
+    const UNITS: &[&str] = &["B", "KiB", "MiB", "GiB", "TiB"];
+
+    let mut size = bytes as f64;
+    let mut unit = 0;
+    while size >= 1024.0 && unit < UNITS.len() - 1 {
+        size /= 1024.0;
+        unit += 1;
+    }
+
+    if unit == 0 {
+        format!("{} B", bytes)
+    } else {
+        format!("{:.1} {}", size, UNITS[unit])
+    }
+}
+
 // ------ MODULES --------------------------------------------------------------
 fn switch_module_priv<D, W: Write>(args: &[&str], mut wtr: W) -> CommandResult<D> {
     if let Some(path) = args.get(0) {
@@ -428,6 +654,18 @@ pub(crate) fn edit_alter<D>(data: &mut ReplData<D>, ei: EditingIndex) -> &'stati
     }
 }
 
+fn edit_last<D>(data: &mut ReplData<D>, _: &mut dyn Write) -> String {
+    match data.last_input().map(String::from) {
+        Some(input) => {
+            // a kept input is replaced by the edit, rather than evaluating it again
+            data.editing = data.last_input_idx();
+            data.editing_src = Some(input);
+            String::new()
+        }
+        None => "there is no previous input".to_string(),
+    }
+}
+
 pub(crate) fn switch_module<D>(data: &mut ReplData<D>, path: &Path) -> &'static str {
     let mut all = make_all_parents(path);
     all.push(path.to_path_buf());
@@ -550,6 +788,46 @@ where
 mod tests {
     use super::*;
 
+    #[test]
+    fn outer_variant_test() {
+        assert_eq!(outer_variant("\"Some(Ok(1))\""), Some("Some"));
+        assert_eq!(outer_variant("\"Running { ticks: 2 }\""), Some("Running"));
+        assert_eq!(outer_variant("\"None\""), Some("None"));
+        assert_eq!(outer_variant("1"), None);
+        assert_eq!(outer_variant("true"), None);
+        assert_eq!(outer_variant("[1, 2]"), None);
+    }
+
+    #[test]
+    fn type_path_test() {
+        assert_eq!(
+            type_path("core::option::Option<i32>"),
+            "core::option::Option"
+        );
+        assert_eq!(
+            type_path("&mut core::result::Result<u8, core::num::ParseIntError>"),
+            "core::result::Result"
+        );
+        assert_eq!(type_path("alloc::borrow::Cow<str>"), "std::borrow::Cow");
+        assert_eq!(
+            type_path("papyrus_mem_code::inner::State"),
+            "crate::inner::State"
+        );
+        assert_eq!(type_path("&str"), "str");
+    }
+
+    #[test]
+    fn fmt_bytes_test() {
+        assert_eq!(fmt_bytes(0), "0 B");
+        assert_eq!(fmt_bytes(1023), "1023 B");
+        assert_eq!(fmt_bytes(1536), "1.5 KiB");
+        assert_eq!(fmt_bytes(300 * 1024 * 1024), "300.0 MiB");
+        assert_eq!(
+            fmt_bytes(5 * 1024 * 1024 * 1024 * 1024 * 1024),
+            "5120.0 TiB"
+        );
+    }
+
     #[test]
     fn make_path_test() {
         assert_eq!(make_path("   "), None);
@@ -625,4 +903,37 @@ mod tests {
         buf.clear();
         rm_static_file::<()>(&mut buf, &["what"]);
     }
+
+    #[test]
+    fn register_command_test() {
+        use crate::repl::{ReadResult, Repl};
+
+        let builder = Builder::new("test")
+            .register_command(
+                "greet",
+                "Greet someone",
+                Box::new(|data: &mut ReplData<()>, args| {
+                    format!("hello {} from {}", args, data.current_mod().display())
+                }),
+            )
+            .unwrap();
+
+        let mut repl: Repl<_, ()> = Repl::default();
+        repl.data.with_cmdtree_builder(builder).unwrap();
+
+        repl.line_input(":greet big world");
+        let mut repl = match repl.read() {
+            ReadResult::Eval(repl) => repl.eval(&mut ()).repl.print().0,
+            _ => panic!("expecting eval"),
+        };
+        assert!(repl.output().contains("hello big world from lib"));
+
+        repl.line_input(":help");
+        let repl = match repl.read() {
+            ReadResult::Eval(repl) => repl.eval(&mut ()).repl.print().0,
+            _ => panic!("expecting eval"),
+        };
+        assert!(repl.output().contains("greet -- Greet someone"));
+        assert!(repl.output().contains("clearout --"));
+    }
 }
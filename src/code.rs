@@ -23,7 +23,7 @@
 //!         expr: String::from("a"),
 //!         semi: false
 //!     }
-//! ]));
+//! ], false));
 //! ```
 //!
 //! Crates have some more structure around them.
@@ -45,6 +45,7 @@
 //! [`Statement`]: Statement
 use super::*;
 use crate::linking::LinkingConfiguration;
+use ::kserd::{Kserd, Value};
 use std::{
     borrow::Borrow,
     cmp::Ordering,
@@ -102,25 +103,38 @@ impl SourceCode {
         self.stmts.clear();
         self.crates.clear();
     }
+
+    /// The number of results bound to `out#`, which are numbered in order from `out0`.
+    ///
+    /// Groups which had their result released are not counted, so once all results are released
+    /// the next result is bound to `out0` again.
+    pub fn out_count(&self) -> usize {
+        self.stmts.iter().filter(|x| x.has_result()).count()
+    }
 }
 
 /// Group of statements that result in an expression to evaulate.
 ///
+/// Wraps as `(statements, released)`.
+///
+/// `released` is a flag marking a group whose result has been removed, all of its statements are
+/// written without an `out#` binding (see `ReplData::with_out_capture`).
+///
 /// # Example
 /// ```rust
 /// # use papyrus::code::*;
 /// let stmt1 = Statement { expr: "let a = 1".to_string(), semi: true };
 /// let stmt2 = Statement { expr: "a".to_string(), semi: false };
-/// let grp = StmtGrp(vec![stmt1, stmt2]);
+/// let grp = StmtGrp(vec![stmt1, stmt2], false);
 /// assert_eq!(&grp.src_line(), "let a = 1; a");
 /// ```
 #[derive(Clone)]
-pub struct StmtGrp(pub Vec<Statement>);
+pub struct StmtGrp(pub Vec<Statement>, pub bool);
 
 impl StmtGrp {
     /// The statements as a single line of rust code.
     pub fn src_line(&self) -> String {
-        let mut buf = String::with_capacity(self.assign_let_binding_length(0));
+        let mut buf = String::with_capacity(self.assign_let_binding_length("out", 0, false));
 
         let stmts = &self.0;
 
@@ -137,11 +151,25 @@ impl StmtGrp {
         buf
     }
 
-    /// Stringfy's the statements and assigns trailing expressions with `let out# = expr;`.
-    fn assign_let_binding(&self, input_num: usize, buf: &mut String) {
+    /// Stringfy's the statements and assigns trailing expressions with `let out# = expr;`, where
+    /// `out` is the `out_prefix`.
+    ///
+    /// If `borrow_ident` is set and the trailing expression is a bare identifier, it is assigned
+    /// as `let out# = &ident;`. This stops a binding from being moved into `out#`, so it can
+    /// still be used (and mutated) by later statements.
+    ///
+    /// Groups without a result are stringified without the assignment.
+    fn assign_let_binding(
+        &self,
+        out_prefix: &str,
+        input_num: usize,
+        borrow_ident: bool,
+        buf: &mut String,
+    ) {
         let stmts = &self.0;
+        let has_result = self.has_result();
 
-        for stmt in &stmts[0..stmts.len().saturating_sub(1)] {
+        for stmt in &stmts[0..stmts.len() - has_result as usize] {
             buf.push_str(&stmt.expr);
             if stmt.semi {
                 buf.push(';');
@@ -149,34 +177,87 @@ impl StmtGrp {
             buf.push('\n');
         }
 
-        if !stmts.is_empty() {
-            buf.push_str("let out");
+        if has_result {
+            buf.push_str("let ");
+            buf.push_str(out_prefix);
             buf.push_str(&input_num.to_string());
             buf.push_str(" = ");
+            if borrow_ident && self.trailing_is_ident() {
+                buf.push('&');
+            }
             buf.push_str(&stmts[stmts.len() - 1].expr);
             buf.push(';');
         }
     }
 
-    fn assign_let_binding_length(&self, input_num: usize) -> usize {
+    fn assign_let_binding_length(
+        &self,
+        out_prefix: &str,
+        input_num: usize,
+        borrow_ident: bool,
+    ) -> usize {
         let stmts = &self.0;
+        let has_result = self.has_result();
         let mut cap = 0;
 
-        for stmt in &stmts[0..stmts.len().saturating_sub(1)] {
+        for stmt in &stmts[0..stmts.len() - has_result as usize] {
             cap += 1 + stmt.expr.len();
             if stmt.semi {
                 cap += 1;
             }
         }
 
-        cap += if !stmts.is_empty() {
-            7 + input_num.to_string().len() + 3 + stmts[stmts.len() - 1].expr.len() + 1
+        cap += if has_result {
+            let brw = if borrow_ident && self.trailing_is_ident() {
+                1
+            } else {
+                0
+            };
+            4 + out_prefix.len()
+                + input_num.to_string().len()
+                + 3
+                + brw
+                + stmts[stmts.len() - 1].expr.len()
+                + 1
         } else {
             0
         };
 
         cap
     }
+
+    /// The group ends with a result which is assigned to `out#`, it has not been released.
+    pub(crate) fn has_result(&self) -> bool {
+        !self.1 && !self.0.is_empty()
+    }
+
+    /// The trailing expression is a single identifier which is used by a statement of the `later`
+    /// groups, such as `v` in `let mut v = vec![]; v` followed by `v.push(1);`.
+    fn trailing_used_by(&self, later: &[StmtGrp]) -> bool {
+        let ident = match self.0.last() {
+            Some(x) if self.trailing_is_ident() => x.expr.trim(),
+            _ => return false,
+        };
+
+        later.iter().flat_map(|x| &x.0).any(|x| {
+            x.expr
+                .split(|c: char| !(c.is_alphanumeric() || c == '_'))
+                .any(|word| word == ident)
+        })
+    }
+
+    /// The trailing expression is a single identifier, such as `a`.
+    fn trailing_is_ident(&self) -> bool {
+        self.0
+            .last()
+            .map(|x| {
+                let expr = x.expr.trim();
+                !expr.is_empty()
+                    && !expr.starts_with(|c: char| c.is_ascii_digit())
+                    && expr.chars().all(|c| c.is_alphanumeric() || c == '_')
+            })
+            .unwrap_or(false)
+    }
 }
 
 /// Construct a single string containing all the source code in `mods_map`.
@@ -191,9 +272,19 @@ pub fn construct_source_code<'a>(
 
     let mut contents = String::with_capacity(cap);
 
+    if linking_config.std_mode == linking::StdMode::NoStd {
+        contents += NO_STD_HEADER;
+    }
+
+    if auto_std_imports(linking_config) {
+        contents += STD_IMPORTS_MOD;
+    }
+
     // add in external crates
-    for external in linking_config.external_libs.iter() {
-        external.construct_code_str(&mut contents);
+    if linking_config.auto_extern {
+        for external in linking_config.external_libs.iter() {
+            external.construct_code_str(&mut contents);
+        }
     }
 
     // do the lib first
@@ -267,21 +358,78 @@ pub fn construct_source_code<'a>(
     (contents, map)
 }
 
-/// Constructs the evaluation function name given the mod sequence path.
+/// Constructs the evaluation function name given the mod sequence path, the prefix, and the
+/// function name (see [`LinkingConfiguration::eval_fn_prefix`] and
+/// [`LinkingConfiguration::eval_fn_name`]).
 /// Appends to the buffer.
-pub fn eval_fn_name<S: AsRef<str>>(mod_path: &[S], buf: &mut String) {
-    buf.push('_');
+pub fn eval_fn_name<S: AsRef<str>>(mod_path: &[S], prefix: &str, name: &str, buf: &mut String) {
+    buf.push_str(prefix);
     for p in mod_path {
         buf.push_str(p.as_ref());
         buf.push('_');
     }
-    buf.push_str("intern_eval"); // 11 len
+    buf.push_str(name);
+}
+
+/// Crate attributes and crates for [`StdMode::NoStd`](linking::StdMode::NoStd).
+///
+/// `std` is declared so the generated code can refer to it, it is not in the prelude.
+const NO_STD_HEADER: &str = "#![no_std]\nextern crate alloc;\nextern crate std;\n";
+
+/// The module of common `std` items imported when
+/// [`auto_std_imports`](LinkingConfiguration::auto_std_imports) is enabled.
+const STD_IMPORTS_MOD: &str = "mod __papyrus_std_imports { pub use std::{cell::{Cell, RefCell}, \
+collections::{BTreeMap, HashMap, HashSet, VecDeque}, rc::Rc, sync::Arc, time::Duration}; }\n";
+
+/// The glob import of [`STD_IMPORTS_MOD`] added to each module.
+///
+/// Being a glob import, user defined items and imports of the same name take precedence.
+const STD_IMPORTS_USE: &str = "#[allow(unused_imports)]\nuse crate::__papyrus_std_imports::*;\n";
+
+/// The common `std` items are imported, which requires the standard library.
+fn auto_std_imports(linking_config: &LinkingConfiguration) -> bool {
+    linking_config.auto_std_imports && linking_config.std_mode == linking::StdMode::Std
+}
+
+/// Items defined in the evaluation function which convert the result into a `Kserd`.
+///
+/// Types implementing `ToKserd` are converted using it, then references (such as `&a[1..]`) whose
+/// owned type implements `ToKserd` are converted through `ToOwned`. Otherwise types implementing
+/// `Debug` are converted into a string of the pretty `Debug` output, then types implementing
+/// `Display` (such as `Box<dyn Display>`) into a string of the `Display` output. Any other type,
+/// such as a closure, is converted into a string of its type name. This uses autoref
+/// specialisation, the result is wrapped and the method is called on `&&&&&__PapyrusOut`.
+const OUT_KSERD_ITEMS: &str = "struct __PapyrusOut<T>(std::cell::Cell<Option<T>>); \
+trait __PapyrusViaKserd { fn out(&self) -> kserd::Kserd<'static>; } \
+impl<'a, T: kserd::ToKserd<'a>> __PapyrusViaKserd for &&&&__PapyrusOut<T> { fn out(&self) -> kserd::Kserd<'static> { kserd::ToKserd::into_kserd(self.0.take().unwrap()).unwrap().into_owned() } } \
+trait __PapyrusViaOwned { fn out(&self) -> kserd::Kserd<'static>; } \
+impl<'a, 'r, T: ?Sized + std::borrow::ToOwned> __PapyrusViaOwned for &&&__PapyrusOut<&'r T> where T::Owned: kserd::ToKserd<'a> { fn out(&self) -> kserd::Kserd<'static> { kserd::ToKserd::into_kserd(std::borrow::ToOwned::to_owned(self.0.take().unwrap())).unwrap().into_owned() } } \
+trait __PapyrusViaDebug { fn out(&self) -> kserd::Kserd<'static>; } \
+impl<T: std::fmt::Debug> __PapyrusViaDebug for &&__PapyrusOut<T> { fn out(&self) -> kserd::Kserd<'static> { kserd::Kserd::new_string(std::format!(\"{:#?}\", self.0.take().unwrap())) } } \
+trait __PapyrusViaDisplay { fn out(&self) -> kserd::Kserd<'static>; } \
+impl<T: std::fmt::Display> __PapyrusViaDisplay for &__PapyrusOut<T> { fn out(&self) -> kserd::Kserd<'static> { kserd::Kserd::new_string(std::format!(\"{}\", self.0.take().unwrap())) } } \
+trait __PapyrusViaTypeName { fn out(&self) -> kserd::Kserd<'static>; } \
+impl<T> __PapyrusViaTypeName for __PapyrusOut<T> { fn out(&self) -> kserd::Kserd<'static> { kserd::Kserd::new_string(std::format!(\"<{}>\", std::any::type_name::<T>())) } }\n";
+
+/// Splits the result of an evaluation function into the result and its type name.
+///
+/// When there are statements, the evaluation function returns a tuple of the type name and the
+/// result. If the `Kserd` is not in this shape it is returned as is.
+pub(crate) fn split_out_type(kserd: Kserd<'static>) -> (Kserd<'static>, Option<String>) {
+    match kserd.val {
+        Value::Tuple(mut v) if v.len() == 2 => {
+            let value = v.pop().expect("length is two");
+            let ty = v.pop().and_then(|x| x.str().map(ToString::to_string));
+            (value, ty)
+        }
+        val => (Kserd { id: kserd.id, val }, None),
+    }
 }
 
 /// Calculates the length of the evaluation function name.
 /// Used for performance.
-fn eval_fn_name_length<S: AsRef<str>>(mod_path: &[S]) -> usize {
-    12 + mod_path.iter().map(|x| x.as_ref().len() + 1).sum::<usize>()
+fn eval_fn_name_length<S: AsRef<str>>(mod_path: &[S], prefix: &str, name: &str) -> usize {
+    prefix.len() + name.len() + mod_path.iter().map(|x| x.as_ref().len() + 1).sum::<usize>()
 }
 
 /// Transforms a path into a vector of components.
@@ -321,8 +469,18 @@ fn calc_capacity<'a>(
     let mut map =
         HashMap::with_capacity_and_hasher(mods_map.len(), fxhash::FxBuildHasher::default());
 
-    for external in linking_config.external_libs.iter() {
-        cap += external.construct_code_str_length();
+    if linking_config.std_mode == linking::StdMode::NoStd {
+        cap += NO_STD_HEADER.len();
+    }
+
+    if auto_std_imports(linking_config) {
+        cap += STD_IMPORTS_MOD.len();
+    }
+
+    if linking_config.auto_extern {
+        for external in linking_config.external_libs.iter() {
+            cap += external.construct_code_str_length();
+        }
     }
 
     // do the lib first
@@ -406,6 +564,11 @@ fn append_buffer<S: AsRef<str>>(
         buf.push('\n');
     }
 
+    // glob import the common std items, explicit imports and items shadow these
+    if auto_std_imports(linking_config) {
+        buf.push_str(STD_IMPORTS_USE);
+    }
+
     // inject static files links
     for f in static_files
         .iter()
@@ -418,23 +581,51 @@ fn append_buffer<S: AsRef<str>>(
     }
 
     // wrap stmts
-    buf.push_str("#[no_mangle]\npub extern \"C\" fn "); // 31 len
-    eval_fn_name(mod_path, buf);
+    // uses the Rust ABI to match compile::execute::DataFunc, panics may unwind out of it
+    buf.push_str("#[no_mangle]\npub fn "); // 20 len
+    eval_fn_name(
+        mod_path,
+        &linking_config.eval_fn_prefix,
+        &linking_config.eval_fn_name,
+        buf,
+    );
     buf.push('(');
     linking_config.construct_fn_args(buf);
     buf.push_str(") -> kserd::Kserd<'static> {\n"); // 29 len
 
     // add stmts
-    let c = src_code.stmts.len();
-    if c >= 1 {
-        // only add statements if more than zero!
+    // the last group with a result is returned
+    let ret = src_code.stmts.iter().rposition(StmtGrp::has_result);
+    if let Some(r) = ret {
+        // only add statements if there is a result!
+        // results are numbered separately to the groups, released groups have no out#
+        let mut out_num = 0;
         src_code.stmts.iter().enumerate().for_each(|(i, x)| {
-            x.assign_let_binding(i, buf);
+            // identifiers used later get borrowed, otherwise they are moved into out#
+            let borrow = x.trailing_used_by(&src_code.stmts[i + 1..]);
+            x.assign_let_binding(&linking_config.out_prefix, out_num, borrow, buf);
             buf.push('\n');
+            out_num += x.has_result() as usize;
         });
-        buf.push_str("kserd::ToKserd::into_kserd(out");
-        buf.push_str(&c.saturating_sub(1).to_string());
-        buf.push_str(").unwrap().into_owned()\n");
+        // flush stdout, the library has its own buffer which must be written before returning
+        buf.push_str("std::io::Write::flush(&mut std::io::stdout()).ok();\n");
+        // capture the type name of the result, see split_out_type
+        // a borrowed identifier records the type of the binding rather than the reference
+        let n = (out_num - 1).to_string();
+        buf.push_str(
+            "let out_ty = { fn ty<T>(_: &T) -> &'static str { std::any::type_name::<T>() } ty(",
+        );
+        if !src_code.stmts[r].trailing_used_by(&src_code.stmts[r + 1..]) {
+            buf.push('&');
+        }
+        buf.push_str(&linking_config.out_prefix);
+        buf.push_str(&n);
+        buf.push_str(") };\n");
+        buf.push_str(OUT_KSERD_ITEMS);
+        buf.push_str("kserd::Kserd::new(kserd::Value::Tuple(std::vec![kserd::Kserd::new_str(out_ty), (&&&&&__PapyrusOut(std::cell::Cell::new(Some(");
+        buf.push_str(&linking_config.out_prefix);
+        buf.push_str(&n);
+        buf.push_str(")))).out()]))\n");
     } else {
         buf.push_str("kserd::Kserd::new_str(\"no statements\")\n");
     }
@@ -465,6 +656,10 @@ fn append_buffer_length<S: AsRef<str>>(
         cap += linking_config.persistent_module_code.len() + 1;
     }
 
+    if auto_std_imports(linking_config) {
+        cap += STD_IMPORTS_USE.len();
+    }
+
     // static files -- use crate::#;\n
     cap += static_files
         .iter()
@@ -474,20 +669,40 @@ fn append_buffer_length<S: AsRef<str>>(
         .sum::<usize>();
 
     // wrap stmts
-    cap += 31 + eval_fn_name_length(mod_path) + 1 + linking_config.construct_fn_args_length() + 29;
+    let fn_name_len = eval_fn_name_length(
+        mod_path,
+        &linking_config.eval_fn_prefix,
+        &linking_config.eval_fn_name,
+    );
+    cap += 20 + fn_name_len + 1 + linking_config.construct_fn_args_length() + 29;
 
     // add stmts
-    let c = src_code.stmts.len();
-    let (add, rng) = if c >= 1 {
+    let ret = src_code.stmts.iter().rposition(StmtGrp::has_result);
+    let (add, rng) = if let Some(r) = ret {
+        let n_len = (src_code.out_count() - 1).to_string().len();
+        let mut out_num = 0;
         let stmts = src_code
             .stmts
             .iter()
             .enumerate()
-            .map(|(i, x)| x.assign_let_binding_length(i) + 1)
-            .sum::<usize>();
-        let return_str = 30 // kserd::ToKserd::into_kserd(out
-            + c.saturating_sub(1).to_string().len()
-            + 24; // ).unwrap().into_owned()\n
+            .map(|(i, x)| {
+                let borrow = x.trailing_used_by(&src_code.stmts[i + 1..]);
+                let len = x.assign_let_binding_length(&linking_config.out_prefix, out_num, borrow);
+                out_num += x.has_result() as usize;
+                len + 1
+            })
+            .sum::<usize>()
+            + 52 // std::io::Write::flush(&mut std::io::stdout()).ok();\n
+            + 82 // let out_ty = { fn ty<T>(_: &T) -> &'static str { std::any::type_name::<T>() } ty(&
+            - src_code.stmts[r].trailing_used_by(&src_code.stmts[r + 1..]) as usize // borrowed identifiers are not borrowed again
+            + linking_config.out_prefix.len()
+            + n_len
+            + 5 // ) };\n
+            + OUT_KSERD_ITEMS.len();
+        let return_str = 124 // kserd::Kserd::new(kserd::Value::Tuple(std::vec![kserd::Kserd::new_str(out_ty), (&&&&&__PapyrusOut(std::cell::Cell::new(Some(
+            + linking_config.out_prefix.len()
+            + n_len
+            + 14; // )))).out()]))\n
 
         (
             stmts + return_str,
@@ -863,14 +1078,14 @@ mod tests {
 
     #[test]
     fn assign_let_binding_test() {
-        let mut grp = StmtGrp(vec![]);
+        let mut grp = StmtGrp(vec![], false);
 
         let mut s = String::new();
-        grp.assign_let_binding(0, &mut s);
+        grp.assign_let_binding("out", 0, false, &mut s);
 
         let ans = "";
         assert_eq!(&s, ans);
-        assert_eq!(grp.assign_let_binding_length(0), ans.len());
+        assert_eq!(grp.assign_let_binding_length("out", 0, false), ans.len());
 
         grp.0.push(Statement {
             expr: "a".to_string(),
@@ -878,11 +1093,11 @@ mod tests {
         });
 
         let mut s = String::new();
-        grp.assign_let_binding(0, &mut s);
+        grp.assign_let_binding("out", 0, false, &mut s);
 
         let ans = "let out0 = a;";
         assert_eq!(&s, ans);
-        assert_eq!(grp.assign_let_binding_length(0), ans.len());
+        assert_eq!(grp.assign_let_binding_length("out", 0, false), ans.len());
 
         grp.0.push(Statement {
             expr: "b".to_string(),
@@ -890,18 +1105,82 @@ mod tests {
         });
 
         let mut s = String::new();
-        grp.assign_let_binding(0, &mut s);
+        grp.assign_let_binding("out", 0, false, &mut s);
 
         let ans = "a\nlet out0 = b;";
         assert_eq!(&s, ans);
-        assert_eq!(grp.assign_let_binding_length(0), ans.len());
+        assert_eq!(grp.assign_let_binding_length("out", 0, false), ans.len());
 
         let mut s = String::new();
-        grp.assign_let_binding(100, &mut s);
+        grp.assign_let_binding("out", 100, false, &mut s);
 
         let ans = "a\nlet out100 = b;";
         assert_eq!(&s, ans);
-        assert_eq!(grp.assign_let_binding_length(100), ans.len());
+        assert_eq!(grp.assign_let_binding_length("out", 100, false), ans.len());
+
+        // borrow identifiers
+        let mut s = String::new();
+        grp.assign_let_binding("out", 0, true, &mut s);
+
+        let ans = "a\nlet out0 = &b;";
+        assert_eq!(&s, ans);
+        assert_eq!(grp.assign_let_binding_length("out", 0, true), ans.len());
+
+        grp.0.push(Statement {
+            expr: "b.len()".to_string(),
+            semi: false,
+        });
+
+        let mut s = String::new();
+        grp.assign_let_binding("out", 0, true, &mut s);
+
+        let ans = "a\nb\nlet out0 = b.len();";
+        assert_eq!(&s, ans);
+        assert_eq!(grp.assign_let_binding_length("out", 0, true), ans.len());
+
+        let mut s = String::new();
+        grp.assign_let_binding("res", 10, true, &mut s);
+
+        let ans = "a\nb\nlet res10 = b.len();";
+        assert_eq!(&s, ans);
+        assert_eq!(grp.assign_let_binding_length("res", 10, true), ans.len());
+
+        // released, so no result
+        grp.0.push(Statement {
+            expr: "let c = 1".to_string(),
+            semi: true,
+        });
+        assert!(grp.has_result());
+        grp.1 = true;
+        assert!(!grp.has_result());
+
+        let mut s = String::new();
+        grp.assign_let_binding("out", 0, true, &mut s);
+
+        let ans = "a\nb\nb.len()\nlet c = 1;\n";
+        assert_eq!(&s, ans);
+        assert_eq!(grp.assign_let_binding_length("out", 0, true), ans.len());
+    }
+
+    #[test]
+    fn trailing_used_by_test() {
+        let grp = |exprs: &[&str]| {
+            let stmts = exprs
+                .iter()
+                .map(|x| Statement {
+                    expr: x.to_string(),
+                    semi: false,
+                })
+                .collect();
+            StmtGrp(stmts, false)
+        };
+
+        let v = grp(&["let mut v = vec![]", "v"]);
+        assert!(!v.trailing_used_by(&[]));
+        assert!(v.trailing_used_by(&[grp(&["v.push(1)", "v"])]));
+        assert!(v.trailing_used_by(&[grp(&["1"]), grp(&["let b = &v[0]", "b"])]));
+        assert!(!v.trailing_used_by(&[grp(&["vec.len()"])]));
+        assert!(!grp(&["v.len()"]).trailing_used_by(&[grp(&["v"])]));
     }
 
     #[test]
@@ -924,13 +1203,13 @@ mod tests {
             append_buffer_length(&src_code, &mod_path, &linking_config, &StaticFiles::new());
 
         let ans = r##"#[no_mangle]
-pub extern "C" fn _intern_eval() -> kserd::Kserd<'static> {
+pub fn _intern_eval() -> kserd::Kserd<'static> {
 kserd::Kserd::new_str("no statements")
 }
 "##;
         assert_eq!(&s, ans);
         assert_eq!(len, ans.len());
-        assert_eq!(rng, 73..111);
+        assert_eq!(rng, 62..100);
         assert_eq!(&ans[rng], r#"kserd::Kserd::new_str("no statements")"#);
 
         // alter mod path
@@ -948,13 +1227,13 @@ kserd::Kserd::new_str("no statements")
             append_buffer_length(&src_code, &mod_path, &linking_config, &StaticFiles::new());
 
         let ans = r##"#[no_mangle]
-pub extern "C" fn _some_path_intern_eval() -> kserd::Kserd<'static> {
+pub fn _some_path_intern_eval() -> kserd::Kserd<'static> {
 kserd::Kserd::new_str("no statements")
 }
 "##;
         assert_eq!(&s, ans);
         assert_eq!(len, ans.len());
-        assert_eq!(rng, 83..121);
+        assert_eq!(rng, 72..110);
         assert_eq!(&ans[rng], r#"kserd::Kserd::new_str("no statements")"#);
 
         // alter the linking config
@@ -975,13 +1254,13 @@ kserd::Kserd::new_str("no statements")
             append_buffer_length(&src_code, &mod_path, &linking_config, &StaticFiles::new());
 
         let ans = r##"#[no_mangle]
-pub extern "C" fn _some_path_intern_eval(app_data: &String) -> kserd::Kserd<'static> {
+pub fn _some_path_intern_eval(app_data: &String) -> kserd::Kserd<'static> {
 kserd::Kserd::new_str("no statements")
 }
 "##;
         assert_eq!(&s, ans);
         assert_eq!(len, ans.len());
-        assert_eq!(rng, 100..138);
+        assert_eq!(rng, 89..127);
         assert_eq!(&ans[rng], r#"kserd::Kserd::new_str("no statements")"#);
 
         // add an item and new input
@@ -1000,7 +1279,7 @@ kserd::Kserd::new_str("no statements")
             append_buffer_length(&src_code, &mod_path, &linking_config, &StaticFiles::new());
 
         let ans = r##"#[no_mangle]
-pub extern "C" fn _some_path_intern_eval(app_data: &String) -> kserd::Kserd<'static> {
+pub fn _some_path_intern_eval(app_data: &String) -> kserd::Kserd<'static> {
 kserd::Kserd::new_str("no statements")
 }
 fn a() {}
@@ -1008,30 +1287,36 @@ fn b() {}
 "##;
         assert_eq!(&s, ans);
         assert_eq!(len, ans.len());
-        assert_eq!(rng, 100..138);
+        assert_eq!(rng, 89..127);
         assert_eq!(&ans[rng], r#"kserd::Kserd::new_str("no statements")"#);
 
         // add stmts
-        src_code.stmts.push(StmtGrp(vec![
-            Statement {
-                expr: "let a = 1".to_string(),
-                semi: true,
-            },
-            Statement {
-                expr: "b".to_string(),
-                semi: false,
-            },
-        ]));
-        src_code.stmts.push(StmtGrp(vec![
-            Statement {
-                expr: "let c = 2".to_string(),
-                semi: true,
-            },
-            Statement {
-                expr: "d".to_string(),
-                semi: false,
-            },
-        ]));
+        src_code.stmts.push(StmtGrp(
+            vec![
+                Statement {
+                    expr: "let a = 1".to_string(),
+                    semi: true,
+                },
+                Statement {
+                    expr: "b".to_string(),
+                    semi: false,
+                },
+            ],
+            false,
+        ));
+        src_code.stmts.push(StmtGrp(
+            vec![
+                Statement {
+                    expr: "let c = b".to_string(),
+                    semi: true,
+                },
+                Statement {
+                    expr: "d".to_string(),
+                    semi: false,
+                },
+            ],
+            false,
+        ));
 
         // throw in a curve ball with an up top placement of an item
         src_code
@@ -1054,25 +1339,165 @@ fn b() {}
         let (len, rng) =
             append_buffer_length(&src_code, &mod_path, &linking_config, &StaticFiles::new());
 
-        let ans = r##"#![feature(UP_TOP)]
+        let ans = format!(
+            r##"#![feature(UP_TOP)]
 some-injected-persistent-code
 #[no_mangle]
-pub extern "C" fn _some_path_intern_eval(app_data: &String) -> kserd::Kserd<'static> {
+pub fn _some_path_intern_eval(app_data: &String) -> kserd::Kserd<'static> {{
 let a = 1;
-let out0 = b;
-let c = 2;
+let out0 = &b;
+let c = b;
 let out1 = d;
-kserd::ToKserd::into_kserd(out1).unwrap().into_owned()
-}
-fn a() {}
-fn b() {}
-"##;
-        assert_eq!(&s, ans);
+std::io::Write::flush(&mut std::io::stdout()).ok();
+let out_ty = {{ fn ty<T>(_: &T) -> &'static str {{ std::any::type_name::<T>() }} ty(&out1) }};
+{}kserd::Kserd::new(kserd::Value::Tuple(std::vec![kserd::Kserd::new_str(out_ty), (&&&&&__PapyrusOut(std::cell::Cell::new(Some(out1)))).out()]))
+}}
+fn a() {{}}
+fn b() {{}}
+"##,
+            OUT_KSERD_ITEMS
+        );
+        assert_eq!(&s, &ans);
+        assert_eq!(len, ans.len());
+        assert_eq!(rng, 1486..1626);
+        assert_eq!(
+            &ans[rng],
+            "kserd::Kserd::new(kserd::Value::Tuple(std::vec![kserd::Kserd::new_str(out_ty), (&&&&&__PapyrusOut(std::cell::Cell::new(Some(out1)))).out()]))"
+        );
+
+        // alter the out prefix
+        let linking_config = linking_config.with_out_prefix("res").unwrap();
+
+        let mut s = String::new();
+        append_buffer(
+            &src_code,
+            &mod_path,
+            &linking_config,
+            &StaticFiles::new(),
+            &mut s,
+        );
+        let (len, rng) =
+            append_buffer_length(&src_code, &mod_path, &linking_config, &StaticFiles::new());
+
+        assert_eq!(len, s.len());
+        assert!(s.contains("\nlet res0 = &b;\n"));
+        assert!(s.contains("\nlet res1 = d;\n"));
+        assert!(s.contains("ty(&res1)"));
+        assert!(!s.contains("out0") && !s.contains("out1"));
+        assert_eq!(
+            &s[rng],
+            "kserd::Kserd::new(kserd::Value::Tuple(std::vec![kserd::Kserd::new_str(out_ty), (&&&&&__PapyrusOut(std::cell::Cell::new(Some(res1)))).out()]))"
+        );
+    }
+
+    #[test]
+    fn construct_ref_test() {
+        use linking::LinkingConfiguration;
+
+        let mut src_code = SourceCode::default();
+        let mod_path: &[&str] = &[];
+        let linking_config = LinkingConfiguration::default();
+
+        src_code.stmts.push(StmtGrp(
+            vec![
+                Statement {
+                    expr: "let a = vec![1, 2, 3]".to_string(),
+                    semi: true,
+                },
+                Statement {
+                    expr: "&a[1..]".to_string(),
+                    semi: false,
+                },
+            ],
+            false,
+        ));
+
+        let mut s = String::new();
+        append_buffer(
+            &src_code,
+            &mod_path,
+            &linking_config,
+            &StaticFiles::new(),
+            &mut s,
+        );
+        let (len, rng) =
+            append_buffer_length(&src_code, &mod_path, &linking_config, &StaticFiles::new());
+
+        let ans = format!(
+            r##"#[no_mangle]
+pub fn _intern_eval() -> kserd::Kserd<'static> {{
+let a = vec![1, 2, 3];
+let out0 = &a[1..];
+std::io::Write::flush(&mut std::io::stdout()).ok();
+let out_ty = {{ fn ty<T>(_: &T) -> &'static str {{ std::any::type_name::<T>() }} ty(&out0) }};
+{}kserd::Kserd::new(kserd::Value::Tuple(std::vec![kserd::Kserd::new_str(out_ty), (&&&&&__PapyrusOut(std::cell::Cell::new(Some(out0)))).out()]))
+}}
+"##,
+            OUT_KSERD_ITEMS
+        );
+        assert_eq!(&s, &ans);
+        assert_eq!(len, ans.len());
+        assert_eq!(rng, 1401..1541);
+        assert_eq!(
+            &ans[rng],
+            "kserd::Kserd::new(kserd::Value::Tuple(std::vec![kserd::Kserd::new_str(out_ty), (&&&&&__PapyrusOut(std::cell::Cell::new(Some(out0)))).out()]))"
+        );
+    }
+
+    #[test]
+    fn construct_released_test() {
+        use linking::LinkingConfiguration;
+
+        let mut src_code = SourceCode::default();
+        let mod_path: &[&str] = &[];
+        let linking_config = LinkingConfiguration::default();
+
+        src_code.stmts.push(StmtGrp(
+            vec![Statement {
+                expr: "let a = 1".to_string(),
+                semi: true,
+            }],
+            true,
+        ));
+        src_code.stmts.push(StmtGrp(
+            vec![Statement {
+                expr: "a + 1".to_string(),
+                semi: false,
+            }],
+            false,
+        ));
+        assert_eq!(src_code.out_count(), 1);
+
+        let mut s = String::new();
+        append_buffer(
+            &src_code,
+            &mod_path,
+            &linking_config,
+            &StaticFiles::new(),
+            &mut s,
+        );
+        let (len, rng) =
+            append_buffer_length(&src_code, &mod_path, &linking_config, &StaticFiles::new());
+
+        // a released group is not numbered, the result is bound to out0
+        let ans = format!(
+            r##"#[no_mangle]
+pub fn _intern_eval() -> kserd::Kserd<'static> {{
+let a = 1;
+let out0 = a + 1;
+std::io::Write::flush(&mut std::io::stdout()).ok();
+let out_ty = {{ fn ty<T>(_: &T) -> &'static str {{ std::any::type_name::<T>() }} ty(&out0) }};
+{}kserd::Kserd::new(kserd::Value::Tuple(std::vec![kserd::Kserd::new_str(out_ty), (&&&&&__PapyrusOut(std::cell::Cell::new(Some(out0)))).out()]))
+}}
+"##,
+            OUT_KSERD_ITEMS
+        );
+        assert_eq!(&s, &ans);
         assert_eq!(len, ans.len());
-        assert_eq!(rng, 200..254);
+        assert_eq!(rng, 1387..1527);
         assert_eq!(
             &ans[rng],
-            "kserd::ToKserd::into_kserd(out1).unwrap().into_owned()"
+            "kserd::Kserd::new(kserd::Value::Tuple(std::vec![kserd::Kserd::new_str(out_ty), (&&&&&__PapyrusOut(std::cell::Cell::new(Some(out0)))).out()]))"
         );
     }
 
@@ -1096,34 +1521,34 @@ fn b() {}
         let (s, map) = construct_source_code(&map, &linking, &StaticFiles::new());
 
         let ans = r##"#[no_mangle]
-pub extern "C" fn _lib_intern_eval() -> kserd::Kserd<'static> {
+pub fn _lib_intern_eval() -> kserd::Kserd<'static> {
 kserd::Kserd::new_str("no statements")
 }
 mod foo {
 #[no_mangle]
-pub extern "C" fn _foo_intern_eval() -> kserd::Kserd<'static> {
+pub fn _foo_intern_eval() -> kserd::Kserd<'static> {
 kserd::Kserd::new_str("no statements")
 }
 mod bar {
 #[no_mangle]
-pub extern "C" fn _foo_bar_intern_eval() -> kserd::Kserd<'static> {
+pub fn _foo_bar_intern_eval() -> kserd::Kserd<'static> {
 kserd::Kserd::new_str("no statements")
 }
 }}
 mod test {
 #[no_mangle]
-pub extern "C" fn _test_intern_eval() -> kserd::Kserd<'static> {
+pub fn _test_intern_eval() -> kserd::Kserd<'static> {
 kserd::Kserd::new_str("no statements")
 }
 mod inner {
 #[no_mangle]
-pub extern "C" fn _test_inner_intern_eval() -> kserd::Kserd<'static> {
+pub fn _test_inner_intern_eval() -> kserd::Kserd<'static> {
 kserd::Kserd::new_str("no statements")
 }
 }
 mod inner2 {
 #[no_mangle]
-pub extern "C" fn _test_inner2_intern_eval() -> kserd::Kserd<'static> {
+pub fn _test_inner2_intern_eval() -> kserd::Kserd<'static> {
 kserd::Kserd::new_str("no statements")
 }
 }}"##;
@@ -1156,6 +1581,77 @@ kserd::Kserd::new_str("no statements")
         );
     }
 
+    #[test]
+    fn construct_no_std_test() {
+        let mut linking = LinkingConfiguration::default();
+        linking.std_mode = linking::StdMode::NoStd;
+        let map = vec![("lib".into(), SourceCode::default())]
+            .into_iter()
+            .collect();
+
+        let (s, map) = construct_source_code(&map, &linking, &StaticFiles::new());
+
+        let ans = r##"#![no_std]
+extern crate alloc;
+extern crate std;
+#[no_mangle]
+pub fn _lib_intern_eval() -> kserd::Kserd<'static> {
+kserd::Kserd::new_str("no statements")
+}
+"##;
+        assert_eq!(&s, ans);
+        assert_eq!(
+            &ans[map.get(Path::new("lib")).unwrap().clone()],
+            r#"kserd::Kserd::new_str("no statements")"#
+        );
+    }
+
+    #[test]
+    fn construct_auto_std_imports_test() {
+        let mut linking = LinkingConfiguration::default();
+        linking.auto_std_imports = true;
+        let map = vec![
+            ("lib".into(), SourceCode::default()),
+            ("a".into(), SourceCode::default()),
+        ]
+        .into_iter()
+        .collect();
+
+        let (s, map) = construct_source_code(&map, &linking, &StaticFiles::new());
+
+        let ans = r##"mod __papyrus_std_imports { pub use std::{cell::{Cell, RefCell}, collections::{BTreeMap, HashMap, HashSet, VecDeque}, rc::Rc, sync::Arc, time::Duration}; }
+#[allow(unused_imports)]
+use crate::__papyrus_std_imports::*;
+#[no_mangle]
+pub fn _lib_intern_eval() -> kserd::Kserd<'static> {
+kserd::Kserd::new_str("no statements")
+}
+mod a {
+#[allow(unused_imports)]
+use crate::__papyrus_std_imports::*;
+#[no_mangle]
+pub fn _a_intern_eval() -> kserd::Kserd<'static> {
+kserd::Kserd::new_str("no statements")
+}
+}"##;
+        assert_eq!(&s, ans);
+        assert_eq!(
+            &ans[map.get(Path::new("a")).unwrap().clone()],
+            r#"kserd::Kserd::new_str("no statements")"#
+        );
+
+        // not available without std
+        linking.std_mode = linking::StdMode::NoStd;
+        let (s, _) = construct_source_code(
+            &vec![("lib".into(), SourceCode::default())]
+                .into_iter()
+                .collect(),
+            &linking,
+            &StaticFiles::new(),
+        );
+        assert!(!s.contains("__papyrus_std_imports"));
+    }
+
     #[test]
     fn eval_fn_name_test() {
         let path: Vec<String> = ["some", "lib", "module", "path"]
@@ -1163,18 +1659,73 @@ kserd::Kserd::new_str("no statements")
             .map(|x| x.to_string())
             .collect();
         let mut s = String::new();
-        eval_fn_name(&path, &mut s);
+        eval_fn_name(&path, "_", "intern_eval", &mut s);
 
         let ans = "_some_lib_module_path_intern_eval";
         assert_eq!(&s, ans);
-        assert_eq!(eval_fn_name_length(&path), ans.len());
+        assert_eq!(eval_fn_name_length(&path, "_", "intern_eval"), ans.len());
 
         let mut s = String::new();
-        eval_fn_name::<&str>(&[], &mut s);
+        eval_fn_name::<&str>(&[], "_", "intern_eval", &mut s);
 
         let ans = "_intern_eval";
         assert_eq!(&s, ans);
-        assert_eq!(eval_fn_name_length::<&str>(&[]), ans.len());
+        assert_eq!(
+            eval_fn_name_length::<&str>(&[], "_", "intern_eval"),
+            ans.len()
+        );
+
+        let mut s = String::new();
+        eval_fn_name(&path, "_", "papyrus_eval", &mut s);
+
+        let ans = "_some_lib_module_path_papyrus_eval";
+        assert_eq!(&s, ans);
+        assert_eq!(eval_fn_name_length(&path, "_", "papyrus_eval"), ans.len());
+
+        let mut s = String::new();
+        eval_fn_name(&path, "__papyrus_", "eval", &mut s);
+
+        let ans = "__papyrus_some_lib_module_path_eval";
+        assert_eq!(&s, ans);
+        assert_eq!(eval_fn_name_length(&path, "__papyrus_", "eval"), ans.len());
+    }
+
+    #[test]
+    fn construct_eval_fn_name_test() {
+        let src_code = SourceCode::default();
+        let mod_path = ["some".to_string(), "path".to_string()];
+        let linking_config = LinkingConfiguration::default()
+            .with_eval_fn_name("papyrus_eval")
+            .unwrap();
+
+        let mut s = String::new();
+        append_buffer(
+            &src_code,
+            &mod_path,
+            &linking_config,
+            &StaticFiles::new(),
+            &mut s,
+        );
+        let (len, _) =
+            append_buffer_length(&src_code, &mod_path, &linking_config, &StaticFiles::new());
+
+        assert!(s.contains("pub fn _some_path_papyrus_eval() -> "));
+        assert_eq!(len, s.len());
+
+        let linking_config = linking_config.with_eval_fn_prefix("__papyrus_").unwrap();
+        let mut s = String::new();
+        append_buffer(
+            &src_code,
+            &mod_path,
+            &linking_config,
+            &StaticFiles::new(),
+            &mut s,
+        );
+        let (len, _) =
+            append_buffer_length(&src_code, &mod_path, &linking_config, &StaticFiles::new());
+
+        assert!(s.contains("pub fn __papyrus_some_path_papyrus_eval() -> "));
+        assert_eq!(len, s.len());
     }
 
     #[test]
@@ -1212,7 +1763,7 @@ kserd::Kserd::new_str("no statements")
 
         let ans = r##"Up Top
 #[no_mangle]
-pub extern "C" fn _lib_intern_eval() -> kserd::Kserd<'static> {
+pub fn _lib_intern_eval() -> kserd::Kserd<'static> {
 kserd::Kserd::new_str("no statements")
 }
 Test1
@@ -1335,21 +1886,21 @@ Test1
         let ans = r##"mod bar2;
 mod foo2;
 #[no_mangle]
-pub extern "C" fn _lib_intern_eval() -> kserd::Kserd<'static> {
+pub fn _lib_intern_eval() -> kserd::Kserd<'static> {
 kserd::Kserd::new_str("no statements")
 }
 mod foo {
 use crate::bar2;
 use crate::foo2;
 #[no_mangle]
-pub extern "C" fn _foo_intern_eval() -> kserd::Kserd<'static> {
+pub fn _foo_intern_eval() -> kserd::Kserd<'static> {
 kserd::Kserd::new_str("no statements")
 }
 mod bar {
 use crate::bar2;
 use crate::foo2;
 #[no_mangle]
-pub extern "C" fn _foo_bar_intern_eval() -> kserd::Kserd<'static> {
+pub fn _foo_bar_intern_eval() -> kserd::Kserd<'static> {
 kserd::Kserd::new_str("no statements")
 }
 }}
@@ -1357,14 +1908,14 @@ mod test {
 use crate::bar2;
 use crate::foo2;
 #[no_mangle]
-pub extern "C" fn _test_intern_eval() -> kserd::Kserd<'static> {
+pub fn _test_intern_eval() -> kserd::Kserd<'static> {
 kserd::Kserd::new_str("no statements")
 }
 mod inner {
 use crate::bar2;
 use crate::foo2;
 #[no_mangle]
-pub extern "C" fn _test_inner_intern_eval() -> kserd::Kserd<'static> {
+pub fn _test_inner_intern_eval() -> kserd::Kserd<'static> {
 kserd::Kserd::new_str("no statements")
 }
 }
@@ -1372,7 +1923,7 @@ mod inner2 {
 use crate::bar2;
 use crate::foo2;
 #[no_mangle]
-pub extern "C" fn _test_inner2_intern_eval() -> kserd::Kserd<'static> {
+pub fn _test_inner2_intern_eval() -> kserd::Kserd<'static> {
 kserd::Kserd::new_str("no statements")
 }
 }}"##;
@@ -1408,16 +1959,19 @@ kserd::Kserd::new_str("no statements")
 
     #[test]
     fn test_stmtgrp_src_line() {
-        let grp = StmtGrp(vec![
-            Statement {
-                expr: "a".into(),
-                semi: false,
-            },
-            Statement {
-                expr: "b".into(),
-                semi: true,
-            },
-        ]);
+        let grp = StmtGrp(
+            vec![
+                Statement {
+                    expr: "a".into(),
+                    semi: false,
+                },
+                Statement {
+                    expr: "b".into(),
+                    semi: true,
+                },
+            ],
+            false,
+        );
         let s = grp.src_line();
         println!("{}", s);
         assert_eq!(&s, "a b;");
@@ -1481,7 +2035,7 @@ kserd::Kserd::new_str("no statements")
         let ans = r##"mod bar2;
 mod foo2;
 #[no_mangle]
-pub extern "C" fn _lib_intern_eval() -> kserd::Kserd<'static> {
+pub fn _lib_intern_eval() -> kserd::Kserd<'static> {
 kserd::Kserd::new_str("no statements")
 }
 "##;
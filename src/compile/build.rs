@@ -8,38 +8,92 @@ use std::{error, fmt};
 pub fn compile<P, F>(
     compile_dir: P,
     linking_config: &crate::linking::LinkingConfiguration,
-    mut stderr_line_cb: F,
+    stderr_line_cb: F,
 ) -> Result<PathBuf, CompilationError>
 where
     P: AsRef<Path>,
     F: FnMut(&str),
 {
     let compile_dir = compile_dir.as_ref();
-    let lib_file = compile_dir.join("target/debug/");
-    let lib_file = if cfg!(windows) {
-        lib_file.join(format!("{}.dll", LIBRARY_NAME))
-    } else if cfg!(target_os = "macos") {
-        lib_file.join(format!("lib{}.dylib", LIBRARY_NAME))
+    let lib_file = lib_file(compile_dir, linking_config.target.as_deref());
+
+    run_cargo(
+        compile_dir,
+        &compile_args(linking_config),
+        linking_config.offline,
+        stderr_line_cb,
+    )
+    .map(|_| lib_file)
+}
+
+/// Type check the code in the given compilation directory, without producing a library.
+///
+/// This only emits metadata (similar to `cargo check`), so is faster than [`compile`].
+pub fn check<P, F>(
+    compile_dir: P,
+    linking_config: &crate::linking::LinkingConfiguration,
+    stderr_line_cb: F,
+) -> Result<(), CompilationError>
+where
+    P: AsRef<Path>,
+    F: FnMut(&str),
+{
+    run_cargo(
+        compile_dir.as_ref(),
+        &check_args(linking_config),
+        linking_config.offline,
+        stderr_line_cb,
+    )
+}
+
+/// The command line which [`compile`] (or [`check`] if `check` is set) runs, such that it can be
+/// reproduced by hand.
+///
+/// `cargo` is run from within the compilation directory, the edition and crate type are set in its
+/// `Cargo.toml`.
+pub fn command_line<P: AsRef<Path>>(
+    compile_dir: P,
+    linking_config: &crate::linking::LinkingConfiguration,
+    check: bool,
+) -> String {
+    let args = if check {
+        check_args(linking_config)
     } else {
-        lib_file.join(format!("lib{}.so", LIBRARY_NAME))
+        compile_args(linking_config)
     };
 
-    let mut args = vec!["rustc".to_owned(), "--".to_owned(), "-Awarnings".to_owned()];
+    let mut cmd = format!(
+        "cd {} && cargo",
+        quote_arg(&compile_dir.as_ref().display().to_string())
+    );
+    for arg in &args {
+        cmd.push(' ');
+        cmd.push_str(&quote_arg(arg));
+    }
 
-    for external in linking_config.external_libs.iter() {
-        args.push("-L".to_owned());
-        args.push(format!("dependency={}", external.deps_path().display()));
-        args.push("--extern".to_owned());
-        args.push(format!(
-            "{}={}",
-            external.lib_name(),
-            external.lib_path().display()
-        ));
+    cmd
+}
+
+fn quote_arg(arg: &str) -> String {
+    if arg.is_empty() || arg.contains(|c: char| c.is_whitespace() || c == '"' || c == '\'') {
+        format!("{:?}", arg)
+    } else {
+        arg.to_owned()
     }
+}
 
+fn run_cargo<F>(
+    compile_dir: &Path,
+    args: &[String],
+    offline: bool,
+    mut stderr_line_cb: F,
+) -> Result<(), CompilationError>
+where
+    F: FnMut(&str),
+{
     let mut child = Command::new("cargo")
         .current_dir(compile_dir)
-        .args(&args)
+        .args(args)
         .stdout(Stdio::piped())
         .stderr(Stdio::piped())
         .spawn()
@@ -60,7 +114,9 @@ where
     match child.wait() {
         Ok(ex) => {
             if ex.success() {
-                Ok(lib_file)
+                Ok(())
+            } else if offline && is_offline_error(&stderr) {
+                Err(CompilationError::OfflineError(stderr))
             } else {
                 Err(CompilationError::CompileError(stderr))
             }
@@ -69,6 +125,102 @@ where
     }
 }
 
+/// Cargo failed because it could not access the network, it mentions the `--offline` flag when a
+/// crate is not available in the local cache.
+fn is_offline_error(stderr: &str) -> bool {
+    stderr.lines().any(|line| line.contains("--offline"))
+}
+
+/// The path of the compiled library.
+///
+/// If compiling for another target, the library is in a target specific folder and named
+/// according to the target's platform.
+fn lib_file(compile_dir: &Path, target: Option<&str>) -> PathBuf {
+    let (dir, windows, macos, wasm) = match target {
+        Some(t) => (
+            compile_dir.join("target").join(t).join("debug"),
+            t.contains("windows"),
+            t.contains("apple"),
+            t.starts_with("wasm"),
+        ),
+        None => (
+            compile_dir.join("target/debug/"),
+            cfg!(windows),
+            cfg!(target_os = "macos"),
+            false,
+        ),
+    };
+
+    if windows {
+        dir.join(format!("{}.dll", LIBRARY_NAME))
+    } else if macos {
+        dir.join(format!("lib{}.dylib", LIBRARY_NAME))
+    } else if wasm {
+        dir.join(format!("{}.wasm", LIBRARY_NAME))
+    } else {
+        dir.join(format!("lib{}.so", LIBRARY_NAME))
+    }
+}
+
+/// The arguments passed to `cargo`.
+fn compile_args(linking_config: &crate::linking::LinkingConfiguration) -> Vec<String> {
+    let mut args = vec!["rustc".to_owned()];
+
+    if let Some(target) = &linking_config.target {
+        args.push("--target".to_owned());
+        args.push(target.clone());
+    }
+
+    if linking_config.offline {
+        args.push("--offline".to_owned());
+    }
+
+    args.push("--".to_owned());
+    args.push("-Awarnings".to_owned());
+    args.push("-C".to_owned());
+    args.push(format!("debuginfo={}", linking_config.debuginfo));
+
+    for external in linking_config.external_libs.iter() {
+        args.push("-L".to_owned());
+        args.push(format!("dependency={}", external.deps_path().display()));
+        args.push("--extern".to_owned());
+        // without `extern crate lib as alias;` the alias is the name in scope
+        let name = match external.alias() {
+            Some(alias) if !linking_config.auto_extern => alias,
+            _ => external.lib_name(),
+        };
+        args.push(format!("{}={}", name, external.lib_path().display()));
+    }
+
+    for (name, kind) in linking_config.native_libs.iter() {
+        args.push("-l".to_owned());
+        args.push(format!("{}={}", kind.as_str(), name));
+    }
+
+    for arg in linking_config.link_args.iter() {
+        args.push("-C".to_owned());
+        args.push(format!("link-arg={}", arg));
+    }
+
+    if let Some(linker) = &linking_config.linker {
+        args.push("-C".to_owned());
+        args.push(format!("linker={}", linker.display()));
+    }
+
+    if linking_config.panic_strategy == crate::linking::PanicStrategy::Abort {
+        args.push("-C".to_owned());
+        args.push("panic=abort".to_owned());
+    }
+
+    args
+}
+
+fn check_args(linking_config: &crate::linking::LinkingConfiguration) -> Vec<String> {
+    let mut args = compile_args(linking_config);
+    args.push("--emit=metadata".to_owned());
+    args
+}
+
 /// Function to rename the output library file and remove the associated dependency.
 ///
 /// In relation to [#44](https://github.com/kurtlawrence/papyrus/issues/44), loading a library will
@@ -107,6 +259,68 @@ fn rename_lib_file<P: AsRef<Path>>(compiled_lib: P) -> io::Result<PathBuf> {
     Ok(lib_path)
 }
 
+/// Whether `file_name` is a library renamed by [`unshackle_library_file`].
+fn is_renamed_lib(file_name: &str) -> bool {
+    file_name.starts_with("papyrus.") && file_name.ends_with(".lib")
+}
+
+/// The total size in bytes of the files under `dir`. A missing directory is empty.
+pub(crate) fn dir_size(dir: &Path) -> io::Result<u64> {
+    let mut size = 0;
+    walk_files(dir, &mut |_, meta| size += meta.len())?;
+    Ok(size)
+}
+
+/// Removes the libraries renamed by [`unshackle_library_file`] under `dir`, oldest first, until
+/// `excess` bytes have been freed or none are left. Returns the number of bytes freed.
+///
+/// Libraries which can not be removed, such as loaded libraries on Windows, are skipped.
+pub(crate) fn evict_libraries(dir: &Path, excess: u64) -> io::Result<u64> {
+    let mut libs = Vec::new();
+    walk_files(dir, &mut |path, meta| {
+        let renamed = path
+            .file_name()
+            .and_then(|x| x.to_str())
+            .map_or(false, is_renamed_lib);
+        if renamed {
+            libs.push((meta.modified().ok(), meta.len(), path.to_path_buf()));
+        }
+    })?;
+    libs.sort();
+
+    let mut freed = 0;
+    for (_, len, path) in libs {
+        if freed >= excess {
+            break;
+        }
+        if std::fs::remove_file(&path).is_ok() {
+            freed += len;
+        }
+    }
+    Ok(freed)
+}
+
+/// Calls `f` with each file under `dir`, recursing into directories but not following links.
+fn walk_files(dir: &Path, f: &mut dyn FnMut(&Path, &std::fs::Metadata)) -> io::Result<()> {
+    let entries = match std::fs::read_dir(dir) {
+        Ok(x) => x,
+        Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(()),
+        Err(e) => return Err(e),
+    };
+
+    for entry in entries {
+        let entry = entry?;
+        let meta = entry.metadata()?;
+        if meta.is_dir() {
+            walk_files(&entry.path(), f)?;
+        } else {
+            f(&entry.path(), &meta);
+        }
+    }
+
+    Ok(())
+}
+
 /// Error type for compilation.
 #[derive(Debug)]
 pub enum CompilationError {
@@ -114,6 +328,9 @@ pub enum CompilationError {
     NoBuildCommand,
     /// A compiling error occured, with the contents of the stderr.
     CompileError(String),
+    /// Cargo is running offline and a required crate is not in the local cache, with the contents
+    /// of the stderr.
+    OfflineError(String),
     /// Generic IO errors.
     IOError(io::Error),
 }
@@ -127,11 +344,165 @@ impl fmt::Display for CompilationError {
                 write!(f, "cargo build command failed to start, is rust installed?")
             }
             CompilationError::CompileError(e) => write!(f, "{}", e),
+            CompilationError::OfflineError(e) => write!(
+                f,
+                "compilation is offline and a required crate is not in the local cargo cache\n{}",
+                e
+            ),
             CompilationError::IOError(e) => write!(f, "io error occurred: {}", e),
         }
     }
 }
 
+#[test]
+fn compile_args_test() {
+    use crate::linking::{LinkingConfiguration, NativeLibKind, PanicStrategy};
+
+    let mut config = LinkingConfiguration::default();
+    assert_eq!(
+        compile_args(&config),
+        vec!["rustc", "--", "-Awarnings", "-C", "debuginfo=1"]
+    );
+
+    config.panic_strategy = PanicStrategy::Propagate;
+    assert_eq!(
+        compile_args(&config),
+        vec!["rustc", "--", "-Awarnings", "-C", "debuginfo=1"]
+    );
+
+    config.panic_strategy = PanicStrategy::Abort;
+    assert_eq!(
+        compile_args(&config),
+        vec![
+            "rustc",
+            "--",
+            "-Awarnings",
+            "-C",
+            "debuginfo=1",
+            "-C",
+            "panic=abort"
+        ]
+    );
+
+    let config = LinkingConfiguration::default().with_debuginfo(0).unwrap();
+    assert_eq!(
+        compile_args(&config),
+        vec!["rustc", "--", "-Awarnings", "-C", "debuginfo=0"]
+    );
+
+    let config = LinkingConfiguration::default()
+        .with_native_lib("m", NativeLibKind::Dylib)
+        .unwrap()
+        .with_link_arg("-lfoo".to_string());
+    assert_eq!(
+        compile_args(&config),
+        vec![
+            "rustc",
+            "--",
+            "-Awarnings",
+            "-C",
+            "debuginfo=1",
+            "-l",
+            "dylib=m",
+            "-C",
+            "link-arg=-lfoo"
+        ]
+    );
+
+    let mut config = LinkingConfiguration::default();
+    config.linker = Some("lld".into());
+    assert_eq!(
+        compile_args(&config),
+        vec![
+            "rustc",
+            "--",
+            "-Awarnings",
+            "-C",
+            "debuginfo=1",
+            "-C",
+            "linker=lld"
+        ]
+    );
+
+    let config = LinkingConfiguration::default().with_offline(true);
+    assert_eq!(
+        compile_args(&config),
+        vec![
+            "rustc",
+            "--offline",
+            "--",
+            "-Awarnings",
+            "-C",
+            "debuginfo=1"
+        ]
+    );
+
+    let mut config = LinkingConfiguration::default();
+    config.target = Some("wasm32-unknown-unknown".to_string());
+    assert_eq!(
+        compile_args(&config),
+        vec![
+            "rustc",
+            "--target",
+            "wasm32-unknown-unknown",
+            "--",
+            "-Awarnings",
+            "-C",
+            "debuginfo=1"
+        ]
+    );
+}
+
+#[test]
+fn command_line_test() {
+    use crate::linking::LinkingConfiguration;
+
+    let config = LinkingConfiguration::default().with_link_arg("-L my libs".to_string());
+    assert_eq!(
+        command_line("compile", &config, false),
+        "cd compile && cargo rustc -- -Awarnings -C debuginfo=1 -C \"link-arg=-L my libs\""
+    );
+    assert_eq!(
+        command_line("my dir", &LinkingConfiguration::default(), true),
+        "cd \"my dir\" && cargo rustc -- -Awarnings -C debuginfo=1 --emit=metadata"
+    );
+}
+
+#[test]
+fn is_offline_error_test() {
+    let stderr = "error: failed to download `rand v0.7.3`
+
+Caused by:
+  attempting to make an HTTP request, but --offline was specified
+";
+    assert!(is_offline_error(stderr));
+    assert!(!is_offline_error(
+        "error[E0425]: cannot find value `a` in this scope"
+    ));
+}
+
+#[test]
+fn lib_file_test() {
+    let dir = Path::new("compile");
+    assert_eq!(
+        lib_file(dir, Some("wasm32-unknown-unknown")),
+        Path::new("compile/target/wasm32-unknown-unknown/debug/papyrus_mem_code.wasm")
+    );
+    assert_eq!(
+        lib_file(dir, Some("x86_64-pc-windows-msvc")),
+        Path::new("compile/target/x86_64-pc-windows-msvc/debug/papyrus_mem_code.dll")
+    );
+    assert_eq!(
+        lib_file(dir, Some("aarch64-apple-darwin")),
+        Path::new("compile/target/aarch64-apple-darwin/debug/libpapyrus_mem_code.dylib")
+    );
+    assert_eq!(
+        lib_file(dir, Some("aarch64-unknown-linux-gnu")),
+        Path::new("compile/target/aarch64-unknown-linux-gnu/debug/libpapyrus_mem_code.so")
+    );
+    assert!(lib_file(dir, None).starts_with("compile/target/debug/"));
+}
+
 #[test]
 fn compilation_error_fmt_test() {
     let e = CompilationError::NoBuildCommand;
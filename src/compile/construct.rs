@@ -20,6 +20,22 @@ pub fn build_compile_dir<P>(
 ) -> io::Result<()>
 where
     P: AsRef<Path>,
+{
+    build_compile_dir_with(compile_dir, mods_map, linking_config, static_files, |s| s)
+}
+
+/// As [`build_compile_dir`], with `transform` applied to the generated source code before it is
+/// written.
+pub(crate) fn build_compile_dir_with<P, F>(
+    compile_dir: P,
+    mods_map: &ModsMap,
+    linking_config: &linking::LinkingConfiguration,
+    static_files: &StaticFiles,
+    transform: F,
+) -> io::Result<()>
+where
+    P: AsRef<Path>,
+    F: FnOnce(String) -> String,
 {
     let compile_dir = compile_dir.as_ref();
 
@@ -34,6 +50,7 @@ where
         .write_all(cargotoml_contents(LIBRARY_NAME, crates.into_iter()).as_bytes())?;
 
     let (src_code, _map) = code::construct_source_code(mods_map, linking_config, static_files);
+    let src_code = transform(src_code);
 
     create_file_and_dir(compile_dir.join("src/lib.rs"))?.write_all(src_code.as_bytes())?;
 
@@ -62,7 +79,7 @@ fn cargotoml_contents<'a, I: Iterator<Item = &'a CrateType>>(lib_name: &str, cra
         r#"[package]
 name = "{lib_name}"
 version = "0.1.0"
-edition = "2018"
+edition = "{edition}"
 
 [lib]
 name = "{lib_name}"
@@ -74,6 +91,7 @@ kserd = {{ version = "0.3", default-features = false, features = [ "format" ] }}
 {crates}
 "#,
         lib_name = lib_name,
+        edition = super::toolchain::EDITION,
         crates = crates
             .map(|c| format!(r#"{} = "*""#, c.cargo_name))
             .collect::<Vec<_>>()
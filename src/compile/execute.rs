@@ -1,34 +1,84 @@
+use crate::linking::PanicStrategy;
 use ::kserd::Kserd;
 use libloading::{Library, Symbol};
+use std::fmt;
 use std::path::Path;
 
 /// We don't type anything here. You must be **VERY** careful to pass through the correct borrow to match the
 /// function signature!
+///
+/// The evaluation function is generated with the Rust ABI (see `code::append_buffer`), so a panic
+/// can unwind back through this call.
 type DataFunc<D> = unsafe fn(D) -> Kserd<'static>;
 
-type ExecResult = Result<(Kserd<'static>, Library), &'static str>;
+type ExecResult = Result<(Kserd<'static>, Library), ExecError>;
+
+/// Error executing the evaluation function.
+#[derive(Debug)]
+pub(crate) enum ExecError {
+    /// Loading the library or the function failed.
+    Load(&'static str),
+    /// The evaluation panicked, with the panic message if it could be retrieved.
+    Panic(Option<String>),
+}
+
+impl fmt::Display for ExecError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ExecError::Load(e) => write!(f, "{}", e),
+            ExecError::Panic(_) => write!(f, "a panic occured with evaluation"),
+        }
+    }
+}
 
 pub(crate) fn exec<P: AsRef<Path>, D>(
     library_file: P,
     function_name: &str,
     app_data: D,
+    panic_strategy: PanicStrategy,
 ) -> ExecResult {
-    exec_no_redirect(library_file, function_name, app_data)
+    exec_no_redirect(library_file, function_name, app_data, panic_strategy)
 }
 
 fn exec_no_redirect<P: AsRef<Path>, Data>(
     library_file: P,
     function_name: &str,
     app_data: Data,
+    panic_strategy: PanicStrategy,
 ) -> ExecResult {
-    let lib = get_lib(library_file)?;
-    let func = get_func(&lib, function_name)?;
+    let lib = get_lib(library_file).map_err(ExecError::Load)?;
+    let func = get_func(&lib, function_name).map_err(ExecError::Load)?;
 
-    let res = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| unsafe { func(app_data) }));
+    let res = match panic_strategy {
+        PanicStrategy::Catch => {
+            std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| unsafe { func(app_data) }))
+        }
+        PanicStrategy::Propagate => {
+            match std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| unsafe {
+                func(app_data)
+            })) {
+                Ok(kserd) => Ok(kserd),
+                Err(payload) => {
+                    // the payload is allocated by the library, it must stay loaded while the
+                    // panic continues unwinding through the caller
+                    std::mem::forget(lib);
+                    std::panic::resume_unwind(payload)
+                }
+            }
+        }
+        // an abort strategy will never unwind back to here
+        PanicStrategy::Abort => Ok(unsafe { func(app_data) }),
+    };
 
     match res {
         Ok(kserd) => Ok((kserd, lib)),
-        Err(_) => Err("a panic occured with evaluation"),
+        Err(payload) => {
+            let msg = payload
+                .downcast_ref::<String>()
+                .cloned()
+                .or_else(|| payload.downcast_ref::<&str>().map(|s| s.to_string()));
+            Err(ExecError::Panic(msg))
+        }
     }
 }
 
@@ -3,22 +3,49 @@
 mod build;
 mod construct;
 mod execute;
+mod toolchain;
 
-pub use self::build::{compile, unshackle_library_file, CompilationError};
+pub use self::build::{check, command_line, compile, unshackle_library_file, CompilationError};
+pub(crate) use self::build::{dir_size, evict_libraries};
 pub use self::construct::build_compile_dir;
-pub(crate) use self::execute::exec;
+pub(crate) use self::construct::build_compile_dir_with;
+pub(crate) use self::execute::{exec, ExecError};
+pub(crate) use self::toolchain::target_installed;
+pub use self::toolchain::ToolchainInfo;
+pub(crate) use self::toolchain::EDITION;
 
 /// The library name to compile as.c
-const LIBRARY_NAME: &str = "papyrus_mem_code";
+pub(crate) const LIBRARY_NAME: &str = "papyrus_mem_code";
 
 #[cfg(test)]
 mod tests {
     use super::*;
     use crate::code::*;
-    use crate::linking::{Extern, LinkingConfiguration};
+    use crate::linking::{Extern, LinkingConfiguration, PanicStrategy};
     use ::kserd::Kserd;
     use std::{fs, path::PathBuf};
 
+    #[test]
+    fn evict_libraries_test() {
+        let dir = PathBuf::from("target/testing/evict_libraries_test");
+        fs::remove_dir_all(&dir).ok();
+        fs::create_dir_all(dir.join("debug")).unwrap();
+        fs::write(dir.join("debug/papyrus.a.lib"), [0; 10]).unwrap();
+        fs::write(dir.join("debug/papyrus.b.lib"), [0; 10]).unwrap();
+        fs::write(dir.join("lib.rs"), [0; 5]).unwrap();
+
+        assert_eq!(dir_size(&dir).unwrap(), 25);
+        assert_eq!(dir_size(&dir.join("missing")).unwrap(), 0);
+
+        // stops once enough is freed
+        assert_eq!(evict_libraries(&dir, 1).unwrap(), 10);
+        assert_eq!(dir_size(&dir).unwrap(), 15);
+
+        // only removes libraries
+        assert_eq!(evict_libraries(&dir, 100).unwrap(), 10);
+        assert_eq!(dir_size(&dir).unwrap(), 5);
+    }
+
     #[test]
     fn nodata_build_fmt_compile_eval_test() {
         let compile_dir = "target/testing/nodata_build_fmt_compile_eval_test";
@@ -35,9 +62,12 @@ mod tests {
         let path = compile(&compile_dir, &linking_config, |_| ()).unwrap();
 
         // eval
-        let r = exec::<_, _>(path, "_lib_intern_eval", &()).unwrap(); // execute library fn
+        let r = exec::<_, _>(path, "_lib_intern_eval", &(), PanicStrategy::Catch).unwrap(); // execute library fn
 
-        assert_eq!(r.0, Kserd::new_num(4));
+        assert_eq!(
+            split_out_type(r.0),
+            (Kserd::new_num(4), Some("i32".to_string()))
+        );
     }
 
     #[test]
@@ -60,9 +90,12 @@ mod tests {
         let path = compile(&compile_dir, &linking_config, |_| ()).unwrap();
 
         // eval
-        let r = exec::<_, _>(path, "_lib_intern_eval", &()).unwrap(); // execute library fn
+        let r = exec::<_, _>(path, "_lib_intern_eval", &(), PanicStrategy::Catch).unwrap(); // execute library fn
 
-        assert_eq!(r.0, Kserd::new_num(4));
+        assert_eq!(
+            split_out_type(r.0),
+            (Kserd::new_num(4), Some("i32".to_string()))
+        );
     }
 
     #[test]
@@ -91,9 +124,12 @@ mod tests {
         let path = compile(&compile_dir, &linking_config, |_| ()).unwrap();
 
         // eval
-        let r = exec::<_, _>(path, "_lib_intern_eval", &()).unwrap(); // execute library fn
+        let r = exec::<_, _>(path, "_lib_intern_eval", &(), PanicStrategy::Catch).unwrap(); // execute library fn
 
-        assert_eq!(r.0, Kserd::new_num(4));
+        assert_eq!(
+            split_out_type(r.0),
+            (Kserd::new_num(4), Some("i32".to_string()))
+        );
     }
 
     #[test]
@@ -122,9 +158,12 @@ mod tests {
         let path = compile(&compile_dir, &linking_config, |_| ()).unwrap();
 
         // eval
-        let r = exec(path, "_lib_intern_eval", &()).unwrap(); // execute library fn
+        let r = exec(path, "_lib_intern_eval", &(), PanicStrategy::Catch).unwrap(); // execute library fn
 
-        assert_eq!(r.0, Kserd::new_num(4));
+        assert_eq!(
+            split_out_type(r.0),
+            (Kserd::new_num(4), Some("i32".to_string()))
+        );
     }
 
     #[test]
@@ -173,19 +212,25 @@ mod tests {
 
     fn pass_compile_eval_file() -> (PathBuf, SourceCode) {
         let mut code = SourceCode::default();
-        code.stmts.push(StmtGrp(vec![Statement {
-            expr: "2+2".to_string(),
-            semi: false,
-        }]));
+        code.stmts.push(StmtGrp(
+            vec![Statement {
+                expr: "2+2".to_string(),
+                semi: false,
+            }],
+            false,
+        ));
         ("lib".into(), code)
     }
 
     fn fail_compile_file() -> (PathBuf, SourceCode) {
         let mut code = SourceCode::default();
-        code.stmts.push(StmtGrp(vec![Statement {
-            expr: "2+".to_string(),
-            semi: false,
-        }]));
+        code.stmts.push(StmtGrp(
+            vec![Statement {
+                expr: "2+".to_string(),
+                semi: false,
+            }],
+            false,
+        ));
         ("lib".into(), code)
     }
 
@@ -196,14 +241,20 @@ mod tests {
             let mut code = SourceCode::default();
             code.crates
                 .push(CrateType::parse_str("extern crate rand;").unwrap());
-            code.stmts.push(StmtGrp(vec![Statement {
-                expr: "rand::random::<u8>()".into(),
-                semi: false,
-            }]));
-            code.stmts.push(StmtGrp(vec![Statement {
-                expr: "2+2".into(),
-                semi: false,
-            }]));
+            code.stmts.push(StmtGrp(
+                vec![Statement {
+                    expr: "rand::random::<u8>()".into(),
+                    semi: false,
+                }],
+                false,
+            ));
+            code.stmts.push(StmtGrp(
+                vec![Statement {
+                    expr: "2+2".into(),
+                    semi: false,
+                }],
+                false,
+            ));
             ("lib".into(), code)
         }]
         .into_iter()
@@ -220,6 +271,7 @@ mod tests {
         // build
         build_compile_dir(&compile_dir, &files, &linking_config, &StaticFiles::new()).unwrap();
         let filestr = fs::read_to_string(&format!("{}/src/lib.rs", compile_dir)).unwrap();
+        assert!(!filestr.contains("extern crate external_kserd;")); // in scope through --extern
         assert!(filestr.contains("\nlet out0 = rand::random::<u8>();"));
         assert!(filestr.contains("\nlet out1 = 2+2;"));
 
@@ -227,8 +279,11 @@ mod tests {
         let path = compile(&compile_dir, &linking_config, |_| ()).unwrap();
 
         // eval
-        let r = exec::<_, _>(path, "_lib_intern_eval", &()).unwrap(); // execute library fn
+        let r = exec::<_, _>(path, "_lib_intern_eval", &(), PanicStrategy::Catch).unwrap(); // execute library fn
 
-        assert_eq!(r.0, Kserd::new_num(4));
+        assert_eq!(
+            split_out_type(r.0),
+            (Kserd::new_num(4), Some("i32".to_string()))
+        );
     }
 }
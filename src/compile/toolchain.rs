@@ -0,0 +1,114 @@
+use std::io;
+use std::path::Path;
+use std::process::Command;
+
+/// The Rust edition that evaluations are compiled with.
+pub(crate) const EDITION: &str = "2018";
+
+/// Information about the Rust toolchain that is used to compile evaluations.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ToolchainInfo {
+    /// The `rustc` version, such as `rustc 1.47.0 (18bf6b4f0 2020-10-07)`.
+    pub rustc_version: String,
+    /// The host target triple, such as `x86_64-unknown-linux-gnu`.
+    pub host_triple: String,
+    /// The Rust edition evaluations are compiled with.
+    pub edition: &'static str,
+}
+
+impl ToolchainInfo {
+    /// Query the toolchain by running `rustc -vV` in the given directory.
+    ///
+    /// The directory should be the compilation directory, such that any toolchain overrides are
+    /// respected.
+    pub fn query<P: AsRef<Path>>(dir: P) -> io::Result<Self> {
+        let output = Command::new("rustc").current_dir(dir).arg("-vV").output()?;
+
+        if !output.status.success() {
+            return Err(io::Error::new(
+                io::ErrorKind::Other,
+                format!(
+                    "rustc -vV failed: {}",
+                    String::from_utf8_lossy(&output.stderr)
+                ),
+            ));
+        }
+
+        parse_rustc_vv(&String::from_utf8_lossy(&output.stdout)).ok_or_else(|| {
+            io::Error::new(
+                io::ErrorKind::InvalidData,
+                "could not parse output of rustc -vV",
+            )
+        })
+    }
+}
+
+/// Returns whether the standard library for `target` is installed in the toolchain used in `dir`.
+///
+/// This checks for the target folder in the sysroot, as reported by `rustc --print sysroot`.
+pub(crate) fn target_installed<P: AsRef<Path>>(dir: P, target: &str) -> io::Result<bool> {
+    let output = Command::new("rustc")
+        .current_dir(dir)
+        .args(&["--print", "sysroot"])
+        .output()?;
+
+    if !output.status.success() {
+        return Err(io::Error::new(
+            io::ErrorKind::Other,
+            format!(
+                "rustc --print sysroot failed: {}",
+                String::from_utf8_lossy(&output.stderr)
+            ),
+        ));
+    }
+
+    let sysroot = String::from_utf8_lossy(&output.stdout);
+    let path = Path::new(sysroot.trim())
+        .join("lib")
+        .join("rustlib")
+        .join(target)
+        .join("lib");
+
+    Ok(path.is_dir())
+}
+
+fn parse_rustc_vv(s: &str) -> Option<ToolchainInfo> {
+    let mut lines = s.lines();
+    let rustc_version = lines
+        .next()
+        .filter(|x| x.starts_with("rustc "))?
+        .trim()
+        .to_string();
+    let host_triple = lines.find(|x| x.starts_with("host:"))?["host:".len()..]
+        .trim()
+        .to_string();
+
+    Some(ToolchainInfo {
+        rustc_version,
+        host_triple,
+        edition: EDITION,
+    })
+}
+
+#[test]
+fn parse_rustc_vv_test() {
+    let s = "rustc 1.47.0 (18bf6b4f0 2020-10-07)
+binary: rustc
+commit-hash: 18bf6b4f01a6feaf7259ba7cdae58031af1b7b39
+commit-date: 2020-10-07
+host: x86_64-unknown-linux-gnu
+release: 1.47.0
+LLVM version: 11.0
+";
+    assert_eq!(
+        parse_rustc_vv(s),
+        Some(ToolchainInfo {
+            rustc_version: "rustc 1.47.0 (18bf6b4f0 2020-10-07)".to_string(),
+            host_triple: "x86_64-unknown-linux-gnu".to_string(),
+            edition: "2018",
+        })
+    );
+
+    assert_eq!(parse_rustc_vv(""), None);
+    assert_eq!(parse_rustc_vv("rustc 1.47.0\nbinary: rustc\n"), None);
+}
@@ -98,6 +98,10 @@ impl CodeCompleter {
     }
 
     /// Get completions that would match a string injected into the current repl state.
+    ///
+    /// Input that is still being typed is often not valid Rust. If `racer` fails on the input
+    /// (it can panic on malformed source), no completions are returned rather than the error
+    /// surfacing.
     pub fn complete(&self, injection: &str, limit: Option<usize>, cache: &CodeCache) -> Vec<Match> {
         let limit = limit.unwrap_or(std::usize::MAX);
 
@@ -107,9 +111,15 @@ impl CodeCompleter {
 
         session.cache_file_contents(LIBRS, contents);
 
-        racer::complete_from_file(LIBRS, Location::Point(pos), &session)
-            .take(limit)
-            .collect()
+        std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| {
+            racer::complete_from_file(LIBRS, Location::Point(pos), &session)
+                .take(limit)
+                .collect()
+        }))
+        .unwrap_or_else(|_| {
+            warn!("racer failed completing input: {}", injection);
+            Vec::new()
+        })
     }
 
     /// Inject code into the current source code and return the amended code,
@@ -251,6 +261,22 @@ mod tests {
         assert_eq!(matches.get(0).map(|x| x.matchstr.as_str()), Some("apple"));
     }
 
+    #[test]
+    fn complete_invalid_input_test() {
+        let cc = CodeCompleter {
+            last_code: String::from("fn apple() {} \n\n fn main() {  }"),
+            split: 29..29,
+        };
+        let cache = CodeCache::new().unwrap_or_else(|e| e.0);
+
+        // unbalanced and unfinished input still completes
+        let matches = cc.complete("let a = (1, ap", None, &cache);
+        assert_eq!(matches.get(0).map(|x| x.matchstr.as_str()), Some("apple"));
+
+        // garbage input does not fail
+        cc.complete("}}} ::. ap", None, &cache);
+    }
+
     #[test]
     fn complete_through_repl() {
         use crate::repl::*;
@@ -12,6 +12,29 @@ pub mod cmdr;
 pub mod code;
 pub mod modules;
 
+/// A completion candidate, see [`ReplData::complete`](crate::repl::ReplData::complete).
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Completion {
+    /// The byte position in the input the replacement starts from, it replaces the input up to the
+    /// cursor.
+    pub start: usize,
+    /// The text which replaces the input from `start`.
+    pub replacement: String,
+    /// The text to show for the candidate, the replacement with a description if there is one.
+    pub display: String,
+    /// What is being completed.
+    pub kind: CompletionKind,
+}
+
+/// The kind of a [`Completion`].
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum CompletionKind {
+    /// A command class or action, such as `:mod switch`.
+    Command,
+    /// A module path argument, such as the `lib` in `:mod switch lib`.
+    Module,
+}
+
 /// Returns the start position of the _last_ word which is broken by any of the characters.
 ///
 /// # Example
@@ -33,3 +56,84 @@ pub fn word_break_start(s: &str, word_break_chars: &[char]) -> usize {
 
     start
 }
+
+/// Returns the candidates which are close to `word`, closest first, such as to suggest
+/// corrections for a misspelt name.
+///
+/// A candidate is close if it takes at most a third of the length of `word` (at least one) single
+/// character edits to match `word`. Swapping adjacent characters counts as one edit. Exact
+/// matches are not returned.
+///
+/// # Example
+/// ```rust
+/// let m = papyrus::complete::close_matches("lenght", vec!["length", "width", "len"]);
+/// assert_eq!(m, vec!["length"]);
+/// ```
+pub fn close_matches<'a, I>(word: &str, candidates: I) -> Vec<&'a str>
+where
+    I: IntoIterator<Item = &'a str>,
+{
+    let max = std::cmp::max(word.chars().count() / 3, 1);
+    let mut matches = candidates
+        .into_iter()
+        .filter(|x| *x != word)
+        .map(|x| (edit_distance(word, x), x))
+        .filter(|x| x.0 <= max)
+        .collect::<Vec<_>>();
+    matches.sort_by_key(|x| x.0);
+    matches.into_iter().map(|x| x.1).collect()
+}
+
+/// The number of insertions, deletions, substitutions, or swaps of adjacent characters to turn
+/// `a` into `b`.
+fn edit_distance(a: &str, b: &str) -> usize {
+    let a = a.chars().collect::<Vec<_>>();
+    let b = b.chars().collect::<Vec<_>>();
+
+    // d[i][j] is the distance between the first i chars of a and the first j chars of b
+    let mut d = vec![vec![0; b.len() + 1]; a.len() + 1];
+    for (i, row) in d.iter_mut().enumerate() {
+        row[0] = i;
+    }
+    for (j, x) in d[0].iter_mut().enumerate() {
+        *x = j;
+    }
+
+    for i in 1..=a.len() {
+        for j in 1..=b.len() {
+            let cost = usize::from(a[i - 1] != b[j - 1]);
+            let mut x = std::cmp::min(
+                std::cmp::min(d[i - 1][j] + 1, d[i][j - 1] + 1),
+                d[i - 1][j - 1] + cost,
+            );
+            if i > 1 && j > 1 && a[i - 1] == b[j - 2] && a[i - 2] == b[j - 1] {
+                x = std::cmp::min(x, d[i - 2][j - 2] + 1);
+            }
+            d[i][j] = x;
+        }
+    }
+
+    d[a.len()][b.len()]
+}
+
+#[test]
+fn close_matches_test() {
+    assert_eq!(edit_distance("", "abc"), 3);
+    assert_eq!(edit_distance("abc", "abc"), 0);
+    assert_eq!(edit_distance("abc", "acb"), 1);
+    assert_eq!(edit_distance("kitten", "sitting"), 3);
+
+    let candidates = vec!["value", "vals", "x", "vale"];
+    assert_eq!(close_matches("vale", candidates), vec!["value", "vals"]);
+    assert_eq!(close_matches("x", vec!["y", "xyz"]), vec!["y"]);
+    assert!(close_matches("value", vec!["other"]).is_empty());
+}
+
+#[test]
+fn word_break_start_unicode_test() {
+    let s = "let 数 = 你好.le";
+    let b = word_break_start(s, &[' ', '.']);
+    assert_eq!(&s[b..], "le");
+    let b = word_break_start("数 你好", &[' ']);
+    assert_eq!(&"数 你好"[b..], "你好");
+}
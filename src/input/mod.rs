@@ -7,6 +7,9 @@ mod tests;
 
 pub use self::parse::parse_command;
 pub use self::parse::parse_program;
+pub use self::parse::{
+    binds_name, is_assertion, let_binding_name, mod_item_name, pattern_item_name,
+};
 
 /// Possible results from reading input from `InputReader`
 #[derive(Debug, PartialEq)]
@@ -27,7 +30,7 @@ pub enum InputResult {
 
 /// Parse `input` and `line` and determine what `InputResult`.
 pub fn determine_result(input: &str, line: &str, treat_as_cmd: bool) -> InputResult {
-    if input.is_empty() {
+    if input.trim().is_empty() {
         return InputResult::Empty; // if line is empty this could result. do not remove
     }
 
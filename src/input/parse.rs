@@ -1,6 +1,7 @@
 use super::*;
 use crate::code::{self, Statement};
 use syn::export::ToTokens;
+use syn::visit::Visit;
 use syn::{self, Block, File, Item, Stmt};
 
 /// Parses a line of input as a command.
@@ -49,7 +50,9 @@ pub fn parse_program(code: &str) -> InputResult {
             .unwrap_or_else(reterr);
     }
 
-    let code = format!("{{ {} }}", code); // wrap in a block so the parser can parse through it without need to guess the type!
+    // wrap in a block so the parser can parse through it without need to guess the type!
+    // the closing brace is on a new line so a trailing line comment does not comment it out
+    let code = format!("{{ {}\n}}", code);
 
     syn::parse_str::<Block>(&code)
         .map(|block| {
@@ -105,6 +108,88 @@ pub fn parse_program(code: &str) -> InputResult {
         .unwrap_or_else(reterr)
 }
 
+/// The name bound by a `let` statement with a simple identifier pattern, such as `let mut a: i32 =
+/// 1`. Returns `None` for other statements, or patterns which bind more than one name.
+pub fn let_binding_name(stmt: &str) -> Option<String> {
+    let block = syn::parse_str::<Block>(&format!("{{ {}; }}", stmt)).ok()?;
+    let local = match block.stmts.as_slice() {
+        [Stmt::Local(local)] if local.init.is_some() => local,
+        _ => return None,
+    };
+
+    let pat = match &local.pat {
+        syn::Pat::Type(pat) => &*pat.pat,
+        pat => pat,
+    };
+
+    match pat {
+        syn::Pat::Ident(pat) if pat.by_ref.is_none() && pat.subpat.is_none() => {
+            Some(pat.ident.to_string())
+        }
+        _ => None,
+    }
+}
+
+/// The code binds `name` in a pattern, such as a `let` binding, a closure or function parameter, or
+/// a `match` arm. Nested blocks and items are searched as well.
+pub fn binds_name(code: &str, name: &str) -> bool {
+    let mut visitor = BindsName { name, found: false };
+
+    if let Ok(item) = syn::parse_str::<Item>(code) {
+        visitor.visit_item(&item);
+    } else if let Ok(block) = syn::parse_str::<Block>(&format!("{{ {}; }}", code)) {
+        visitor.visit_block(&block);
+    }
+
+    visitor.found
+}
+
+struct BindsName<'a> {
+    name: &'a str,
+    found: bool,
+}
+
+impl<'ast, 'a> Visit<'ast> for BindsName<'a> {
+    fn visit_pat_ident(&mut self, pat: &'ast syn::PatIdent) {
+        self.found |= pat.ident == self.name;
+        syn::visit::visit_pat_ident(self, pat);
+    }
+}
+
+/// The statement is a single assertion macro: `assert!`, `assert_eq!`, `assert_ne!`, or one of
+/// their `debug_` variants.
+pub fn is_assertion(stmt: &str) -> bool {
+    let mac = match syn::parse_str::<Expr>(stmt) {
+        Ok(Expr::Macro(x)) => x.mac,
+        _ => return false,
+    };
+
+    match mac.path.get_ident().map(ToString::to_string).as_deref() {
+        Some("assert") | Some("assert_eq") | Some("assert_ne") => true,
+        Some("debug_assert") | Some("debug_assert_eq") | Some("debug_assert_ne") => true,
+        _ => false,
+    }
+}
+
+/// The name defined by an item which is in scope for patterns, such as `const A: u8 = 1;` or
+/// `struct A;`. Returns `None` for other items.
+pub fn pattern_item_name(item: &str) -> Option<String> {
+    match syn::parse_str::<Item>(item).ok()? {
+        Item::Const(x) => Some(x.ident.to_string()),
+        Item::Static(x) => Some(x.ident.to_string()),
+        Item::Struct(x) if !matches!(x.fields, syn::Fields::Named(_)) => Some(x.ident.to_string()),
+        _ => None,
+    }
+}
+
+/// The name of an inline module item, such as `mod a { ... }`. Returns `None` for other items.
+pub fn mod_item_name(item: &str) -> Option<String> {
+    match syn::parse_str::<Item>(item).ok()? {
+        Item::Mod(x) if x.content.is_some() => Some(x.ident.to_string()),
+        _ => None,
+    }
+}
+
 #[cfg(feature = "format")]
 fn fmt(s: String) -> String {
     crate::fmt::format(&s).unwrap_or(s)
@@ -132,19 +217,13 @@ fn parse_item(item: Item) -> ParseItemResult {
             error!("haven't handled item variant Const");
             ParseItemResult::Error("haven't handled item variant Const. Raise a request here https://github.com/kurtlawrence/papyrus/issues".to_string())
         }
-        Item::Mod(_) => {
-            error!("haven't handled item variant Mod");
-            ParseItemResult::Error("haven't handled item variant Mod. Raise a request here https://github.com/kurtlawrence/papyrus/issues".to_string())
-        }
+        Item::Mod(m) if m.content.is_none() => ParseItemResult::Error(
+            "modules must be defined inline, such as `mod name { ... }`".to_string(),
+        ),
         Item::ForeignMod(_) => {
             error!("haven't handled item variant ForeignMod");
             ParseItemResult::Error("haven't handled item variant ForeignMod. Raise a request here https://github.com/kurtlawrence/papyrus/issues".to_string())
         }
-        Item::Type(_) => {
-            error!("haven't handled item variant Type");
-            ParseItemResult::Error("haven't handled item variant Type. Raise a request here https://github.com/kurtlawrence/papyrus/issues".to_string())
-        }
-
         Item::Enum(_) => {
             error!("haven't handled item variant Enum");
             ParseItemResult::Error("haven't handled item variant Enum. Raise a request here https://github.com/kurtlawrence/papyrus/issues".to_string())
@@ -200,10 +279,6 @@ fn parse_expr(expr: Expr) -> Result<String, String> {
 			error!("haven't handled expr variant Lit");
 			Err("haven't handled expr variant Lit. Raise a request here https://github.com/kurtlawrence/papyrus/issues".to_string())
 		}
-		Expr::Cast(_) => {
-			error!("haven't handled expr variant Cast");
-			Err("haven't handled expr variant Cast. Raise a request here https://github.com/kurtlawrence/papyrus/issues".to_string())
-		}
 		Expr::Type(_) => {
 			error!("haven't handled expr variant Type");
 			Err("haven't handled expr variant Type. Raise a request here https://github.com/kurtlawrence/papyrus/issues".to_string())
@@ -224,18 +299,10 @@ fn parse_expr(expr: Expr) -> Result<String, String> {
 			error!("haven't handled expr variant Match");
 			Err("haven't handled expr variant Match. Raise a request here https://github.com/kurtlawrence/papyrus/issues".to_string())
 		}
-		Expr::Closure(_) => {
-			error!("haven't handled expr variant Closure");
-			Err("haven't handled expr variant Closure. Raise a request here https://github.com/kurtlawrence/papyrus/issues".to_string())
-		}
 		Expr::Unsafe(_) => {
 			error!("haven't handled expr variant Unsafe");
 			Err("haven't handled expr variant Unsafe. Raise a request here https://github.com/kurtlawrence/papyrus/issues".to_string())
 		}
-		Expr::Block(_) => {
-			error!("haven't handled expr variant Block");
-			Err("haven't handled expr variant Block. Raise a request here https://github.com/kurtlawrence/papyrus/issues".to_string())
-		}
 		Expr::Assign(_) => {
 			error!("haven't handled expr variant Assign");
 			Err("haven't handled expr variant Assign. Raise a request here https://github.com/kurtlawrence/papyrus/issues".to_string())
@@ -255,10 +322,6 @@ fn parse_expr(expr: Expr) -> Result<String, String> {
 			error!("haven't handled expr variant Range");
 			Err("haven't handled expr variant Range. Raise a request here https://github.com/kurtlawrence/papyrus/issues".to_string())
 		}
-		Expr::Reference(_) => {
-			error!("haven't handled expr variant Reference");
-			Err("haven't handled expr variant Reference. Raise a request here https://github.com/kurtlawrence/papyrus/issues".to_string())
-		}
 		Expr::Break(_) => {
 			error!("haven't handled expr variant Break");
 			Err("haven't handled expr variant Break. Raise a request here https://github.com/kurtlawrence/papyrus/issues".to_string())
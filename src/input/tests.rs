@@ -214,6 +214,18 @@ fn test_exprs() {
             crates: vec![]
         })
     );
+    // Expr::Reference
+    assert_eq!(
+        parse_program("&a[1..]"),
+        InputResult::Program(Input {
+            items: vec![],
+            stmts: vec![Statement {
+                expr: "&a[1..]".to_string(),
+                semi: false
+            }],
+            crates: vec![]
+        })
+    );
     // Expr::MethodCall
     assert_eq!(
         parse_program("std::env::current_dir()"),
@@ -239,6 +251,158 @@ fn test_exprs() {
     );
 }
 
+#[test]
+fn generic_fns() {
+    match parse_program("fn id<T>(x: T) -> T { x }") {
+        InputResult::Program(input) => {
+            assert_eq!(input.items.len(), 1);
+            assert_eq!(input.stmts.len(), 0);
+        }
+        _ => panic!("expecting program"),
+    }
+
+    match parse_program("id::<i32>(5)") {
+        InputResult::Program(input) => {
+            assert_eq!(input.items.len(), 0);
+            assert_eq!(input.stmts.len(), 1);
+            assert!(!input.stmts[0].semi);
+            assert!(input.stmts[0]
+                .expr
+                .replace(' ', "")
+                .contains("id::<i32>(5)"));
+        }
+        _ => panic!("expecting program"),
+    }
+}
+
+#[test]
+fn impl_trait_fns() {
+    match parse_program("fn make() -> impl Iterator<Item = i32> { 0..3 }") {
+        InputResult::Program(input) => {
+            assert_eq!(input.items.len(), 1);
+            assert!(input.items[0].0.contains("impl Iterator"));
+            assert_eq!(input.stmts.len(), 0);
+        }
+        _ => panic!("expecting program"),
+    }
+
+    match parse_program("make().collect::<Vec<_>>()") {
+        InputResult::Program(input) => {
+            assert_eq!(input.items.len(), 0);
+            assert_eq!(input.stmts.len(), 1);
+            assert!(!input.stmts[0].semi);
+        }
+        _ => panic!("expecting program"),
+    }
+}
+
+#[test]
+fn type_alias_and_const_generics() {
+    match parse_program("type Mat = Vec<Vec<f64>>;") {
+        InputResult::Program(input) => {
+            assert_eq!(input.items.len(), 1);
+            assert!(input.items[0].0.starts_with("type Mat"));
+            assert!(!input.items[0].1);
+            assert_eq!(input.stmts.len(), 0);
+        }
+        x => panic!("expecting program, got {:?}", x),
+    }
+
+    match parse_program("fn zeros<const N: usize>() -> [u8; N] { [0; N] }") {
+        InputResult::Program(input) => {
+            assert_eq!(input.items.len(), 1);
+            assert!(input.items[0].0.contains("const N"));
+            assert_eq!(input.stmts.len(), 0);
+        }
+        x => panic!("expecting program, got {:?}", x),
+    }
+
+    match parse_program("zeros::<3>().len()") {
+        InputResult::Program(input) => {
+            assert_eq!(input.items.len(), 0);
+            assert_eq!(input.stmts.len(), 1);
+            assert!(!input.stmts[0].semi);
+        }
+        x => panic!("expecting program, got {:?}", x),
+    }
+}
+
+#[test]
+fn inline_modules() {
+    match parse_program(
+        "mod utils { pub fn f() -> u8 { 1 } pub mod inner { pub const A: u8 = 2; } }",
+    ) {
+        InputResult::Program(input) => {
+            assert_eq!(input.items.len(), 1);
+            assert!(input.items[0].0.starts_with("mod utils"));
+            assert_eq!(input.stmts.len(), 0);
+            assert_eq!(mod_item_name(&input.items[0].0), Some("utils".to_string()));
+        }
+        x => panic!("expecting program, got {:?}", x),
+    }
+
+    assert_eq!(
+        parse_program("mod utils;"),
+        InputResult::InputError(
+            "modules must be defined inline, such as `mod name { ... }`".to_string()
+        )
+    );
+    assert_eq!(mod_item_name("fn utils() {}"), None);
+}
+
+#[test]
+fn block_exprs() {
+    match parse_program("{ let v = vec![1, 2, 3]; println!(\"{:?}\", v); v }") {
+        InputResult::Program(input) => {
+            assert_eq!(input.items.len(), 0);
+            assert_eq!(input.stmts.len(), 1);
+            assert!(!input.stmts[0].semi);
+        }
+        x => panic!("expecting program, got {:?}", x),
+    }
+}
+
+#[test]
+fn binds_name_test() {
+    let binds = |code| binds_name(code, "app_data");
+
+    assert!(binds("let app_data = 5"));
+    assert!(binds("let (a, app_data) = (1, 2)"));
+    assert!(binds("let Some(app_data) = x"));
+    assert!(binds("v.iter().map(|app_data| app_data + 1)"));
+    assert!(binds("fn f(app_data: u8) -> u8 { app_data }"));
+    assert!(binds("match x { Some(app_data) => 1, None => 0 }"));
+    assert!(binds("{ let app_data = 1; app_data }"));
+
+    assert!(!binds("app_data.len()"));
+    assert!(!binds("let a = app_data.len()"));
+    assert!(!binds("fn f(x: u8) -> u8 { x }"));
+    assert!(!binds("let app_data2 = 5"));
+}
+
+#[test]
+fn cast_exprs() {
+    match parse_program("Box::new(5) as Box<dyn std::fmt::Display>") {
+        InputResult::Program(input) => {
+            assert_eq!(input.items.len(), 0);
+            assert_eq!(input.stmts.len(), 1);
+            assert!(!input.stmts[0].semi);
+        }
+        x => panic!("expecting program, got {:?}", x),
+    }
+}
+
+#[test]
+fn closures() {
+    match parse_program("|x: i32| x + 1") {
+        InputResult::Program(input) => {
+            assert_eq!(input.stmts.len(), 1);
+            assert!(!input.stmts[0].semi);
+        }
+        _ => panic!("expecting program"),
+    }
+}
+
 #[test]
 fn test_determine_result() {
     assert_eq!(
@@ -254,6 +418,8 @@ fn test_determine_result() {
         InputResult::Command("help cmd".to_string())
     );
     assert_eq!(determine_result("", "", false), InputResult::Empty);
+    assert_eq!(determine_result("  ", "  ", false), InputResult::Empty);
+    assert_eq!(determine_result("\n\t", "\t", false), InputResult::Empty);
     assert_eq!(
         determine_result("2+2", "2+2", false),
         InputResult::Program(Input {
@@ -272,6 +438,98 @@ fn test_determine_result() {
     assert_eq!(determine_result("{", "{", false), InputResult::More);
 }
 
+#[test]
+fn comments() {
+    // the semi flag of each statement
+    let semis = |input| match parse_program(input) {
+        InputResult::Program(input) => input.stmts.iter().map(|x| x.semi).collect::<Vec<_>>(),
+        x => panic!("expecting program, got {:?}", x),
+    };
+
+    assert_eq!(semis("2 + 2 // a comment"), vec![false]);
+    assert_eq!(semis("2 + 2 /* a comment */"), vec![false]);
+    assert_eq!(semis("/* { ; */ 2 + 2"), vec![false]);
+    assert_eq!(semis("let x = 5; // a comment"), vec![true]);
+    assert_eq!(semis("let x = 5; // {\nx"), vec![true, false]);
+    assert_eq!(
+        semis("let x = {\n// }\n5 /* } */\n};\nx"),
+        vec![true, false]
+    );
+
+    // braces and semis in comments are not counted
+    assert_eq!(parse_program("fn a() { // }"), InputResult::More);
+    assert_eq!(parse_program("fn a() { /* } */"), InputResult::More);
+    assert_eq!(parse_program("/* unclosed"), InputResult::More);
+    assert_eq!(
+        determine_result("2+2 // ;", "2+2 // ;", false),
+        InputResult::Program(Input {
+            items: Vec::new(),
+            stmts: vec![Statement {
+                expr: "2 + 2".to_string(),
+                semi: false,
+            }],
+            crates: Vec::new()
+        })
+    );
+    assert_eq!(
+        determine_result("let a = 1; // }", "let a = 1; // }", false),
+        InputResult::More
+    );
+
+    match parse_program("fn a() -> u8 { // {\n3 /* { */ }") {
+        InputResult::Program(input) => {
+            assert_eq!(input.items.len(), 1);
+            assert!(input.stmts.is_empty());
+        }
+        x => panic!("expecting program, got {:?}", x),
+    }
+}
+
+#[test]
+fn unicode_input() {
+    let r = parse_program("let 数 = \"你好，世界\";");
+    match r {
+        InputResult::Program(input) => {
+            assert_eq!(input.stmts.len(), 1);
+            assert!(input.stmts[0].expr.contains("数"));
+            assert!(input.stmts[0].expr.contains("\"你好，世界\""));
+        }
+        _ => panic!("expecting program"),
+    }
+
+    assert_eq!(parse_program("let a = \"你好"), InputResult::More);
+}
+
+#[test]
+fn let_binding_name_test() {
+    assert_eq!(let_binding_name("let a = 1"), Some("a".to_string()));
+    assert_eq!(
+        let_binding_name("let mut b: Vec<u8> = vec![]"),
+        Some("b".to_string())
+    );
+    assert_eq!(let_binding_name("let (a, b) = (1, 2)"), None);
+    assert_eq!(let_binding_name("let ref a = 1"), None);
+    assert_eq!(let_binding_name("let a"), None);
+    assert_eq!(let_binding_name("a = 1"), None);
+    assert_eq!(let_binding_name("let a = 1; let b = 2"), None);
+}
+
+#[test]
+fn is_assertion_test() {
+    assert!(is_assertion("assert!(true)"));
+    assert!(is_assertion("assert_eq!(add(2, 3), 5)"));
+    assert!(is_assertion(" assert_ne ! (1, 2)"));
+    assert!(is_assertion("debug_assert!(a)"));
+    assert!(is_assertion("debug_assert_ne!(a, b)"));
+    assert!(!is_assertion("println!(\"assert\")"));
+    assert!(!is_assertion("assert_eq(1, 2)"));
+    assert!(!is_assertion("my::assert!(a)"));
+    assert!(!is_assertion("let a = assert!(true)"));
+    assert!(!is_assertion("assert_approx!(a, b)"));
+    assert!(!is_assertion("assertions!(a)"));
+    assert!(!is_assertion("assert!(a) == ()"));
+}
+
 #[test]
 fn fail_parse_program() {
     assert_eq!(
@@ -283,3 +541,20 @@ fn fail_parse_program() {
         InputResult::InputError("expected `;`".to_string())
     );
 }
+
+#[test]
+fn pattern_item_name_test() {
+    assert_eq!(
+        pattern_item_name("const out0: u8 = 1;"),
+        Some("out0".to_string())
+    );
+    assert_eq!(
+        pattern_item_name("static A: u8 = 1;"),
+        Some("A".to_string())
+    );
+    assert_eq!(pattern_item_name("struct A;"), Some("A".to_string()));
+    assert_eq!(pattern_item_name("struct A(u8);"), Some("A".to_string()));
+    assert_eq!(pattern_item_name("struct A { a: u8 }"), None);
+    assert_eq!(pattern_item_name("fn a() {}"), None);
+    assert_eq!(pattern_item_name("not an item"), None);
+}
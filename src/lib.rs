@@ -68,8 +68,9 @@
 //! - _racer-completion_: enable code completion using [`racer`](https://github.com/racer-rust/racer).
 //!     **Requires a nightly compiler**
 //! - _runnable_: papyrus can be _run_, without needing to manually handle repl states and output
+//! - _server_: serve a REPL session over a TCP socket, replying with JSON
 //!
-//! All features are enabled by default.
+//! All features except _server_ are enabled by default.
 //!
 //! ### Cargo
 //! Papyrus leverages installed binaries of both `cargo` and `rustc`. This requirement may lift in the
@@ -149,6 +150,8 @@ pub mod repl;
 /// Running the repl. Requires `runnable` feature.
 #[cfg(feature = "runnable")]
 pub mod run;
+#[cfg(feature = "server")]
+pub mod serve;
 
 /// Re-exports of most common types and modules.
 pub mod prelude {
@@ -94,11 +94,12 @@
 //!
 //! ## Behind the scenes
 //!
-//! - Papyrus takes the crate name you specify and will add this as `extern crate CRATE_NAME;` to the source file.
+//! - Papyrus takes the crate name you specify and, if [`LinkingConfiguration::auto_extern`] is set, will add this as `extern crate CRATE_NAME;` to the source file.
 //! - When setting the external crate name, the `rlib` library is found and copied into the compilation directory.
-//!   - Papyrus uses `std::env::current_exe()` to find the executing folder, and searches for the `rlib` file in that folder (`libCRATE_NAME.rlib`)
+//!   - Papyrus searches the directories listed in the `PAPYRUS_RLIB_PATH` environment variable (separated like `PATH`), in order, for the `rlib` file (`libCRATE_NAME.rlib`)
+//!   - Papyrus then uses `std::env::current_exe()` to find the executing folder, and searches for the `rlib` file in that folder
 //!   - Specify the path to the `rlib` library if it is located in a different folder
-//! - When compiling the REPL code, a rustc flag is set, linking the `rlib` such that `extern crate CRATE_NAME;` works.
+//! - When compiling the REPL code, a rustc flag is set, linking the `rlib` such that `CRATE_NAME` is in scope.
 //!
 //! ## Passing `MyStruct` data through
 //!
@@ -133,7 +134,8 @@
 //! Run this project (`cargo run`).
 //! It should spool up fine and prompt you with `papyrus=>`.
 //! Now you can try to use the linked data.
-//! The linked data is in a variable `app_data`. It is borrowed or mutably borrowed depending on the
+//! The linked data is in a variable `app_data` (this name can be changed with
+//! [`LinkingConfiguration::with_data_name`]). It is borrowed or mutably borrowed depending on the
 //! REPL state.
 //!
 //! ```sh
@@ -144,7 +146,9 @@
 //! ## Notes
 //! ### Panics
 //!
-//! To avoid crashing the application on a panic, `catch_unwind` is employed.
+//! To avoid crashing the application on a panic, `catch_unwind` is employed by default.
+//! This can be changed using [`PanicStrategy`], for instance when debugging linked code and the
+//! panic should propagate with a full backtrace.
 //! This function requires data that crosses the boundary be `UnwindSafe`, making `&` and `&mut` not valid data types.
 //! Papyrus uses `AssertUnwindSafe` wrappers to make this work, however it makes `app_data` vulnerable to breaking
 //! invariant states if a panic is triggered.
@@ -193,6 +197,14 @@
 //! it will still be using the consistent `kserd` dependency, although an astute user might try to
 //! implement `::kserd::ToKserd` which would break! At least at this point it is easy to back out
 //! changes in the temporary REPL session.
+//!
+//! ## Linking a Crate Which Depends on Papyrus
+//! A tool built on papyrus will often link its own crate, as in the example above, so the linked
+//! library depends on papyrus itself. This works as is, the library's papyrus is found in its
+//! `deps` folder. However, adding papyrus to the REPL with `extern crate papyrus;` would bring in a
+//! second copy of papyrus from crates.io, which does not match the one the library was built with.
+//! Papyrus detects this and errors on the input. As with other overlapping dependencies, re-export
+//! papyrus from the library with `pub use papyrus;` and refer to it as `some_lib::papyrus`.
 
 use std::collections::HashSet;
 use std::path::{Path, PathBuf};
@@ -216,6 +228,9 @@ pub struct LinkingConfiguration {
     /// Indicates a mutable block.
     pub mutable: bool,
 
+    /// The name the data is bound to in evaluated code, the default is `app_data`.
+    pub data_name: String,
+
     /// Additional external libraries to link.
     ///
     /// These are only precompiled libraries, it is preferable
@@ -229,6 +244,73 @@ pub struct LinkingConfiguration {
     /// It is sometimes necessary to have injected code, especially to solve dependency duplication
     /// issues. See [`the _linking_ module for a description`](crate::linking).
     pub persistent_module_code: String,
+
+    /// How panics in the evaluated code are handled.
+    pub panic_strategy: PanicStrategy,
+
+    /// Native (system) libraries to link, passed to the compiler as `-l kind=name`.
+    pub native_libs: Vec<(String, NativeLibKind)>,
+
+    /// Additional arguments for the linker, passed to the compiler as `-C link-arg=arg`.
+    pub link_args: Vec<String>,
+
+    /// The name of the generated evaluation function.
+    ///
+    /// The module path is prefixed to the name, so the default of `intern_eval` in the `lib`
+    /// module becomes `_lib_intern_eval`. Change this if it collides with a user defined
+    /// function.
+    pub eval_fn_name: String,
+
+    /// The prefix of the generated evaluation function signature, placed before the module path.
+    ///
+    /// The default of `_` gives `_lib_intern_eval`, setting it to `__papyrus_` gives
+    /// `__papyrus_lib_intern_eval`, which is easier to recognise in the emitted source.
+    pub eval_fn_prefix: String,
+
+    /// The prefix of the bindings results are assigned to, the default of `out` binds results as
+    /// `out0`, `out1`, and so on. Change this if it collides with a user defined item or linked
+    /// crate.
+    pub out_prefix: String,
+
+    /// The target triple to compile for, passed to cargo as `--target`.
+    ///
+    /// A target is only set if it differs from the host. Code compiled for another target cannot
+    /// be executed, so evaluations will only compile.
+    pub target: Option<String>,
+
+    /// Whether evaluated code has the full standard library in scope.
+    pub std_mode: StdMode,
+
+    /// Import commonly used `std` items into every module, the default is `false`.
+    ///
+    /// The items are `Cell`, `RefCell`, `BTreeMap`, `HashMap`, `HashSet`, `VecDeque`, `Rc`,
+    /// `Arc`, and `Duration`. They are glob imported, so items and imports of the same name in
+    /// evaluated code take precedence. Nothing is imported with [`StdMode::NoStd`].
+    pub auto_std_imports: bool,
+
+    /// Add `extern crate CRATE_NAME;` to the source code for each external library.
+    ///
+    /// From the 2018 edition, crates passed with `--extern` are in scope without an `extern crate`
+    /// item, so the default follows the edition evaluations are compiled with: `true` for 2015 and
+    /// `false` for 2018 onwards. Without the `extern crate` item, an aliased library is passed to
+    /// the compiler under its alias.
+    pub auto_extern: bool,
+
+    /// The amount of debug information compiled into evaluations, passed to the compiler as
+    /// `-C debuginfo=level`.
+    ///
+    /// Level `0` emits no debug information, `1` emits line tables only, and `2` emits full debug
+    /// information. The default of `1` keeps backtraces useful while compiling faster than `2`.
+    pub debuginfo: u8,
+
+    /// Run cargo with `--offline`, so it never accesses the network, the default is `false`.
+    ///
+    /// Crates referenced by evaluated code must already be in the local cargo cache.
+    pub offline: bool,
+
+    /// The linker to use, passed to the compiler as `-C linker=path`, such as `lld` or a cross
+    /// linker for the target. The default of `None` uses the compiler's default linker.
+    pub linker: Option<PathBuf>,
 }
 
 impl Default for LinkingConfiguration {
@@ -236,8 +318,86 @@ impl Default for LinkingConfiguration {
         Self {
             data_type: None,
             mutable: false,
+            data_name: String::from("app_data"),
             external_libs: HashSet::new(),
             persistent_module_code: String::new(),
+            panic_strategy: PanicStrategy::default(),
+            native_libs: Vec::new(),
+            link_args: Vec::new(),
+            eval_fn_name: String::from("intern_eval"),
+            eval_fn_prefix: String::from("_"),
+            out_prefix: String::from("out"),
+            target: None,
+            std_mode: StdMode::default(),
+            auto_std_imports: false,
+            auto_extern: crate::compile::EDITION == "2015",
+            debuginfo: 1,
+            offline: false,
+            linker: None,
+        }
+    }
+}
+
+/// How a panic in evaluated code is handled.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum PanicStrategy {
+    /// Catch the panic with `catch_unwind` and return it as an evaluation error. This is the
+    /// default.
+    Catch,
+    /// Do not catch the panic, letting it unwind into the calling application.
+    ///
+    /// The evaluation function uses the Rust ABI, so unwinding out of it is sound as long as the
+    /// evaluated code is compiled with the same toolchain as the application. The panic payload
+    /// is allocated by the evaluated library, so the library is not unloaded after it panics.
+    Propagate,
+    /// Compile the evaluated code with `-C panic=abort`, a panic will abort the process.
+    Abort,
+}
+
+impl Default for PanicStrategy {
+    fn default() -> Self {
+        PanicStrategy::Catch
+    }
+}
+
+/// The standard library available to evaluated code.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum StdMode {
+    /// The full standard library, this is the default.
+    Std,
+    /// Evaluate against `core` and `alloc` only.
+    ///
+    /// The generated crate is `#![no_std]` with `extern crate alloc;`, so the prelude is the
+    /// `core` prelude and `alloc` items (such as `alloc::vec::Vec`) must be named. `std` is still
+    /// linked so the evaluation can be loaded and its result returned, but inputs which use `std`
+    /// paths or `std` only macros (such as `println!`) are rejected.
+    NoStd,
+}
+
+impl Default for StdMode {
+    fn default() -> Self {
+        StdMode::Std
+    }
+}
+
+/// The kind of a native library to link.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum NativeLibKind {
+    /// A dynamic library, `-l dylib=name`.
+    Dylib,
+    /// A static library, `-l static=name`.
+    Static,
+    /// A macOS framework, `-l framework=name`.
+    Framework,
+}
+
+impl NativeLibKind {
+    /// The kind as passed to the compiler.
+    pub fn as_str(self) -> &'static str {
+        match self {
+            NativeLibKind::Dylib => "dylib",
+            NativeLibKind::Static => "static",
+            NativeLibKind::Framework => "framework",
         }
     }
 }
@@ -252,12 +412,132 @@ impl LinkingConfiguration {
         self
     }
 
+    /// Set the name the data is bound to in evaluated code, such as `world` for a signature of
+    /// `world: &mut crate::World`.
+    ///
+    /// The name must be a valid identifier and not a keyword.
+    pub fn with_data_name(mut self, name: &str) -> Result<Self, &'static str> {
+        validate_data_name(name)?;
+        self.data_name = name.to_string();
+        Ok(self)
+    }
+
+    /// Link a native library, such as `m` or `sqlite3`.
+    ///
+    /// The name must be plausible as a library name, consisting of alphanumeric characters or any
+    /// of `_-.+`.
+    pub fn with_native_lib(
+        mut self,
+        name: &str,
+        kind: NativeLibKind,
+    ) -> Result<Self, &'static str> {
+        validate_native_lib_name(name)?;
+        self.native_libs.push((name.to_string(), kind));
+        Ok(self)
+    }
+
+    /// Pass an argument to the linker.
+    pub fn with_link_arg(mut self, arg: String) -> Self {
+        self.link_args.push(arg);
+        self
+    }
+
+    /// Set whether `extern crate CRATE_NAME;` is added for each external library.
+    ///
+    /// See [`LinkingConfiguration::auto_extern`].
+    pub fn with_auto_extern(mut self, auto_extern: bool) -> Self {
+        self.auto_extern = auto_extern;
+        self
+    }
+
+    /// Set the linker to use.
+    ///
+    /// The linker must be a path to an existing file, or a program name found in `PATH`.
+    pub fn with_linker(mut self, linker: PathBuf) -> Result<Self, &'static str> {
+        validate_linker(&linker)?;
+        self.linker = Some(linker);
+        Ok(self)
+    }
+
+    /// Run cargo with `--offline`.
+    ///
+    /// See [`LinkingConfiguration::offline`].
+    pub fn with_offline(mut self, offline: bool) -> Self {
+        self.offline = offline;
+        self
+    }
+
+    /// Set the name of the generated evaluation function.
+    ///
+    /// The name must be a valid identifier, consisting of alphanumeric characters or `_`.
+    pub fn with_eval_fn_name(mut self, name: &str) -> Result<Self, &'static str> {
+        validate_eval_fn_name(name)?;
+        self.eval_fn_name = name.to_string();
+        Ok(self)
+    }
+
+    /// Set the prefix of the generated evaluation function signature.
+    ///
+    /// The prefix must be a valid identifier start, consisting of alphanumeric characters or `_`
+    /// and not starting with a digit.
+    pub fn with_eval_fn_prefix(mut self, prefix: &str) -> Result<Self, &'static str> {
+        validate_eval_fn_prefix(prefix)?;
+        self.eval_fn_prefix = prefix.to_string();
+        Ok(self)
+    }
+
+    /// Set the prefix of the bindings results are assigned to.
+    ///
+    /// The prefix must be a valid identifier which does not end with a digit.
+    pub fn with_out_prefix(mut self, prefix: &str) -> Result<Self, &'static str> {
+        validate_out_prefix(prefix)?;
+        self.out_prefix = prefix.to_string();
+        Ok(self)
+    }
+
+    /// Set the amount of debug information compiled into evaluations.
+    ///
+    /// The level must be `0`, `1`, or `2`. See [`LinkingConfiguration::debuginfo`].
+    pub fn with_debuginfo(mut self, level: u8) -> Result<Self, &'static str> {
+        validate_debuginfo(level)?;
+        self.debuginfo = level;
+        Ok(self)
+    }
+
+    /// The name is a result binding, such as `out0` with the default prefix.
+    pub fn is_out_name(&self, name: &str) -> bool {
+        name.starts_with(self.out_prefix.as_str()) && {
+            let n = &name[self.out_prefix.len()..];
+            !n.is_empty() && n.chars().all(|c| c.is_ascii_digit())
+        }
+    }
+
+    /// The first linked crate or data name which collides with the result bindings.
+    pub fn out_collision(&self) -> Option<&str> {
+        let data_name = self.data_type.as_ref().map(|_| self.data_name.as_str());
+        self.external_libs
+            .iter()
+            .map(Extern::name)
+            .chain(data_name)
+            .find(|x| self.is_out_name(x))
+    }
+
+    /// The first linked crate which depends on papyrus, such as a tool built on papyrus linking
+    /// its own crate.
+    pub fn papyrus_dependent(&self) -> Option<&str> {
+        self.external_libs
+            .iter()
+            .find(|x| x.depends_on("papyrus"))
+            .map(Extern::name)
+    }
+
     /// Constructs the function arguments signature.
     /// Appends result to buffer.
     pub fn construct_fn_args(&self, buf: &mut String) {
         if let Some(d) = &self.data_type {
             // matches pfh::compile::execute::DataFunc definition.
-            buf.push_str("app_data: &"); // 11 len
+            buf.push_str(&self.data_name);
+            buf.push_str(": &"); // 3 len
             if self.mutable {
                 buf.push_str("mut ");
             }
@@ -271,11 +551,115 @@ impl LinkingConfiguration {
     pub fn construct_fn_args_length(&self) -> usize {
         self.data_type
             .as_ref()
-            .map(|d| 11 + d.len() + if self.mutable { 4 } else { 0 })
+            .map(|d| self.data_name.len() + 3 + d.len() + if self.mutable { 4 } else { 0 })
             .unwrap_or(0)
     }
 }
 
+pub(crate) fn validate_native_lib_name(name: &str) -> Result<(), &'static str> {
+    let valid = !name.is_empty()
+        && name
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || "_-.+".contains(c));
+
+    if valid {
+        Ok(())
+    } else {
+        Err("native library name is not valid")
+    }
+}
+
+/// Rust keywords, including reserved keywords and `_`, which can not be used as identifiers.
+pub(crate) const KEYWORDS: &[&str] = &[
+    "_", "abstract", "as", "async", "await", "become", "box", "break", "const", "continue",
+    "crate", "do", "dyn", "else", "enum", "extern", "false", "final", "fn", "for", "if", "impl",
+    "in", "let", "loop", "macro", "match", "mod", "move", "mut", "override", "priv", "pub", "ref",
+    "return", "self", "Self", "static", "struct", "super", "trait", "true", "try", "type",
+    "typeof", "unsafe", "unsized", "use", "virtual", "where", "while", "yield",
+];
+
+pub(crate) fn validate_data_name(name: &str) -> Result<(), &'static str> {
+    let valid = !name.is_empty()
+        && !name.starts_with(|c: char| c.is_ascii_digit())
+        && name.chars().all(|c| c.is_ascii_alphanumeric() || c == '_');
+
+    if !valid {
+        Err("data name is not a valid identifier")
+    } else if KEYWORDS.contains(&name) {
+        Err("data name is a reserved keyword")
+    } else {
+        Ok(())
+    }
+}
+
+pub(crate) fn validate_out_prefix(prefix: &str) -> Result<(), &'static str> {
+    let valid = !prefix.is_empty()
+        && !prefix.starts_with(|c: char| c.is_ascii_digit())
+        && prefix
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '_');
+
+    if !valid {
+        Err("out prefix is not a valid identifier")
+    } else if prefix.ends_with(|c: char| c.is_ascii_digit()) {
+        Err("out prefix must not end with a digit")
+    } else {
+        Ok(())
+    }
+}
+
+pub(crate) fn validate_debuginfo(level: u8) -> Result<(), &'static str> {
+    if level <= 2 {
+        Ok(())
+    } else {
+        Err("debuginfo level must be 0, 1, or 2")
+    }
+}
+
+pub(crate) fn validate_linker(linker: &Path) -> Result<(), &'static str> {
+    fn is_file(path: &Path) -> bool {
+        path.is_file() || cfg!(windows) && path.with_extension("exe").is_file()
+    }
+
+    let found = if linker.is_absolute() || linker.components().count() > 1 {
+        is_file(linker)
+    } else {
+        std::env::var_os("PATH")
+            .map(|paths| std::env::split_paths(&paths).any(|dir| is_file(&dir.join(linker))))
+            .unwrap_or(false)
+    };
+
+    if found {
+        Ok(())
+    } else {
+        Err("linker was not found")
+    }
+}
+
+pub(crate) fn validate_eval_fn_name(name: &str) -> Result<(), &'static str> {
+    let valid = !name.is_empty() && name.chars().all(|c| c.is_ascii_alphanumeric() || c == '_');
+
+    if valid {
+        Ok(())
+    } else {
+        Err("evaluation function name is not a valid identifier")
+    }
+}
+
+pub(crate) fn validate_eval_fn_prefix(prefix: &str) -> Result<(), &'static str> {
+    let starts_ok = prefix.chars().next().map_or(false, |c| !c.is_ascii_digit());
+    let valid = starts_ok
+        && prefix
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '_');
+
+    if valid {
+        Ok(())
+    } else {
+        Err("evaluation function prefix is not a valid identifier")
+    }
+}
+
 /// Represents an externally linked library.
 ///
 /// The structure holds a path to an `lib*.rlib` library. The path
@@ -312,8 +696,9 @@ impl Extern {
     }
 
     /// Uses the executable name to derive the library name, and
-    /// returns the external linking using this. _The executable and library
-    /// must be in the same folder_.
+    /// returns the external linking using this. _The library must be in
+    /// a directory listed in `PAPYRUS_RLIB_PATH` or the same folder as the
+    /// executable_.
     ///
     /// This is a conveniance function if the library name is the same
     /// as the executeable.
@@ -397,6 +782,12 @@ impl Extern {
         self.alias
     }
 
+    /// The name the library is referred to in evaluated code, the alias if there is one, otherwise
+    /// the library name.
+    pub fn name(&self) -> &str {
+        self.alias.unwrap_or_else(|| self.lib_name())
+    }
+
     /// The canoncialized library path (in `lib*.rlib` format).
     pub fn lib_path(&self) -> &Path {
         self.path.as_path()
@@ -407,6 +798,18 @@ impl Extern {
         self.path.parent().unwrap().join("deps") // this has been validated already.
     }
 
+    /// Whether the library depends on the crate `crate_name`, that is the `deps` folder contains a
+    /// `lib*-<hash>.rlib` for it.
+    pub fn depends_on(&self, crate_name: &str) -> bool {
+        let prefix = format!("lib{}-", crate_name.replace('-', "_"));
+        fs::read_dir(self.deps_path())
+            .into_iter()
+            .flatten()
+            .filter_map(Result::ok)
+            .filter_map(|entry| entry.file_name().into_string().ok())
+            .any(|file| file.starts_with(&prefix) && file.ends_with(".rlib"))
+    }
+
     /// Append the buffer with the code representation.
     pub fn construct_code_str(&self, buf: &mut String) {
         buf.push_str("extern crate "); // 13
@@ -444,13 +847,83 @@ impl std::hash::Hash for Extern {
     }
 }
 
+/// Environment variable listing directories to search for rlibs, before the executable directory.
+const RLIB_PATH_VAR: &str = "PAPYRUS_RLIB_PATH";
+
 fn get_rlib_path(crate_name: &str) -> io::Result<PathBuf> {
     let lib_name = format!("lib{}.rlib", crate_name);
-    let exe = std::env::current_exe()?;
-    fs::read_dir(exe.parent().expect("files should always have a parent"))?
-        .filter(|entry| entry.is_ok())
-        .map(|entry| entry.expect("filtered some").path())
-        .find(|path| path.ends_with(&lib_name))
+
+    let env_paths = std::env::var_os(RLIB_PATH_VAR);
+    let found = rlib_search_dirs(env_paths.as_ref().map(|x| x.as_os_str()))
+        .iter()
+        .find_map(|dir| find_rlib_in(dir, &lib_name).ok());
+    if let Some(path) = found {
+        return Ok(path);
+    }
+
+    let dir = exe_dir()?;
+    find_rlib_in(&dir, &lib_name)
+}
+
+/// The existing directories in a `PATH` like list of paths.
+fn rlib_search_dirs(paths: Option<&std::ffi::OsStr>) -> Vec<PathBuf> {
+    paths
+        .map(std::env::split_paths)
+        .into_iter()
+        .flatten()
+        .filter(|dir| {
+            let exists = dir.is_dir();
+            if !exists {
+                debug!(
+                    "skipping rlib search directory '{}', it does not exist",
+                    dir.display()
+                );
+            }
+            exists
+        })
+        .collect()
+}
+
+/// The directory the current executable lives in.
+///
+/// Some sandboxed environments report an executable path without a parent, or one which cannot
+/// be queried at all. Both cases surface as an error rather than a panic.
+fn exe_dir() -> io::Result<PathBuf> {
+    let exe = std::env::current_exe().map_err(|e| {
+        io::Error::new(
+            e.kind(),
+            format!("could not resolve the current executable: {}", e),
+        )
+    })?;
+
+    exe_parent(&exe).map(Path::to_path_buf)
+}
+
+fn exe_parent(exe: &Path) -> io::Result<&Path> {
+    exe.parent()
+        .filter(|p| !p.as_os_str().is_empty())
+        .ok_or_else(|| {
+            io::Error::new(
+                io::ErrorKind::NotFound,
+                format!(
+                    "could not resolve the directory of executable '{}', specify the rlib path instead",
+                    exe.display()
+                ),
+            )
+        })
+}
+
+fn find_rlib_in(dir: &Path, lib_name: &str) -> io::Result<PathBuf> {
+    fs::read_dir(dir)
+        .map_err(|e| {
+            io::Error::new(
+                e.kind(),
+                format!("could not read directory '{}': {}", dir.display(), e),
+            )
+        })?
+        .filter_map(Result::ok)
+        .map(|entry| entry.path())
+        .find(|path| path.ends_with(lib_name))
         .ok_or_else(|| {
             io::Error::new(
                 io::ErrorKind::NotFound,
@@ -463,6 +936,153 @@ fn get_rlib_path(crate_name: &str) -> io::Result<PathBuf> {
 mod tests {
     use super::*;
 
+    #[test]
+    fn native_lib_test() {
+        let config = LinkingConfiguration::default()
+            .with_native_lib("m", NativeLibKind::Dylib)
+            .unwrap()
+            .with_native_lib("stdc++", NativeLibKind::Static)
+            .unwrap()
+            .with_link_arg("-Wl,--as-needed".to_string());
+        assert_eq!(
+            config.native_libs,
+            vec![
+                ("m".to_string(), NativeLibKind::Dylib),
+                ("stdc++".to_string(), NativeLibKind::Static)
+            ]
+        );
+        assert_eq!(config.link_args, vec!["-Wl,--as-needed".to_string()]);
+
+        let r = LinkingConfiguration::default().with_native_lib("", NativeLibKind::Dylib);
+        assert_eq!(r.err(), Some("native library name is not valid"));
+        let r = LinkingConfiguration::default().with_native_lib("m -C foo", NativeLibKind::Dylib);
+        assert_eq!(r.err(), Some("native library name is not valid"));
+    }
+
+    #[test]
+    fn eval_fn_name_test() {
+        let config = LinkingConfiguration::default();
+        assert_eq!(config.eval_fn_name, "intern_eval");
+
+        let config = config.with_eval_fn_name("papyrus_eval_2").unwrap();
+        assert_eq!(config.eval_fn_name, "papyrus_eval_2");
+
+        let r = LinkingConfiguration::default().with_eval_fn_name("");
+        assert_eq!(
+            r.err(),
+            Some("evaluation function name is not a valid identifier")
+        );
+        let r = LinkingConfiguration::default().with_eval_fn_name("a() {}");
+        assert_eq!(
+            r.err(),
+            Some("evaluation function name is not a valid identifier")
+        );
+    }
+
+    #[test]
+    fn eval_fn_prefix_test() {
+        let config = LinkingConfiguration::default();
+        assert_eq!(config.eval_fn_prefix, "_");
+
+        let config = config.with_eval_fn_prefix("__papyrus_").unwrap();
+        assert_eq!(config.eval_fn_prefix, "__papyrus_");
+        let config = config.with_eval_fn_prefix("repl").unwrap();
+        assert_eq!(config.eval_fn_prefix, "repl");
+
+        for prefix in &["", "2_", "a b", "pub fn "] {
+            let r = LinkingConfiguration::default().with_eval_fn_prefix(prefix);
+            assert_eq!(
+                r.err(),
+                Some("evaluation function prefix is not a valid identifier")
+            );
+        }
+    }
+
+    #[test]
+    fn data_name_test() {
+        let config = unsafe { LinkingConfiguration::default().with_data("crate::World") };
+        let mut buf = String::new();
+        config.construct_fn_args(&mut buf);
+        assert_eq!(buf, "app_data: &crate::World");
+        assert_eq!(config.construct_fn_args_length(), buf.len());
+
+        let mut config = config.with_data_name("world").unwrap();
+        config.mutable = true;
+        let mut buf = String::new();
+        config.construct_fn_args(&mut buf);
+        assert_eq!(buf, "world: &mut crate::World");
+        assert_eq!(config.construct_fn_args_length(), buf.len());
+
+        // associated type paths are used verbatim
+        let config = unsafe {
+            LinkingConfiguration::default().with_data("<crate::World as crate::Store>::Data")
+        };
+        let mut buf = String::new();
+        config.construct_fn_args(&mut buf);
+        assert_eq!(buf, "app_data: &<crate::World as crate::Store>::Data");
+        assert_eq!(config.construct_fn_args_length(), buf.len());
+
+        let r = LinkingConfiguration::default().with_data_name("1world");
+        assert_eq!(r.err(), Some("data name is not a valid identifier"));
+        let r = LinkingConfiguration::default().with_data_name("a: &u8, b");
+        assert_eq!(r.err(), Some("data name is not a valid identifier"));
+        let r = LinkingConfiguration::default().with_data_name("match");
+        assert_eq!(r.err(), Some("data name is a reserved keyword"));
+    }
+
+    #[test]
+    fn out_prefix_test() {
+        let config = LinkingConfiguration::default();
+        assert!(config.is_out_name("out0"));
+        assert!(config.is_out_name("out12"));
+        assert!(!config.is_out_name("out"));
+        assert!(!config.is_out_name("out_ty"));
+        assert!(!config.is_out_name("output1"));
+        assert_eq!(config.out_collision(), None);
+
+        let config = config.with_out_prefix("res").unwrap();
+        assert!(config.is_out_name("res3"));
+        assert!(!config.is_out_name("out3"));
+
+        let mut config = unsafe { config.with_data("u8") }
+            .with_data_name("res1")
+            .unwrap();
+        assert_eq!(config.out_collision(), Some("res1"));
+        config.data_type = None;
+        assert_eq!(config.out_collision(), None);
+
+        let r = LinkingConfiguration::default().with_out_prefix("r-");
+        assert_eq!(r.err(), Some("out prefix is not a valid identifier"));
+        let r = LinkingConfiguration::default().with_out_prefix("r1");
+        assert_eq!(r.err(), Some("out prefix must not end with a digit"));
+    }
+
+    #[test]
+    fn debuginfo_test() {
+        assert_eq!(LinkingConfiguration::default().debuginfo, 1);
+        let config = LinkingConfiguration::default().with_debuginfo(2).unwrap();
+        assert_eq!(config.debuginfo, 2);
+        let r = LinkingConfiguration::default().with_debuginfo(3);
+        assert_eq!(r.err(), Some("debuginfo level must be 0, 1, or 2"));
+    }
+
+    #[test]
+    fn linker_test() {
+        let dir = Path::new("target/testing/linker_test");
+        fs::create_dir_all(dir).unwrap();
+        fs::write(dir.join("my-linker"), b"").unwrap();
+
+        let config = LinkingConfiguration::default()
+            .with_linker(dir.join("my-linker"))
+            .unwrap();
+        assert_eq!(config.linker, Some(dir.join("my-linker")));
+
+        let r = LinkingConfiguration::default().with_linker(dir.join("no-such-linker"));
+        assert_eq!(r.err(), Some("linker was not found"));
+        let r = LinkingConfiguration::default().with_linker("papyrus-no-such-linker".into());
+        assert_eq!(r.err(), Some("linker was not found"));
+    }
+
     #[test]
     fn get_rlib_path_test() {
         let r = get_rlib_path("some_crate");
@@ -472,6 +1092,68 @@ mod tests {
         assert_eq!(e.to_string(), "did not find file: 'libsome_crate.rlib'");
     }
 
+    #[test]
+    fn rlib_search_dirs_test() {
+        let dir = Path::new("target/testing/rlib_search_dirs_test");
+        fs::create_dir_all(dir).unwrap();
+        fs::write(dir.join("libsome_crate.rlib"), b"").unwrap();
+
+        assert!(rlib_search_dirs(None).is_empty());
+
+        let paths =
+            std::env::join_paths(&[Path::new("target/testing/no-such-dir"), dir, Path::new("")])
+                .unwrap();
+        let dirs = rlib_search_dirs(Some(&paths));
+        assert_eq!(dirs, vec![dir.to_path_buf()]);
+        assert!(find_rlib_in(&dirs[0], "libsome_crate.rlib").is_ok());
+    }
+
+    #[test]
+    fn exe_without_parent_test() {
+        let e = exe_parent(Path::new("papyrus")).unwrap_err();
+        assert_eq!(e.kind(), io::ErrorKind::NotFound);
+        assert_eq!(
+            e.to_string(),
+            "could not resolve the directory of executable 'papyrus', specify the rlib path instead"
+        );
+
+        let e = exe_parent(Path::new("/")).unwrap_err();
+        assert_eq!(e.kind(), io::ErrorKind::NotFound);
+
+        assert_eq!(
+            exe_parent(Path::new("/bin/papyrus")).unwrap(),
+            Path::new("/bin")
+        );
+    }
+
+    #[test]
+    fn find_rlib_in_missing_dir_test() {
+        let e = find_rlib_in(Path::new("target/testing/no-such-dir"), "libfoo.rlib").unwrap_err();
+        assert_eq!(e.kind(), io::ErrorKind::NotFound);
+        assert!(e
+            .to_string()
+            .starts_with("could not read directory 'target/testing/no-such-dir'"));
+    }
+
+    #[test]
+    fn depends_on_test() {
+        let dir = Path::new("target/testing/depends_on_test");
+        fs::create_dir_all(dir.join("deps")).unwrap();
+        fs::write(dir.join("libtool.rlib"), b"").unwrap();
+        fs::write(dir.join("deps/libpapyrus-0123abcd.rlib"), b"").unwrap();
+        fs::write(dir.join("deps/libpapyrus_ext-0123abcd.rlib"), b"").unwrap();
+
+        let e = Extern::new(dir.join("libtool.rlib")).unwrap();
+        assert!(e.depends_on("papyrus"));
+        assert!(e.depends_on("papyrus-ext"));
+        assert!(!e.depends_on("kserd"));
+
+        let mut config = LinkingConfiguration::default();
+        assert_eq!(config.papyrus_dependent(), None);
+        config.external_libs.insert(e);
+        assert_eq!(config.papyrus_dependent(), Some("tool"));
+    }
+
     #[test]
     fn construct_code_str_test() {
         let mut e = Extern {
@@ -79,7 +79,8 @@ use papyrus::*;
 fn main() {
     windows_term_hack();
 
-    let repl = repl!();
+    let mut repl = repl!();
+    repl.data.with_banner(Some(repl::default_banner()));
 
     let app_data = &mut ();
 
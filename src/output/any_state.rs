@@ -6,6 +6,46 @@ impl<S> Output<S> {
         &self.buf
     }
 
+    /// Full buffer as plain text, with ANSI escape sequences such as colouring removed.
+    ///
+    /// This is suitable for copying or saving the whole output.
+    pub fn plain_buffer(&self) -> String {
+        strip_ansi(&self.buf)
+    }
+
+    /// The OSC 8 hyperlinks in the buffer, in the order they appear.
+    ///
+    /// A hyperlink is written as `ESC ] 8 ; ; URL ST text ESC ] 8 ; ; ST`, where `ST` is `ESC \` or
+    /// `BEL`. The text of each link is plain text, as in [`plain_buffer`], which also keeps the
+    /// text of links. A link which is not closed runs to the end of the buffer.
+    ///
+    /// [`plain_buffer`]: Output::plain_buffer
+    pub fn hyperlinks(&self) -> Vec<Hyperlink> {
+        let mut links = Vec::new();
+        let mut open: Option<Hyperlink> = None;
+        parse_ansi(&self.buf, |x| match x {
+            Ansi::Char(ch) => {
+                if let Some(link) = &mut open {
+                    link.text.push(ch);
+                }
+            }
+            Ansi::Osc(osc) => {
+                if osc.starts_with("8;") {
+                    links.extend(open.take());
+                    let url = osc[2..].splitn(2, ';').nth(1).unwrap_or_default();
+                    if !url.is_empty() {
+                        open = Some(Hyperlink {
+                            url: url.to_string(),
+                            text: String::new(),
+                        });
+                    }
+                }
+            }
+        });
+        links.extend(open);
+        links
+    }
+
     /// Get the contents of the line at index `idx`.
     pub fn line(&self, idx: usize) -> Option<&str> {
         use std::cmp::Ordering::*;
@@ -43,6 +83,15 @@ impl<S> Output<S> {
         rx
     }
 
+    /// A sender to the current listener, if any, for host code to inject its own line changes.
+    ///
+    /// Changes are received in the order they are sent, interleaved with the changes of the
+    /// output at that time. A line is sent as a [`OutputChange::CurrentLine`] followed by a
+    /// [`OutputChange::NewLine`]. Injected changes are not added to the output buffer.
+    pub fn sender(&self) -> Option<Sender> {
+        self.tx.clone()
+    }
+
     /// Close the sending channel.
     pub fn close(&mut self) {
         self.tx = None;
@@ -54,3 +103,96 @@ impl Default for Output<Read> {
         Self::new()
     }
 }
+
+/// Removes ANSI escape sequences. Control sequences (`ESC [ ... final`) and operating system
+/// commands (`ESC ] ... ST`), such as the ends of OSC 8 hyperlinks, are removed entirely, other
+/// escapes remove the character following `ESC`.
+pub(crate) fn strip_ansi(s: &str) -> String {
+    let mut plain = String::with_capacity(s.len());
+    parse_ansi(s, |x| {
+        if let Ansi::Char(ch) = x {
+            plain.push(ch);
+        }
+    });
+    plain
+}
+
+enum Ansi<'a> {
+    /// A character which is not part of an escape sequence.
+    Char(char),
+    /// The contents of an operating system command, between `ESC ]` and the terminator.
+    Osc(&'a str),
+}
+
+/// Splits `s` into characters and operating system commands, skipping other escape sequences.
+fn parse_ansi<'a>(s: &'a str, mut f: impl FnMut(Ansi<'a>)) {
+    let mut chars = s.char_indices();
+    while let Some((_, ch)) = chars.next() {
+        if ch != '\u{1b}' {
+            f(Ansi::Char(ch));
+            continue;
+        }
+
+        match chars.next() {
+            Some((_, '[')) => {
+                chars.find(|(_, c)| ('@'..='~').contains(c));
+            }
+            Some((i, ']')) => {
+                // terminated by BEL or ST (`ESC \`)
+                let start = i + 1;
+                let mut end = s.len();
+                while let Some((j, c)) = chars.next() {
+                    if c == '\u{7}' || c == '\u{1b}' {
+                        end = j;
+                        if c == '\u{1b}' {
+                            chars.next();
+                        }
+                        break;
+                    }
+                }
+                f(Ansi::Osc(&s[start..end]));
+            }
+            _ => (),
+        }
+    }
+}
+
+#[test]
+fn strip_ansi_test() {
+    assert_eq!(strip_ansi("plain\ntext"), "plain\ntext");
+    assert_eq!(
+        strip_ansi("\u{1b}[32mpapyrus\u{1b}[0m \u{1b}[1;34m[out0]\u{1b}[0m: 2"),
+        "papyrus [out0]: 2"
+    );
+    assert_eq!(strip_ansi("a\u{1b}[2Kb\u{1b}7c"), "abc");
+    assert_eq!(
+        strip_ansi(
+            "see \u{1b}]8;;https://docs.rs\u{1b}\\docs\u{1b}]8;;\u{1b}\\ and \
+             \u{1b}]0;title\u{7}more"
+        ),
+        "see docs and more"
+    );
+}
+
+#[test]
+fn hyperlinks_test() {
+    let mut output = Output::new().into_write();
+    output.write_str(
+        "\u{1b}]8;;https://docs.rs\u{1b}\\\u{1b}[1mdocs\u{1b}[0m\u{1b}]8;;\u{1b}\\ and \
+         \u{1b}]8;id=1;https://crates.io\u{7}crates",
+    );
+    assert_eq!(output.plain_buffer(), "docs and crates");
+    assert_eq!(
+        output.hyperlinks(),
+        vec![
+            Hyperlink {
+                url: "https://docs.rs".to_string(),
+                text: "docs".to_string()
+            },
+            Hyperlink {
+                url: "https://crates.io".to_string(),
+                text: "crates".to_string()
+            },
+        ]
+    );
+}
@@ -82,6 +82,10 @@
 //!                     lock.flush().unwrap();
 //!                 }
 //!                 OutputChange::NewLine => writeln!(&mut stdout, "").unwrap(),
+//!                 OutputChange::Clear => {
+//!                     write!(&mut stdout, "\x1b[2J\x1b[H").unwrap();
+//!                     stdout.flush().unwrap();
+//!                 }
 //!             }
 //!         }
 //!     })
@@ -206,6 +210,11 @@
 //!                     output.push('\n');
 //!                     pos = output.len();
 //!                 }
+//!                 OutputChange::Clear => {
+//!                     output.clear();
+//!                     pos = 0;
+//!                     std::fs::write("repl-output.txt", &output).unwrap();
+//!                 }
 //!             }
 //!         }
 //!     })
@@ -269,11 +278,17 @@ mod any_state;
 mod read;
 mod write;
 
+pub(crate) use any_state::strip_ansi;
+
 use crossbeam_channel as channel;
+use std::borrow::Cow;
 
 /// Line change receiving end.
 pub type Receiver = channel::Receiver<OutputChange>;
 
+/// Line change sending end, see [`Output::sender`].
+pub type Sender = channel::Sender<OutputChange>;
+
 /// Represents a buffered output from the repl.
 ///
 /// Output can be either the `Read` or `Write` states,
@@ -293,15 +308,70 @@ pub struct Output<S> {
     lines_pos: Vec<usize>,
 
     tx: Option<channel::Sender<OutputChange>>,
+
+    /// The encoding of bytes written through `io::Write`.
+    encoding: Encoding,
+}
+
+/// The encoding used to decode bytes written to an [`Output`] through [`std::io::Write`].
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum Encoding {
+    /// UTF-8, invalid sequences are replaced with `U+FFFD`. This is the default.
+    Utf8,
+    /// ISO-8859-1 (Latin-1), each byte is the character with the same code point.
+    Latin1,
+}
+
+impl Default for Encoding {
+    fn default() -> Self {
+        Encoding::Utf8
+    }
+}
+
+impl Encoding {
+    /// Decode `bytes` into a string.
+    ///
+    /// # Example
+    /// ```rust
+    /// # use papyrus::output::Encoding;
+    /// assert_eq!(Encoding::Latin1.decode(b"caf\xe9"), "café");
+    /// assert_eq!(Encoding::Utf8.decode(b"caf\xe9"), "caf\u{fffd}");
+    /// ```
+    pub fn decode(self, bytes: &[u8]) -> Cow<str> {
+        match self {
+            Encoding::Utf8 => String::from_utf8_lossy(bytes),
+            Encoding::Latin1 => Cow::Owned(bytes.iter().map(|&b| char::from(b)).collect()),
+        }
+    }
+}
+
+/// A hyperlink written to an [`Output`] with an OSC 8 escape sequence.
+///
+/// Terminals which support OSC 8 show the text as a link. See [`Output::hyperlinks`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Hyperlink {
+    /// The target of the link.
+    pub url: String,
+    /// The text shown for the link, without escape sequences.
+    pub text: String,
 }
 
 /// Line change event.
+///
+/// # Ordering
+/// Changes are sent in the order they are written to the [`Output`]. Evaluated code does not write
+/// to the output; anything it prints goes directly to the process's stdout. The generated code
+/// flushes stdout before returning, and the result line (`[out#]`) is only written once
+/// evaluation has finished. So anything the evaluated code prints comes _before_ the
+/// `CurrentLine` change that holds the result.
 #[derive(Debug, PartialEq)]
 pub enum OutputChange {
     /// A change was made on the current line.
     CurrentLine(String),
     /// Output is on a new line now.
     NewLine,
+    /// The output was cleared, all previous lines are discarded.
+    Clear,
 }
 
 /// Only read functions available.
@@ -395,6 +465,15 @@ impl<S> Output<S> {
             }
         }
     }
+
+    fn send_clear(&mut self) {
+        if let Some(tx) = self.tx.as_ref() {
+            match tx.try_send(OutputChange::Clear) {
+                Ok(_) => (),
+                Err(_) => self.tx = None, // receiver disconnected, stop sending msgs
+            }
+        }
+    }
 }
 
 #[cfg(test)]
@@ -501,6 +580,7 @@ mod tests {
                     lines.last_mut().map(|x| *x = s);
                 }
                 OutputChange::NewLine => lines.push(String::new()),
+                OutputChange::Clear => lines = vec![String::new()],
             }
         }
 
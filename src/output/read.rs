@@ -16,13 +16,18 @@ impl Output<Read> {
             buf: String::new(),
             lines_pos: Vec::new(),
             tx: None,
+            encoding: Encoding::default(),
         }
     }
 
     /// Finished read state, move to write.
     pub fn into_write(self) -> Output<Write> {
         let Output {
-            buf, lines_pos, tx, ..
+            buf,
+            lines_pos,
+            tx,
+            encoding,
+            ..
         } = self;
 
         let state = Write;
@@ -32,6 +37,7 @@ impl Output<Read> {
             buf,
             lines_pos,
             tx,
+            encoding,
         }
     }
 
@@ -8,7 +8,11 @@ impl Output<Write> {
     /// The input buffer is initialised as empty.
     pub fn into_read(self) -> Output<Read> {
         let Output {
-            buf, lines_pos, tx, ..
+            buf,
+            lines_pos,
+            tx,
+            encoding,
+            ..
         } = self;
 
         let state = Read {
@@ -24,9 +28,16 @@ impl Output<Write> {
             buf,
             lines_pos,
             tx,
+            encoding,
         }
     }
 
+    /// Set the encoding used to decode bytes written through [`io::Write`]. The default is
+    /// [`Encoding::Utf8`].
+    pub fn set_encoding(&mut self, encoding: Encoding) {
+        self.encoding = encoding;
+    }
+
     /// Writes the string contents to the end of the buffer.
     ///
     /// # Line Changes
@@ -75,11 +86,34 @@ impl Output<Write> {
 
         self.send_line_chg();
     }
+
+    /// Clear the whole buffer, discarding all previous lines.
+    ///
+    /// # Line Changes
+    /// Triggers a clear event.
+    ///
+    /// # Examples
+    /// ```rust
+    /// # use papyrus::output::Output;
+    ///
+    /// let mut o = Output::new().into_write();
+    ///
+    /// o.write_str("Hello\nworld");
+    /// o.clear();
+    /// assert_eq!(o.buffer(), "");
+    /// assert_eq!(o.lines_len(), 1);
+    /// ```
+    pub fn clear(&mut self) {
+        self.buf.clear();
+        self.lines_pos.clear();
+
+        self.send_clear();
+    }
 }
 
 impl io::Write for Output<Write> {
     fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
-        let s = String::from_utf8_lossy(buf);
+        let s = self.encoding.decode(buf);
         self.push_str(&s);
         Ok(buf.len())
     }
@@ -132,4 +166,84 @@ mod tests {
             ]
         );
     }
+
+    #[test]
+    fn injected_changes() {
+        let mut o = Output::new().into_write();
+
+        let rx = o.listen();
+        let tx = o.sender().unwrap();
+
+        o.write_line("Hello");
+        tx.send(OutputChange::CurrentLine("status".to_owned()))
+            .unwrap();
+        tx.send(OutputChange::NewLine).unwrap();
+        o.write_line("world");
+
+        drop(tx);
+        o.close();
+
+        let msgs = rx.iter().collect::<Vec<_>>();
+
+        assert_eq!(o.buffer(), "Hello\nworld\n");
+
+        assert_eq!(
+            &msgs,
+            &[
+                OutputChange::CurrentLine("Hello".to_owned()),
+                OutputChange::NewLine,
+                OutputChange::CurrentLine("status".to_owned()),
+                OutputChange::NewLine,
+                OutputChange::CurrentLine("world".to_owned()),
+                OutputChange::NewLine
+            ]
+        );
+    }
+
+    #[test]
+    fn clearing() {
+        let mut o = Output::new().into_write();
+
+        let rx = o.listen();
+
+        o.write_str("Hello\nworld");
+        o.clear();
+        o.write_str("again");
+
+        o.close();
+
+        let msgs = rx.iter().collect::<Vec<_>>();
+
+        assert_eq!(o.buffer(), "again");
+        assert_eq!(o.lines_len(), 1);
+
+        assert_eq!(
+            &msgs,
+            &[
+                OutputChange::CurrentLine("Hello".to_owned()),
+                OutputChange::NewLine,
+                OutputChange::CurrentLine("world".to_owned()),
+                OutputChange::Clear,
+                OutputChange::CurrentLine("again".to_owned()),
+            ]
+        );
+    }
+
+    #[test]
+    fn decoding_written_bytes() {
+        use std::io::Write;
+
+        let mut o = Output::new().into_write();
+        o.write_all(b"caf\xe9\n").unwrap();
+        assert_eq!(o.buffer(), "caf\u{fffd}\n");
+
+        o.set_encoding(Encoding::Latin1);
+        o.write_all(b"caf\xe9").unwrap();
+        assert_eq!(o.buffer(), "caf\u{fffd}\ncafé");
+
+        // the encoding is kept between states
+        let mut o = o.into_read().into_write();
+        o.write_all(b"\xe9").unwrap();
+        assert!(o.buffer().ends_with("caféé"));
+    }
 }
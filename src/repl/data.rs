@@ -2,6 +2,7 @@ use super::*;
 use crate::code::{
     parse_crates_in_file, validate_static_file_path, AddingStaticFileError, ModsMap, SourceCode,
 };
+use crate::complete::{Completion, CompletionKind};
 
 impl<Data> Default for ReplData<Data> {
     fn default() -> Self {
@@ -17,13 +18,40 @@ impl<Data> Default for ReplData<Data> {
             current_mod: lib_path,
             prompt_colour: Color::Cyan,
             out_colour: Color::BrightGreen,
+            variant_colours: false,
             compilation_dir: default_compile_dir(),
+            instance_name: None,
             linking: LinkingConfiguration::default(),
             editing: None,
             editing_src: None,
             static_files: StaticFiles::new(),
             loadedlibs: VecDeque::new(),
             loaded_libs_size_limit: 0,
+            transcript: None,
+            out_types: Default::default(),
+            last_out_type: None,
+            toolchain_info: None,
+            run: true,
+            assertion_reporting: false,
+            echo_bindings: false,
+            out_capture: true,
+            result_width: None,
+            float_precision: None,
+            max_disk_usage: None,
+            name_suggestions: false,
+            output_encoding: output::Encoding::default(),
+            completion: true,
+            banner: None,
+            emitted: Vec::new(),
+            last_input: None,
+            last_input_idx: None,
+            input_transforms: Vec::new(),
+            source_transforms: Vec::new(),
+            panic_message_filter: None,
+            session: None,
+            virtual_files: Vec::new(),
+            eval_metrics: EvalMetrics::default(),
+            last_compile_command: None,
         };
 
         r.with_cmdtree_builder(Builder::new("papyrus"))
@@ -42,9 +70,86 @@ impl<Data> ReplData<Data> {
         }
         assert!(dir.is_dir());
         self.compilation_dir = dir.to_path_buf();
+        self.instance_name = None;
+        self.toolchain_info = None; // toolchain could be different in the new directory
         Ok(self)
     }
 
+    /// Set the compilation directory to one derived from `name`, `$TMPDIR/papyrus/NAME`.
+    ///
+    /// This gives a stable directory which can be inspected and cleaned up, useful for tests. The
+    /// name may only contain alphanumeric characters, `_`, or `-`, and can only be used by one
+    /// REPL in the process at a time, it is released when the REPL is dropped or the compilation
+    /// directory is changed.
+    pub fn with_instance_name(&mut self, name: &str) -> io::Result<&mut Self> {
+        if self.instance_name() == Some(name) {
+            return Ok(self);
+        }
+
+        let claimed = InstanceName::claim(name)?;
+        self.with_compilation_dir(instance_compile_dir(name))?;
+        self.instance_name = Some(claimed);
+        Ok(self)
+    }
+
+    /// The name set with [`with_instance_name`](ReplData::with_instance_name), if the compilation
+    /// directory has not since been changed.
+    pub fn instance_name(&self) -> Option<&str> {
+        self.instance_name.as_ref().map(|x| x.0.as_str())
+    }
+
+    /// Information about the Rust toolchain used to compile evaluations.
+    ///
+    /// This runs `rustc -vV` in the compilation directory the first time it is called, the result
+    /// is cached.
+    pub fn toolchain_info(&mut self) -> io::Result<&crate::compile::ToolchainInfo> {
+        if self.toolchain_info.is_none() {
+            let info = crate::compile::ToolchainInfo::query(&self.compilation_dir)?;
+            self.toolchain_info = Some(info);
+        }
+
+        Ok(self.toolchain_info.as_ref().expect("set above"))
+    }
+
+    /// Append a transcript of each evaluation to the file at `path`.
+    ///
+    /// Each entry contains a timestamp, the prompt and input, and the output or error. The file is
+    /// created if it does not exist and is flushed after every evaluation. This is independent of
+    /// any input history.
+    pub fn with_transcript(&mut self, path: PathBuf) -> io::Result<&mut Self> {
+        // check the file can be opened for appending
+        fs::OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&path)?;
+        self.transcript = Some(Transcript {
+            path,
+            input: String::new(),
+        });
+        Ok(self)
+    }
+
+    /// The path of the transcript file, if one is set.
+    pub fn transcript(&self) -> Option<&Path> {
+        self.transcript.as_ref().map(|x| x.path.as_path())
+    }
+
+    pub(super) fn transcript_input(&mut self, prompt: &str, input: &str) {
+        if let Some(t) = self.transcript.as_mut() {
+            t.input.clear();
+            t.input.push_str(prompt);
+            t.input.push_str(input.trim_end());
+        }
+    }
+
+    pub(super) fn transcript_output(&mut self, output: &str) {
+        if let Some(t) = self.transcript.as_mut() {
+            if let Err(e) = t.append(output) {
+                error!("writing to transcript '{}' failed: {}", t.path.display(), e);
+            }
+        }
+    }
+
     /// Link an external library.
     ///
     /// This is primarily used for linking the calling library, and there
@@ -58,6 +163,444 @@ impl<Data> ReplData<Data> {
         self
     }
 
+    /// The names of the linked external libraries, sorted. These are the names the libraries are
+    /// referred to in evaluated code, see [`Extern::name`](linking::Extern::name).
+    pub fn linked_crates(&self) -> Vec<&str> {
+        let mut names: Vec<_> = self
+            .linking
+            .external_libs
+            .iter()
+            .map(linking::Extern::name)
+            .collect();
+        names.sort_unstable();
+        names
+    }
+
+    /// Unlink an external library, by the name it is referred to in evaluated code.
+    ///
+    /// The library is no longer passed to the compiler, so existing code which uses it will fail
+    /// to compile.
+    pub fn unlink_crate(&mut self, name: &str) -> Result<&mut Self, &'static str> {
+        let libs = &mut self.linking.external_libs;
+        let len = libs.len();
+        libs.retain(|x| x.name() != name);
+        if libs.len() == len {
+            Err("no linked crate has this name")
+        } else {
+            Ok(self)
+        }
+    }
+
+    /// Set how panics in evaluated code are handled. The default is [`PanicStrategy::Catch`].
+    ///
+    /// Changing the strategy is mostly useful when debugging linked code, where a panic should
+    /// propagate with a full backtrace rather than be swallowed.
+    ///
+    /// [`PanicStrategy::Catch`]: linking::PanicStrategy::Catch
+    pub fn with_panic_strategy(&mut self, strategy: linking::PanicStrategy) -> &mut Self {
+        self.linking.panic_strategy = strategy;
+        self
+    }
+
+    /// Link a native library, such as `m` or `sqlite3`.
+    ///
+    /// See [`LinkingConfiguration::with_native_lib`].
+    pub fn with_native_lib(
+        &mut self,
+        name: &str,
+        kind: linking::NativeLibKind,
+    ) -> Result<&mut Self, &'static str> {
+        linking::validate_native_lib_name(name)?;
+        self.linking.native_libs.push((name.to_string(), kind));
+        Ok(self)
+    }
+
+    /// Pass an argument to the linker when compiling.
+    pub fn with_link_arg(&mut self, arg: String) -> &mut Self {
+        self.linking.link_args.push(arg);
+        self
+    }
+
+    /// Set the name the data is bound to in evaluated code, the default is `app_data`.
+    ///
+    /// See [`LinkingConfiguration::with_data_name`].
+    pub fn with_data_name(&mut self, name: &str) -> Result<&mut Self, &'static str> {
+        linking::validate_data_name(name)?;
+        self.linking.data_name = name.to_string();
+        Ok(self)
+    }
+
+    /// Set whether `extern crate CRATE_NAME;` is added for each external library.
+    ///
+    /// See [`LinkingConfiguration::auto_extern`].
+    pub fn with_auto_extern(&mut self, auto_extern: bool) -> &mut Self {
+        self.linking.auto_extern = auto_extern;
+        self
+    }
+
+    /// Set the standard library available to evaluated code. The default is [`StdMode::Std`].
+    ///
+    /// In [`StdMode::NoStd`], code is evaluated against `core` and `alloc`, which is useful for
+    /// prototyping `no_std` snippets. Inputs using `std` are rejected with an error.
+    ///
+    /// [`StdMode::Std`]: linking::StdMode::Std
+    /// [`StdMode::NoStd`]: linking::StdMode::NoStd
+    pub fn with_std_mode(&mut self, mode: linking::StdMode) -> &mut Self {
+        self.linking.std_mode = mode;
+        self
+    }
+
+    /// Import commonly used `std` items, such as `HashMap` and `Rc`, into evaluated code. The
+    /// default is `false`.
+    ///
+    /// See [`LinkingConfiguration::auto_std_imports`].
+    pub fn with_auto_std_imports(&mut self, auto_std_imports: bool) -> &mut Self {
+        self.linking.auto_std_imports = auto_std_imports;
+        self
+    }
+
+    /// Set whether evaluated code is run. The default is `true`.
+    ///
+    /// If `false`, inputs are only type checked (similar to `cargo check`) and no library is built
+    /// or executed. The evaluation outputs `OK` or the compilation errors. Checked inputs are not
+    /// kept, as every evaluation runs the kept statements again, so code with side effects that
+    /// should not be run can be checked and is never run once running is enabled again.
+    pub fn with_run(&mut self, run: bool) -> &mut Self {
+        self.run = run;
+        self
+    }
+
+    /// Set the name of the generated evaluation function, the default is `intern_eval`.
+    ///
+    /// See [`LinkingConfiguration::eval_fn_name`].
+    pub fn with_eval_fn_name(&mut self, name: &str) -> Result<&mut Self, &'static str> {
+        linking::validate_eval_fn_name(name)?;
+        self.linking.eval_fn_name = name.to_string();
+        Ok(self)
+    }
+
+    /// Set the prefix of the generated evaluation function signature, the default is `_`.
+    ///
+    /// See [`LinkingConfiguration::eval_fn_prefix`].
+    pub fn with_eval_fn_prefix(&mut self, prefix: &str) -> Result<&mut Self, &'static str> {
+        linking::validate_eval_fn_prefix(prefix)?;
+        self.linking.eval_fn_prefix = prefix.to_string();
+        Ok(self)
+    }
+
+    /// Set the prefix of the bindings results are assigned to, such as `res` to bind results as
+    /// `res0`, `res1`, and so on.
+    ///
+    /// See [`LinkingConfiguration::out_prefix`].
+    pub fn with_out_prefix(&mut self, prefix: &str) -> Result<&mut Self, &'static str> {
+        linking::validate_out_prefix(prefix)?;
+        self.linking.out_prefix = prefix.to_string();
+        Ok(self)
+    }
+
+    /// Set the amount of debug information compiled into evaluations, `0`, `1`, or `2`. The
+    /// default is `1`.
+    ///
+    /// See [`LinkingConfiguration::debuginfo`].
+    pub fn with_debuginfo(&mut self, level: u8) -> Result<&mut Self, &'static str> {
+        linking::validate_debuginfo(level)?;
+        self.linking.debuginfo = level;
+        Ok(self)
+    }
+
+    /// Set the linker evaluations are linked with, such as a faster linker like `mold`.
+    ///
+    /// The linker must be a path to an existing file, or a program name found in `PATH`. See
+    /// [`LinkingConfiguration::linker`].
+    pub fn with_linker(&mut self, linker: PathBuf) -> Result<&mut Self, &'static str> {
+        linking::validate_linker(&linker)?;
+        self.linking.linker = Some(linker);
+        Ok(self)
+    }
+
+    /// Run cargo with `--offline`, so compilations never access the network. The default is
+    /// `false`.
+    ///
+    /// Evaluations depend on `kserd`, so it and any crates referenced by evaluated code must
+    /// already be in the local cargo cache. If a crate is missing, the evaluation fails with a
+    /// [`CompilationError::OfflineError`](crate::compile::CompilationError::OfflineError).
+    pub fn with_offline(&mut self, offline: bool) -> &mut Self {
+        self.linking.offline = offline;
+        self
+    }
+
+    /// Report the outcome of inputs which are assertions, such as `assert_eq!(add(2, 3), 5);`.
+    /// The default is `false`.
+    ///
+    /// If enabled, an input which is a single `assert!`, `assert_eq!`, or `assert_ne!` (or a
+    /// `debug_` variant) outputs `✓ assertion passed` when it succeeds, or `✗` and the assertion
+    /// message when it fails. Assertions are not kept as part of the session.
+    pub fn with_assertion_reporting(&mut self, report: bool) -> &mut Self {
+        self.assertion_reporting = report;
+        self
+    }
+
+    /// Set a filter which rewrites captured panic messages before they are output, such as to
+    /// redact sensitive data. The default outputs messages as is.
+    ///
+    /// Panic messages are captured when evaluated code panics with the
+    /// [`Catch`](crate::linking::PanicStrategy::Catch) strategy, and are output for failed
+    /// [assertions](ReplData::with_assertion_reporting). The panic hook, which prints to stderr,
+    /// is not affected.
+    pub fn with_panic_message_filter(
+        &mut self,
+        filter: Box<dyn Fn(&str) -> String + Send>,
+    ) -> &mut Self {
+        self.panic_message_filter = Some(filter);
+        self
+    }
+
+    pub(crate) fn filter_panic_message<'a>(&self, msg: &'a str) -> Cow<'a, str> {
+        match &self.panic_message_filter {
+            Some(f) => Cow::Owned(f(msg)),
+            None => Cow::Borrowed(msg),
+        }
+    }
+
+    /// Add a transform which rewrites raw input before it is evaluated.
+    ///
+    /// Transforms are applied at the very start of the evaluation path, before commands are
+    /// parsed, so sugar such as `?x` meaning `dbg!(x)` can be implemented. Multiple transforms are
+    /// applied in the order they were added, each receiving the output of the previous one.
+    ///
+    /// Transforms must produce valid Rust (or a command); invalid output surfaces as a normal
+    /// parsing or compilation error.
+    pub fn with_input_transform(
+        &mut self,
+        transform: Box<dyn Fn(&str) -> String + Send>,
+    ) -> &mut Self {
+        self.input_transforms.push(transform);
+        self
+    }
+
+    pub(crate) fn transform_input<'a>(&self, input: &'a str) -> Cow<'a, str> {
+        self.input_transforms
+            .iter()
+            .fold(Cow::Borrowed(input), |input, f| Cow::Owned(f(&input)))
+    }
+
+    /// Add a transform which rewrites the generated source code before it is compiled.
+    ///
+    /// Unlike [`with_input_transform`](ReplData::with_input_transform), the transform receives
+    /// the complete generated `lib.rs`, including the evaluation functions wrapping each module's
+    /// statements, so it can be used to instrument or experiment with the code papyrus produces.
+    /// Transforms run on every evaluation, in the order they were added.
+    ///
+    /// Transforms must keep the source compilable, and must not rename or change the return
+    /// values of the evaluation functions, otherwise results can not be read back.
+    pub fn with_source_transform(
+        &mut self,
+        transform: Box<dyn Fn(String) -> String + Send>,
+    ) -> &mut Self {
+        self.source_transforms.push(transform);
+        self
+    }
+
+    pub(crate) fn transform_source(&self, src: String) -> String {
+        self.source_transforms.iter().fold(src, |src, f| f(src))
+    }
+
+    /// Output the value of `let` bindings. The default is `false`.
+    ///
+    /// If enabled, an input ending with a `let` statement binding a single name, such as
+    /// `let x = 2 + 2;`, is evaluated straight away. The binding is kept and its value is output as
+    /// `x: 4`, rather than waiting for more input.
+    pub fn with_echo_bindings(&mut self, echo: bool) -> &mut Self {
+        self.echo_bindings = echo;
+        self
+    }
+
+    /// Keep the results of inputs as `out#` bindings. The default is `true`.
+    ///
+    /// If disabled, the result of an input is output but not kept, so it can not be referenced by
+    /// later inputs. Statements before the result, such as `let` bindings, are kept. This keeps
+    /// the generated source smaller for uses which only need the side effects or output of inputs.
+    pub fn with_out_capture(&mut self, capture: bool) -> &mut Self {
+        self.out_capture = capture;
+        self
+    }
+
+    /// Wrap results to `width` columns. The default is `None`, results are output as is.
+    ///
+    /// The value of a result is laid out so continuation lines are indented under the start of
+    /// the value, after the `[out#]:` label. Lines which would go past `width` are broken at
+    /// whitespace where possible. Only the output is wrapped, the transcript keeps the raw value.
+    pub fn with_result_width(&mut self, width: Option<usize>) -> &mut Self {
+        self.result_width = width;
+        self
+    }
+
+    /// The result width, see [`with_result_width`](ReplData::with_result_width).
+    pub fn result_width(&self) -> Option<usize> {
+        self.result_width
+    }
+
+    /// Format floating point results to `precision` decimal places. The default is `None`, floats
+    /// are output with Rust's formatting.
+    ///
+    /// Results of type `f32` or `f64` are formatted with `precision` decimal places, such as
+    /// `0.3333` for `1.0 / 3.0` with a precision of 4. The floats within other results are
+    /// formatted if the type name contains `f32` or `f64`, such as `Vec<f64>` or `(f64, f64)`.
+    /// `NaN` and `inf` are output as is, and integers are unaffected.
+    pub fn with_float_precision(&mut self, precision: Option<usize>) -> &mut Self {
+        self.float_precision = precision;
+        self
+    }
+
+    /// Formats the floats in the `value` of the last evaluated result to the float precision, if
+    /// set.
+    pub(crate) fn apply_float_precision(&self, value: String) -> String {
+        match (self.float_precision, self.last_out_type.as_deref()) {
+            (Some(precision), Some(ty)) => format_floats(ty, &value, precision),
+            _ => value,
+        }
+    }
+
+    /// The disk space used by the session, in bytes.
+    ///
+    /// This is the size of the compilation directory, which holds the generated source, the build
+    /// artifacts, and the library built for each evaluation.
+    pub fn session_disk_usage(&self) -> io::Result<u64> {
+        crate::compile::dir_size(&self.compilation_dir)
+    }
+
+    /// Cap the disk space used by the session to `bytes`. The default is `None`, the usage is not
+    /// capped.
+    ///
+    /// Each evaluation builds a new library which is kept in the compilation directory. If the
+    /// [`session_disk_usage`] is over the cap after an evaluation, these libraries are removed,
+    /// oldest first, until the usage is within the cap. Other build artifacts are kept as removing
+    /// them would force a full rebuild, so the usage can stay over the cap.
+    ///
+    /// [`session_disk_usage`]: ReplData::session_disk_usage
+    pub fn with_max_disk_usage(&mut self, bytes: Option<u64>) -> &mut Self {
+        self.max_disk_usage = bytes;
+        self
+    }
+
+    /// The disk space cap, see [`with_max_disk_usage`](ReplData::with_max_disk_usage).
+    pub fn max_disk_usage(&self) -> Option<u64> {
+        self.max_disk_usage
+    }
+
+    /// Removes old libraries if the session is over the disk space cap.
+    pub(crate) fn limit_disk_usage(&self) {
+        let max = match self.max_disk_usage {
+            Some(max) => max,
+            None => return,
+        };
+
+        let r = self.session_disk_usage().and_then(|used| {
+            if used > max {
+                crate::compile::evict_libraries(&self.compilation_dir, used - max).map(|_| ())
+            } else {
+                Ok(())
+            }
+        });
+        if let Err(e) = r {
+            warn!("failed to limit the session disk usage: {}", e);
+        }
+    }
+
+    /// Suggest names when compilation fails because a name could not be found. The default is
+    /// `false`.
+    ///
+    /// For each name in a `cannot find ... in this scope` error, such as a misspelt variable, the
+    /// closest matching names used in the current module, `out#` results, and linked crates are
+    /// appended to the error as `did you mean: value, values?`.
+    pub fn with_name_suggestions(&mut self, suggest: bool) -> &mut Self {
+        self.name_suggestions = suggest;
+        self
+    }
+
+    /// Set the encoding used to decode bytes written to the output. The default is UTF-8.
+    ///
+    /// This applies to bytes written through the output's [`io::Write`](std::io::Write)
+    /// implementation, such as by commands which pass on the output of a linked library. Anything
+    /// evaluated code prints goes directly to stdout and is not decoded.
+    pub fn with_output_encoding(&mut self, encoding: output::Encoding) -> &mut Self {
+        self.output_encoding = encoding;
+        self
+    }
+
+    /// Complete input when tab is pressed in the terminal REPL. The default is `true`.
+    ///
+    /// If disabled, tab inserts a tab character and no completion work is done, which suits dumb
+    /// terminals or piped input. [`complete`](ReplData::complete) is not affected.
+    pub fn with_completion(&mut self, completion: bool) -> &mut Self {
+        self.completion = completion;
+        self
+    }
+
+    /// Whether input is completed when tab is pressed in the terminal REPL.
+    pub fn completion(&self) -> bool {
+        self.completion
+    }
+
+    /// Set the message shown before the first prompt when the REPL is run in the terminal.
+    /// `None` shows nothing, which is the default.
+    ///
+    /// [`default_banner`](super::default_banner) shows the papyrus version and how to get help.
+    pub fn with_banner(&mut self, banner: Option<String>) -> &mut Self {
+        self.banner = banner;
+        self
+    }
+
+    /// Provide a file at `path` with the contents `bytes` to evaluated code.
+    ///
+    /// This allows deterministic evaluation of code which reads files, such as
+    /// `std::fs::read_to_string("data.txt")`. Adding a file at the same path replaces its contents.
+    ///
+    /// # Limits
+    /// Evaluated code runs in this process, so the file is not truly virtual: it is written to
+    /// `path` (relative paths resolve against the current working directory) just before the
+    /// evaluated code is executed, and removed afterwards, along with any directories created for
+    /// it. Writes that the evaluated code makes to the file are discarded. If a file already exists
+    /// at `path` it is never overwritten, instead the evaluation fails with an error.
+    pub fn with_virtual_file<P: Into<PathBuf>, B: Into<Vec<u8>>>(
+        &mut self,
+        path: P,
+        bytes: B,
+    ) -> &mut Self {
+        let path = path.into();
+        self.virtual_files.retain(|(p, _)| p != &path);
+        self.virtual_files.push((path, bytes.into()));
+        self
+    }
+
+    /// Compile for the target triple `target`, such as `wasm32-unknown-unknown`.
+    ///
+    /// Code compiled for a target other than the host cannot be run, so evaluations will only
+    /// compile, reporting the compiled library path or the compilation errors. Setting the target
+    /// to the host triple returns to compiling and running natively.
+    ///
+    /// Errors if the standard library for the target is not installed (use
+    /// `rustup target add <target>`).
+    pub fn with_target(&mut self, target: &str) -> io::Result<&mut Self> {
+        if self.toolchain_info()?.host_triple == target {
+            self.linking.target = None;
+            return Ok(self);
+        }
+
+        if !crate::compile::target_installed(&self.compilation_dir, target)? {
+            return Err(io::Error::new(
+                io::ErrorKind::NotFound,
+                format!(
+                    "target `{}` is not installed, try `rustup target add {}`",
+                    target, target
+                ),
+            ));
+        }
+
+        self.linking.target = Some(target.to_string());
+        Ok(self)
+    }
+
     /// The current mod that is being repl'd on.
     pub fn current_mod(&self) -> &Path {
         self.current_mod.as_path()
@@ -71,11 +614,158 @@ impl<Data> ReplData<Data> {
             .expect("thin shouldn't fail, always should exist.")
     }
 
+    /// The type name of the result `out#` in the current module, if known.
+    ///
+    /// The type is recorded when the result is evaluated. The name is as given by
+    /// [`std::any::type_name`], so it is only meant as a description, such as `alloc::vec::Vec<i32>`.
+    pub fn out_type(&self, n: usize) -> Option<&str> {
+        if n >= self.current_src().out_count() {
+            return None;
+        }
+
+        self.out_types
+            .get(&(self.current_mod.clone(), n))
+            .map(|x| x.as_str())
+    }
+
+    /// The stage timings of the last program evaluation.
+    ///
+    /// This is useful to see where the evaluation latency goes, such as whether it is dominated
+    /// by compilation. The metrics are also logged at the `debug` level.
+    pub fn eval_metrics(&self) -> EvalMetrics {
+        self.eval_metrics
+    }
+
+    /// The command line used for the most recent compilation, if there has been one.
+    ///
+    /// This includes all the flags passed to the compiler, such as `--extern` and `-L` for linked
+    /// libraries, so a failing compilation can be reproduced by hand. See
+    /// [`compile::command_line`](crate::compile::command_line).
+    pub fn last_compile_command(&self) -> Option<&str> {
+        self.last_compile_command.as_deref()
+    }
+
+    /// Emit a line to the output, such as a status message.
+    ///
+    /// Lines are written to the output of the next evaluation, so they render through the same
+    /// [`OutputChange`](crate::output::OutputChange)s as everything else. Lines emitted before an
+    /// evaluation come before any of its output. Lines emitted by a command while it is handled
+    /// come after anything it writes directly, and before its result.
+    ///
+    /// To show a line straight away, send it through
+    /// [`Repl::output_sender`](crate::repl::Repl::output_sender) instead.
+    pub fn emit<S: Into<String>>(&mut self, line: S) -> &mut Self {
+        self.emitted.push(line.into());
+        self
+    }
+
+    /// The text of the last program input, after any input transforms.
+    ///
+    /// Commands are not included.
+    pub fn last_input(&self) -> Option<&str> {
+        self.last_input.as_deref()
+    }
+
+    /// The index of the last program input in the current module, if it is still kept.
+    pub(crate) fn last_input_idx(&self) -> Option<EditingIndex> {
+        let (path, ei) = self.last_input_idx.as_ref()?;
+        let src = self.current_src();
+        let len = match ei.editing {
+            Editing::Stmt => src.stmts.len(),
+            Editing::Item => src.items.len(),
+            Editing::Crate => src.crates.len(),
+        };
+
+        if path == &self.current_mod && ei.index < len {
+            Some(*ei)
+        } else {
+            None
+        }
+    }
+
+    /// The message shown before the first prompt, see [`with_banner`](ReplData::with_banner).
+    pub fn banner(&self) -> Option<&str> {
+        self.banner.as_deref()
+    }
+
+    /// Clears the results (`out#`) of the current module, keeping items, crates, and the other
+    /// statements of previous inputs, such as `let` bindings.
+    ///
+    /// Inputs which were only a result are removed. Subsequent results start again at `out0`, the
+    /// kept statements are not numbered. Returns the number of results cleared.
+    pub fn reset_outputs(&mut self) -> usize {
+        let cmod = self.current_mod.clone();
+        self.out_types.retain(|(path, _), _| path != &cmod);
+        self.last_input_idx = None;
+
+        let src = self
+            .mods_map
+            .get_mut(&cmod)
+            .expect("current mod should always exist");
+        let results = (0..src.stmts.len())
+            .rev()
+            .filter(|&i| src.stmts[i].has_result())
+            .collect::<Vec<_>>();
+        for &i in &results {
+            super::eval::release_result(src, i);
+        }
+        results.len()
+    }
+
     /// The current file map, mappings of modules to source code.
     pub fn mods_map(&self) -> &ModsMap {
         &self.mods_map
     }
 
+    /// The completion candidates for `input` with the cursor at the byte position `cursor`, in the
+    /// order they should be presented.
+    ///
+    /// A cursor past the end of `input`, or inside a character, is moved back to the nearest
+    /// character boundary.
+    ///
+    /// Commands and module paths are completed. Code completion requires a cache and runs
+    /// asynchronously in the terminal REPL, use `complete::code::CodeCompleter` directly for it.
+    pub fn complete(&self, input: &str, cursor: usize) -> Vec<Completion> {
+        use crate::complete::{cmdr::TreeCompleter, modules::ModulesCompleter};
+
+        let mut cursor = cursor.min(input.len());
+        while !input.is_char_boundary(cursor) {
+            cursor -= 1;
+        }
+        let line = &input[..cursor];
+
+        let start = TreeCompleter::word_break(line);
+        let commands = TreeCompleter::build(&self.cmdtree)
+            .complete(line)
+            .map(|(replacement, info)| {
+                let help = info.help_msg.as_str();
+                Completion {
+                    start,
+                    replacement: replacement.to_owned(),
+                    display: if help.is_empty() {
+                        replacement.to_owned()
+                    } else {
+                        format!("{} -- {}", replacement, help)
+                    },
+                    kind: CompletionKind::Command,
+                }
+            })
+            .collect::<Vec<_>>();
+
+        let start = ModulesCompleter::word_break(line);
+        let modules = ModulesCompleter::build(&self.cmdtree, &self.mods_map)
+            .complete(line)
+            .map(|replacement| Completion {
+                start,
+                display: replacement.clone(),
+                replacement,
+                kind: CompletionKind::Module,
+            })
+            .collect::<Vec<_>>();
+
+        commands.into_iter().chain(modules).collect()
+    }
+
     /// The current linking configuration.
     /// Not mutable as it could lead to undefined behaviour if changed.
     pub fn linking(&self) -> &LinkingConfiguration {
@@ -154,7 +844,8 @@ impl<Data> ReplData<Data> {
     /// Clears the cached loaded libraries.
     ///
     /// This can be used to clear resources. Loaded libraries are stored up to the
-    /// [`loaded_libs_size_limit`] but can be cleared earlier if need be.
+    /// [`loaded_libs_size_limit`] but can be cleared earlier if need be. A thread spawned by
+    /// evaluated code which is still running crashes the host process once its library is cleared.
     ///
     /// [`loaded_libs_size_limit`]: ReplData
     pub fn clear_loaded_libs(&mut self) {
@@ -175,6 +866,35 @@ impl<Data> ReplData<Data> {
     }
 }
 
+impl Transcript {
+    fn append(&mut self, output: &str) -> io::Result<()> {
+        use std::io::Write;
+        use std::time::{SystemTime, UNIX_EPOCH};
+
+        let secs = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|x| x.as_secs())
+            .unwrap_or_default();
+
+        let mut file = fs::OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&self.path)?;
+
+        writeln!(file, "# {}", secs)?;
+        writeln!(file, "{}", self.input)?;
+        if !output.is_empty() {
+            writeln!(file, "{}", output)?;
+        }
+        writeln!(file)?;
+        file.flush()?;
+
+        self.input.clear();
+
+        Ok(())
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -202,4 +922,145 @@ mod tests {
         data.add_static_file("path/to/something.rs".into(), "")
             .unwrap();
     }
+
+    #[test]
+    fn transcript_test() {
+        let path = PathBuf::from("./target/transcript-test.txt");
+        fs::remove_file(&path).ok();
+
+        let mut data: ReplData<()> = ReplData::default();
+        assert_eq!(data.transcript(), None);
+        data.with_transcript(path.clone()).unwrap();
+        assert_eq!(data.transcript(), Some(path.as_path()));
+
+        data.transcript_input("[lib] papyrus=> ", "let a = 1;\na\n");
+        data.transcript_output("[out0]: 1");
+        data.transcript_input("[lib] papyrus=> ", ":help\n");
+        data.transcript_output("");
+
+        let s = fs::read_to_string(&path).unwrap();
+        let lines: Vec<_> = s.lines().filter(|x| !x.starts_with("# ")).collect();
+        assert_eq!(
+            lines,
+            vec![
+                "[lib] papyrus=> let a = 1;",
+                "a",
+                "[out0]: 1",
+                "",
+                "[lib] papyrus=> :help",
+                ""
+            ]
+        );
+        assert_eq!(s.lines().filter(|x| x.starts_with("# ")).count(), 2);
+    }
+
+    #[test]
+    fn reset_outputs_test() {
+        use crate::code::{Statement, StmtGrp};
+
+        let mut data: ReplData<()> = ReplData::default();
+        let lib = PathBuf::from("lib");
+        {
+            let src = data.mods_map.get_mut(&lib).unwrap();
+            src.items.push(("fn a() {}".to_string(), false));
+            src.stmts.push(StmtGrp(
+                vec![Statement {
+                    expr: "a()".to_string(),
+                    semi: false,
+                }],
+                false,
+            ));
+        }
+        data.out_types.insert((lib.clone(), 0), "()".to_string());
+        assert_eq!(data.out_type(0), Some("()"));
+
+        assert_eq!(data.reset_outputs(), 1);
+        assert_eq!(data.current_src().stmts.len(), 0);
+        assert_eq!(data.current_src().items.len(), 1);
+        assert_eq!(data.out_type(0), None);
+        assert!(data.out_types.is_empty());
+
+        assert_eq!(data.reset_outputs(), 0);
+
+        // other statements are kept
+        {
+            let src = data.mods_map.get_mut(&lib).unwrap();
+            src.stmts.push(StmtGrp(
+                vec![
+                    Statement {
+                        expr: "let b = 1".to_string(),
+                        semi: true,
+                    },
+                    Statement {
+                        expr: "b".to_string(),
+                        semi: false,
+                    },
+                ],
+                false,
+            ));
+            src.stmts.push(StmtGrp(
+                vec![Statement {
+                    expr: "let c = 2".to_string(),
+                    semi: true,
+                }],
+                true,
+            ));
+        }
+        assert_eq!(data.reset_outputs(), 1);
+        let stmts = &data.current_src().stmts;
+        assert_eq!(stmts.len(), 2);
+        assert_eq!(stmts[0].0.len(), 1);
+        assert_eq!(stmts[0].0[0].expr, "let b = 1");
+        assert!(stmts.iter().all(|x| !x.has_result()));
+    }
+
+    #[test]
+    fn complete_test() {
+        let mut data: ReplData<()> = ReplData::default();
+        data.mods_map
+            .insert("amodule".into(), SourceCode::default());
+
+        let input = ":mod sw";
+        let c = data.complete(input, input.len());
+        assert!(c.iter().any(|x| x.kind == CompletionKind::Command
+            && format!("{}{}", &input[..x.start], x.replacement) == ":mod switch"));
+
+        let module = Completion {
+            start: 12,
+            replacement: "amodule".to_string(),
+            display: "amodule".to_string(),
+            kind: CompletionKind::Module,
+        };
+        let c = data.complete(":mod switch amo", 15);
+        assert!(c.contains(&module));
+        // only the input before the cursor is completed
+        let c = data.complete(":mod switch amo other", 15);
+        assert!(c.contains(&module));
+
+        assert!(data.complete("let a = 1", 9).is_empty());
+
+        // cursors inside a character or past the end do not panic
+        let c = data.complete(":mod switch amoé", 16);
+        assert!(c.contains(&module));
+        let c = data.complete(":mod switch amo", 100);
+        assert!(c.contains(&module));
+    }
+
+    #[test]
+    fn transform_input_test() {
+        let mut data: ReplData<()> = ReplData::default();
+        assert_eq!(data.transform_input("?a"), Cow::Borrowed("?a"));
+
+        data.with_input_transform(Box::new(|s| {
+            if s.starts_with('?') {
+                format!("dbg!({})", &s[1..])
+            } else {
+                s.to_string()
+            }
+        }))
+        .with_input_transform(Box::new(|s| s.replace("dbg!", "std::dbg!")));
+
+        assert_eq!(data.transform_input("?a"), "std::dbg!(a)");
+        assert_eq!(data.transform_input("a + 1"), "a + 1");
+    }
 }
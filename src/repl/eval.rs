@@ -5,7 +5,7 @@ use crate::{
     compile,
 };
 use std::borrow::{Borrow, BorrowMut};
-use std::ops::{Deref, DerefMut};
+use std::ops::{Deref, DerefMut, Range};
 use std::sync::{Arc, Mutex};
 
 /// > **These methods are available when the REPL is in the [`Evaluate`] state.**
@@ -70,6 +70,12 @@ impl<D> Repl<Evaluate, D> {
         self.state.output.listen()
     }
 
+    /// A sender to inject line changes to the output listener, see
+    /// [`Output::sender`](output::Output::sender).
+    pub fn output_sender(&self) -> Option<output::Sender> {
+        self.state.output.sender()
+    }
+
     /// Close the sender side of the output channel.
     pub fn close_channel(&mut self) {
         self.state.output.close()
@@ -118,6 +124,8 @@ where
 
     let Evaluate { mut output, result } = state;
 
+    data.write_emitted(&mut output);
+
     let mut keep_mutating = false; // default to stop mutating phase
                                    // can't cancel before as handle program requires it for decisions
 
@@ -129,13 +137,17 @@ where
             r.map(EvalOutput::Print)
         }
         InputResult::Program(input) => {
-            Ok(data.handle_program(input, &mut output, obtain_mut_data, obtain_brw_data))
+            let r = data.handle_program(input, &mut output, obtain_mut_data, obtain_brw_data);
+            debug!("evaluation metrics: {:?}", data.eval_metrics);
+            Ok(r)
         }
-        InputResult::InputError(err) => Ok(EvalOutput::Print(Cow::Owned(err))),
+        InputResult::InputError(err) => Ok(EvalOutput::Error(Cow::Owned(err))),
         InputResult::Eof => Err(Signal::Exit),
         _ => Ok(EvalOutput::Print(Cow::Borrowed(""))),
     };
 
+    data.write_emitted(&mut output); // lines emitted while handling the input
+
     let (eval_output, sig) = match mapped {
         Ok(hir) => (hir, Signal::None),
         Err(sig) => (EvalOutput::Print(Cow::Borrowed("")), sig),
@@ -161,16 +173,315 @@ where
 }
 
 impl<D> ReplData<D> {
-    fn handle_command<F, R, W>(
+    /// Evaluates `input` and returns the result as a string, without writing to any output.
+    ///
+    /// The input is treated as a program in the current module and is stored the same as it would
+    /// be through the REPL cycle. No `OutputChange`s are emitted, but any prints from within the
+    /// evaluated code still go to stdout. Commands are not supported.
+    ///
+    /// Returns `Ok` with an empty string if there was no statement to evaluate, or with the message
+    /// of a successful input which has no result, such as `✓ assertion passed`. Returns `Err` with
+    /// the message if parsing, compilation, or execution fails.
+    pub fn eval_value(&mut self, input: &str, app_data: &mut D) -> Result<String, String> {
+        use std::cell::{Ref, RefCell, RefMut};
+
+        if input.trim().is_empty() {
+            return Ok(String::new());
+        }
+
+        let input = match crate::input::parse_program(&self.transform_input(input)) {
+            InputResult::Program(input) => input,
+            InputResult::InputError(e) => return Err(e),
+            _ => return Err("input is not a complete program".to_string()),
+        };
+
+        let mut output = Output::default().into_write();
+        output.set_encoding(self.output_encoding);
+        let app_data = RefCell::new(app_data);
+
+        let r = self.handle_program(
+            input,
+            &mut output,
+            || RefMut::map(app_data.borrow_mut(), |x| &mut **x),
+            || Ref::map(app_data.borrow(), |x| &**x),
+        );
+
+        match r {
+            EvalOutput::Data(kserd) | EvalOutput::Binding(_, kserd) => {
+                Ok(self.apply_float_precision(kserd.as_str_with_config(Default::default())))
+            }
+            EvalOutput::Print(s) => Ok(s.into_owned()),
+            EvalOutput::Checked => Ok("OK".to_string()),
+            EvalOutput::Error(e) => Err(e.into_owned()),
+        }
+    }
+
+    /// Evaluates each input in order, as with [`eval_value`], such that either all inputs are
+    /// kept or none are.
+    ///
+    /// If an input fails, the source code of all modules, the result types, and the last input
+    /// and its result type are restored to before the batch, and the index of the failing input is
+    /// returned. The inputs are not written to a session recording or transcript. Side effects of
+    /// the inputs which did run, such as changes to `app_data` or files, are _not_ reverted.
+    ///
+    /// [`eval_value`]: ReplData::eval_value
+    pub fn eval_batch(
+        &mut self,
+        inputs: &[&str],
+        app_data: &mut D,
+    ) -> Result<Vec<String>, BatchError> {
+        let mods_map = self.mods_map.clone();
+        let out_types = self.out_types.clone();
+        let last_input = self.last_input.clone();
+        let last_input_idx = self.last_input_idx.clone();
+        let last_out_type = self.last_out_type.clone();
+
+        let mut results = Vec::with_capacity(inputs.len());
+        for (index, input) in inputs.iter().enumerate() {
+            match self.eval_value(input, app_data) {
+                Ok(r) => results.push(r),
+                Err(error) => {
+                    self.mods_map = mods_map;
+                    self.out_types = out_types;
+                    self.last_input = last_input;
+                    self.last_input_idx = last_input_idx;
+                    self.last_out_type = last_out_type;
+                    return Err(BatchError { index, error });
+                }
+            }
+        }
+
+        Ok(results)
+    }
+
+    /// Evaluates `input` as with [`eval_value`](ReplData::eval_value), returning the result and
+    /// its type name, then discards the input so the source code and results are as before.
+    pub(crate) fn eval_discarded(
+        &mut self,
+        input: &str,
+        app_data: &mut D,
+    ) -> Result<(String, Option<String>), String> {
+        let mods_map = self.mods_map.clone();
+        let out_types = self.out_types.clone();
+
+        let r = self
+            .eval_value(input, app_data)
+            .map(|value| (value, self.last_out_type.clone()));
+
+        self.mods_map = mods_map;
+        self.out_types = out_types;
+        r
+    }
+
+    /// Type checks `input` as if it were entered in the current module, without running it.
+    ///
+    /// The input is _not_ stored, the current module is left unchanged. Returns the compilation
+    /// errors if the input fails to type check.
+    pub fn eval_check(&mut self, input: &str) -> Result<(), String> {
+        let input = match crate::input::parse_program(&self.transform_input(input)) {
+            InputResult::Program(input) => input,
+            InputResult::InputError(e) => return Err(e),
+            _ => return Err("input is not a complete program".to_string()),
+        };
+
+        self.check_input(&input)?;
+
+        let saved = self.current_src().clone();
+        let (lstmts, litem, lcrates) = (saved.stmts.len(), saved.items.len(), saved.crates.len());
+        self.insert_input(input, lstmts, litem, lcrates);
+
+        let r = self.check_compile_dir();
+
+        *self.get_current_file_mut() = saved;
+
+        r
+    }
+
+    /// Install `items` into the current module before any user input.
+    ///
+    /// Each string is parsed as a program, such as helper `fn`s or `use` declarations, which lets
+    /// an application ship a pre-populated REPL. Statements are not allowed. The items are type
+    /// checked together, if any fail to parse or check the error is returned and none of the
+    /// items are installed.
+    pub fn with_initial_items(&mut self, items: &[&str]) -> Result<&mut Self, String> {
+        let saved = self.current_src().clone();
+
+        let mut r = Ok(());
+        for item in items {
+            let input = match crate::input::parse_program(item) {
+                InputResult::Program(input) if input.stmts.is_empty() => Ok(input),
+                InputResult::Program(_) => {
+                    Err(format!("initial items can not be statements: {}", item))
+                }
+                InputResult::InputError(e) => Err(e),
+                _ => Err(format!("initial item is not complete: {}", item)),
+            };
+
+            match input {
+                Ok(input) => {
+                    let src = self.current_src();
+                    let (lstmts, litem, lcrates) =
+                        (src.stmts.len(), src.items.len(), src.crates.len());
+                    self.insert_input(input, lstmts, litem, lcrates);
+                }
+                Err(e) => {
+                    r = Err(e);
+                    break;
+                }
+            }
+        }
+
+        match r.and_then(|_| self.check_compile_dir()) {
+            Ok(()) => Ok(self),
+            Err(e) => {
+                *self.get_current_file_mut() = saved;
+                Err(e)
+            }
+        }
+    }
+
+    fn write_emitted(&mut self, output: &mut Output<output::Write>) {
+        for line in self.emitted.drain(..) {
+            output.write_line(&line);
+        }
+    }
+
+    /// In [`StdMode::NoStd`], errors if the input uses `std`.
+    ///
+    /// [`StdMode::NoStd`]: linking::StdMode::NoStd
+    fn check_std_mode(&self, input: &Input) -> Result<(), String> {
+        if self.linking.std_mode == linking::StdMode::Std {
+            return Ok(());
+        }
+
+        let code = input
+            .items
+            .iter()
+            .map(|x| x.0.as_str())
+            .chain(input.stmts.iter().map(|x| x.expr.as_str()));
+        for code in code {
+            if let Some(usage) = std_usage(code) {
+                return Err(format!(
+                    "`{}` is not available in no_std mode, use `core` or `alloc` instead: {}",
+                    usage, code
+                ));
+            }
+        }
+
+        Ok(())
+    }
+
+    /// The checks an input must pass before it is compiled.
+    fn check_input(&self, input: &Input) -> Result<(), String> {
+        self.check_std_mode(input)
+            .and_then(|_| self.check_out_prefix(input))
+            .and_then(|_| self.check_papyrus_crate(input))
+    }
+
+    /// When data is linked, a warning if the input binds the name of the data argument, which
+    /// shadows the data. The input is still evaluated.
+    fn data_name_warning(&self, input: &Input) -> Option<String> {
+        if self.linking.data_type.is_none() {
+            return None;
+        }
+
+        let name = &self.linking.data_name;
+        let binds = input
+            .items
+            .iter()
+            .map(|x| x.0.as_str())
+            .chain(input.stmts.iter().map(|x| x.expr.as_str()))
+            .any(|code| crate::input::binds_name(code, name));
+
+        if binds {
+            Some(format!(
+                "warning: `{0}` is the name of the linked data and binding it shadows the data, \
+                 use a different name or rename the data with `with_data_name`",
+                name
+            ))
+        } else {
+            None
+        }
+    }
+
+    /// Errors if a linked crate, the data, or an item or `let` binding in the input would collide
+    /// with the result bindings (`out#`).
+    fn check_out_prefix(&self, input: &Input) -> Result<(), String> {
+        let collision = self
+            .linking
+            .out_collision()
+            .map(String::from)
+            .or_else(|| {
+                input
+                    .items
+                    .iter()
+                    .filter_map(|x| crate::input::pattern_item_name(&x.0))
+                    .find(|x| self.linking.is_out_name(x))
+            })
+            .or_else(|| {
+                input
+                    .stmts
+                    .iter()
+                    .filter_map(|x| crate::input::let_binding_name(&x.expr))
+                    .find(|x| self.linking.is_out_name(x))
+            });
+
+        match collision {
+            Some(name) => Err(format!(
+                "`{}` collides with the names results are bound to, \
+                 use a different name or change the prefix with `with_out_prefix`",
+                name
+            )),
+            None => Ok(()),
+        }
+    }
+
+    /// Errors if the input adds the `papyrus` crate while a linked crate depends on papyrus. The
+    /// added crate would be a second papyrus, distinct from the one the linked crate is built with.
+    fn check_papyrus_crate(&self, input: &Input) -> Result<(), String> {
+        if !input.crates.iter().any(|x| x.cargo_name == "papyrus") {
+            return Ok(());
+        }
+
+        match self.linking.papyrus_dependent() {
+            Some(name) => Err(format!(
+                "`papyrus` is a dependency of the linked crate `{0}` and adding it would link a \
+                 second, conflicting version, re-export it from `{0}` with `pub use papyrus;` and \
+                 use `{0}::papyrus` instead",
+                name
+            )),
+            None => Ok(()),
+        }
+    }
+
+    /// Builds the compilation directory and type checks it.
+    fn check_compile_dir(&mut self) -> Result<(), String> {
+        compile::build_compile_dir_with(
+            &self.compilation_dir,
+            &self.mods_map,
+            &self.linking,
+            &self.static_files,
+            |src| self.transform_source(src),
+        )
+        .map_err(|e| format!("failed to build compile directory: {}", e))?;
+
+        self.last_compile_command = Some(compile::command_line(
+            &self.compilation_dir,
+            &self.linking,
+            true,
+        ));
+        compile::check(&self.compilation_dir, &self.linking, |_| ()).map_err(|e| e.to_string())
+    }
+
+    fn handle_command<F, R>(
         &mut self,
         cmds: &str,
-        writer: &mut W,
+        writer: &mut Output<output::Write>,
         obtain_mut_app_data: F,
     ) -> Result<Cow<'static, str>, Signal>
     where
         F: FnOnce() -> R,
         R: DerefMut<Target = D>,
-        W: io::Write,
     {
         use cmdtree::LineResult as lr;
 
@@ -199,6 +510,10 @@ impl<D> ReplData<D> {
                 CommandResult::SwitchModule(path) => {
                     Cow::Borrowed(crate::cmds::switch_module(self, &path))
                 }
+                CommandResult::ClearOutput => {
+                    writer.clear();
+                    Cow::Borrowed("")
+                }
 
                 CommandResult::ActionOnReplData(action) => Cow::Owned(action(self, writer)),
                 CommandResult::ActionOnAppData(action) => {
@@ -228,10 +543,71 @@ impl<D> ReplData<D> {
         Fbrw: FnOnce() -> Rbrw,
         Rbrw: Deref<Target = D>,
     {
+        self.eval_metrics = EvalMetrics::default();
+        self.last_out_type = None;
+
+        if let Err(e) = self.check_input(&input) {
+            return EvalOutput::Error(e.into());
+        }
+
+        if let Some(warning) = self.data_name_warning(&input) {
+            warn!("{}", warning);
+            writer.write_line(&warning);
+        }
+
         let (nitems, ncrates) = (input.items.len(), input.crates.len());
 
+        let report_assertion = self.assertion_reporting
+            && !self.linking.mutable
+            && input.items.is_empty()
+            && input.crates.is_empty()
+            && match input.stmts.as_slice() {
+                [stmt] => crate::input::is_assertion(&stmt.expr),
+                _ => false,
+            };
+
+        let echo = if self.echo_bindings && !self.linking.mutable {
+            echoed_binding(&input)
+        } else {
+            None
+        };
+
+        // the binding is returned as the result
+        if let Some(name) = &echo {
+            input.stmts.push(code::Statement {
+                expr: name.clone(),
+                semi: false,
+            });
+        }
+
         let has_stmts = !input.stmts.is_empty();
 
+        // checked inputs are not kept, otherwise a later evaluation would run them
+        let checked_src = if self.run {
+            None
+        } else {
+            Some(self.current_src().clone())
+        };
+
+        // an inline module replaces an earlier module with the same name
+        let mut replaced = Vec::new();
+        if self.editing.is_none() {
+            let names = input
+                .items
+                .iter()
+                .filter_map(|x| crate::input::mod_item_name(&x.0))
+                .collect::<Vec<_>>();
+            if !names.is_empty() {
+                let src = self.get_current_file_mut();
+                for idx in (0..src.items.len()).rev() {
+                    let name = crate::input::mod_item_name(&src.items[idx].0);
+                    if name.map(|x| names.contains(&x)).unwrap_or(false) {
+                        replaced.push((idx, src.items.remove(idx)));
+                    }
+                }
+            }
+        }
+
         let (lstmts, litem, lcrates) = {
             let src = self.current_src();
             (src.stmts.len(), src.items.len(), src.crates.len())
@@ -280,10 +656,20 @@ impl<D> ReplData<D> {
             (lstmts, litem, lcrates)
         };
 
+        // `:edit last` can replace a single statement group or item
+        let ei = match (has_stmts, nitems, ncrates) {
+            (true, 0, 0) => Some((Editing::Stmt, stmt_idx)),
+            (false, 1, 0) => Some((Editing::Item, item_idx)),
+            _ => None,
+        };
+        self.last_input_idx =
+            ei.map(|(editing, index)| (self.current_mod.clone(), EditingIndex { editing, index }));
+
         self.insert_input(input, stmt_idx, item_idx, crate_idx);
 
         let maybe_pop_input = |repl_data: &mut ReplData<D>| {
             if undo {
+                repl_data.last_input_idx = None;
                 let src = repl_data.get_current_file_mut();
 
                 if has_stmts {
@@ -297,24 +683,59 @@ impl<D> ReplData<D> {
                 for _ in 0..ncrates {
                     src.crates.remove(crate_idx);
                 }
+
+                for (idx, item) in replaced.iter().rev() {
+                    src.items.insert(*idx, item.clone());
+                }
             }
         };
 
         // build directory
-        let res = compile::build_compile_dir(
+        let timer = Instant::now();
+        let res = compile::build_compile_dir_with(
             &self.compilation_dir,
             &self.mods_map,
             &self.linking,
             &self.static_files,
+            |src| self.transform_source(src),
         );
+        self.eval_metrics.codegen = timer.elapsed();
         if let Err(e) = res {
             maybe_pop_input(self); // failed so don't save
-            return EvalOutput::Print(Cow::Owned(format!(
+            return EvalOutput::Error(Cow::Owned(format!(
                 "failed to build compile directory: {}",
                 e
             )));
         }
 
+        self.last_compile_command = Some(compile::command_line(
+            &self.compilation_dir,
+            &self.linking,
+            !self.run,
+        ));
+
+        let timer = Instant::now();
+
+        if !self.run {
+            let r = compile::check(&self.compilation_dir, &self.linking, |line| {
+                writer.erase_last_line();
+                writer.write_str(line);
+            });
+
+            writer.erase_last_line();
+            self.eval_metrics.compile = timer.elapsed();
+
+            if let Some(src) = checked_src {
+                *self.get_current_file_mut() = src;
+                self.last_input_idx = None;
+            }
+
+            return match r {
+                Ok(()) => EvalOutput::Checked,
+                Err(e) => EvalOutput::Error(Cow::Owned(self.compilation_error_msg(e))),
+            };
+        }
+
         // compile
         let lib_file = compile::compile(&self.compilation_dir, &self.linking, |line| {
             writer.erase_last_line();
@@ -322,17 +743,37 @@ impl<D> ReplData<D> {
         });
 
         writer.erase_last_line();
+        self.eval_metrics.compile = timer.elapsed();
 
         let lib_file = match lib_file {
             Ok(f) => f,
             Err(e) => {
                 maybe_pop_input(self); // failed so don't save
-                return EvalOutput::Print(Cow::Owned(format!("{}", e)));
+                return EvalOutput::Error(Cow::Owned(self.compilation_error_msg(e)));
             }
         };
 
-        if has_stmts {
+        if let Some(target) = &self.linking.target {
+            // can not execute code compiled for another target
+            EvalOutput::Print(Cow::Owned(format!(
+                "compiled for {}: {}",
+                target,
+                lib_file.display()
+            )))
+        } else if has_stmts {
             // execute
+            let vfiles = match VirtualFiles::materialize(&self.virtual_files) {
+                Ok(x) => x,
+                Err(e) => {
+                    maybe_pop_input(self); // failed so don't save
+                    return EvalOutput::Error(Cow::Owned(format!(
+                        "failed to write virtual files: {}",
+                        e
+                    )));
+                }
+            };
+
+            let timer = Instant::now();
             let exec_res = {
                 // once compilation succeeds and we are going to evaluate it (which libloads) we
                 // first rename the files to avoid locking for the next compilation that might
@@ -340,20 +781,30 @@ impl<D> ReplData<D> {
                 let lib_file = compile::unshackle_library_file(lib_file);
 
                 let mut fn_name = String::new();
-                code::eval_fn_name(&code::into_mod_path_vec(self.current_mod()), &mut fn_name);
+                code::eval_fn_name(
+                    &code::into_mod_path_vec(self.current_mod()),
+                    &self.linking.eval_fn_prefix,
+                    &self.linking.eval_fn_name,
+                    &mut fn_name,
+                );
 
                 if self.linking.mutable {
                     let mut r = obtain_mut_data();
                     let app_data: &mut D = r.borrow_mut();
-                    compile::exec(&lib_file, &fn_name, app_data)
+                    compile::exec(&lib_file, &fn_name, app_data, self.linking.panic_strategy)
                 } else {
                     let r = obtain_brw_data();
                     let app_data: &D = r.borrow();
-                    compile::exec(&lib_file, &fn_name, app_data)
+                    compile::exec(&lib_file, &fn_name, app_data, self.linking.panic_strategy)
                 }
             };
+            self.eval_metrics.run = timer.elapsed();
+            drop(vfiles); // remove as soon as execution finishes
+            self.limit_disk_usage();
             match exec_res {
                 Ok((kserd, lib)) => {
+                    let (kserd, ty) = code::split_out_type(kserd);
+
                     // store vec, maybe
                     add_to_limit_vec(
                         &mut self.loadedlibs,
@@ -361,17 +812,37 @@ impl<D> ReplData<D> {
                         self.loaded_libs_size_limit,
                     );
 
-                    if self.linking.mutable {
+                    if report_assertion {
+                        maybe_pop_input(self); // don't save assertions
+                        EvalOutput::Print(Cow::Borrowed("✓ assertion passed"))
+                    } else if self.linking.mutable {
                         maybe_pop_input(self); // don't save mutating inputs
                         EvalOutput::Print(Cow::Owned(format!("finished mutating block: {}", kserd)))
                     // don't print as `out#`
                     } else {
-                        EvalOutput::Data(kserd)
+                        if !self.out_capture {
+                            release_result(self.get_current_file_mut(), stmt_idx);
+                        } else if let Some(ty) = &ty {
+                            let n = self.current_src().out_count().saturating_sub(1);
+                            self.out_types
+                                .insert((self.current_mod.clone(), n), ty.clone());
+                        }
+                        // also kept for a released result, it is still printed
+                        self.last_out_type = ty;
+                        match echo {
+                            Some(name) => EvalOutput::Binding(name, kserd),
+                            None => EvalOutput::Data(kserd),
+                        }
                     }
                 }
+                Err(compile::ExecError::Panic(Some(msg))) if report_assertion => {
+                    maybe_pop_input(self); // failed so don't save
+                    let msg = self.filter_panic_message(&msg);
+                    EvalOutput::Error(Cow::Owned(format!("✗ {}", msg)))
+                }
                 Err(e) => {
                     maybe_pop_input(self); // failed so don't save
-                    EvalOutput::Print(Cow::Borrowed(e))
+                    EvalOutput::Error(Cow::Owned(e.to_string()))
                 }
             }
         } else {
@@ -380,6 +851,58 @@ impl<D> ReplData<D> {
         }
     }
 
+    /// The message of a compilation error, with suggestions for names which could not be found
+    /// if enabled.
+    fn compilation_error_msg(&self, error: compile::CompilationError) -> String {
+        let mut msg = error.to_string();
+        if self.name_suggestions {
+            let suggestions = self.suggest_names(&unresolved_names(&msg));
+            msg.push_str(&suggestions);
+        }
+        msg
+    }
+
+    /// Suggests close matches for each name from the names used in the current module.
+    fn suggest_names(&self, names: &[&str]) -> String {
+        if names.is_empty() {
+            return String::new();
+        }
+
+        let src = self.current_src();
+        let code = src
+            .items
+            .iter()
+            .map(|x| x.0.clone())
+            .chain(src.stmts.iter().map(StmtGrp::src_line))
+            .chain(src.crates.iter().map(|x| x.src_line.clone()))
+            .collect::<Vec<_>>();
+
+        let mut candidates = code
+            .iter()
+            .flat_map(|code| identifiers(code).into_iter().map(move |x| &code[x]))
+            .filter(|x| !linking::KEYWORDS.contains(x))
+            .map(ToString::to_string)
+            .chain((0..src.out_count()).map(|i| format!("{}{}", self.linking.out_prefix, i)))
+            .chain(self.linked_crates().into_iter().map(ToString::to_string))
+            .collect::<Vec<_>>();
+        candidates.sort();
+        candidates.dedup();
+
+        let mut suggestions = String::new();
+        for name in names {
+            let matches =
+                crate::complete::close_matches(name, candidates.iter().map(|x| x.as_str()));
+            if !matches.is_empty() {
+                suggestions.push_str(&format!(
+                    "\n`{}` was not found, did you mean: {}?",
+                    name,
+                    matches.into_iter().take(3).collect::<Vec<_>>().join(", ")
+                ));
+            }
+        }
+        suggestions
+    }
+
     fn insert_input(&mut self, input: Input, stmt_idx: usize, item_idx: usize, crate_idx: usize) {
         let Input {
             items,
@@ -390,7 +913,7 @@ impl<D> ReplData<D> {
         let src = self.get_current_file_mut();
 
         if !stmts.is_empty() {
-            src.stmts.insert(stmt_idx, StmtGrp(stmts));
+            src.stmts.insert(stmt_idx, StmtGrp(stmts, false));
         }
 
         for item in items.into_iter().rev() {
@@ -410,6 +933,139 @@ impl<D> ReplData<D> {
     }
 }
 
+/// Finds the first use of `std` in `code`, either a `std::` path or a macro which is only
+/// available in `std`, such as `println!`.
+fn std_usage(code: &str) -> Option<String> {
+    const STD_MACROS: &[&str] = &["println", "print", "eprintln", "eprint", "dbg"];
+
+    identifiers(code).into_iter().find_map(|span| {
+        let ident = &code[span.clone()];
+        let rest = code[span.end..].trim_start();
+        if ident == "std" && rest.starts_with("::") {
+            Some("std".to_string())
+        } else if rest.starts_with('!') && !rest.starts_with("!=") && STD_MACROS.contains(&ident) {
+            Some(format!("{}!", ident))
+        } else {
+            None
+        }
+    })
+}
+
+/// The spans of the identifiers (and keywords) in `code`, skipping string and char literals.
+fn identifiers(code: &str) -> Vec<Range<usize>> {
+    let mut idents = Vec::new();
+
+    let mut chars = code.char_indices().peekable();
+    while let Some((i, c)) = chars.next() {
+        if c == '"' {
+            // skip string literals
+            let mut escaped = false;
+            for (_, c) in &mut chars {
+                match c {
+                    _ if escaped => escaped = false,
+                    '\\' => escaped = true,
+                    '"' => break,
+                    _ => (),
+                }
+            }
+        } else if c == '\'' {
+            // skip char literals, leaving lifetimes
+            let rest = &code[i + 1..];
+            if rest.starts_with('\\') {
+                chars.find(|x| x.0 > i + 2 && x.1 == '\'');
+            } else if rest.chars().nth(1) == Some('\'') {
+                chars.nth(1);
+            }
+        } else if c.is_alphabetic() || c == '_' {
+            let mut end = i + c.len_utf8();
+            while let Some(&(j, c)) = chars.peek() {
+                if !(c.is_alphanumeric() || c == '_') {
+                    break;
+                }
+                end = j + c.len_utf8();
+                chars.next();
+            }
+
+            idents.push(i..end);
+        }
+    }
+
+    idents
+}
+
+/// The names a compilation error could not find, such as `x` in
+/// ``cannot find value `x` in this scope``.
+fn unresolved_names(error: &str) -> Vec<&str> {
+    let mut names = Vec::new();
+    for line in error.lines() {
+        let msg = match line.find("cannot find ") {
+            Some(i) if line[i..].contains(" in this scope") => &line[i..],
+            _ => continue,
+        };
+        if let Some(name) = msg.split('`').nth(1) {
+            if !names.contains(&name) {
+                names.push(name);
+            }
+        }
+    }
+    names
+}
+
+/// Virtual files written to disk, which are removed when dropped.
+struct VirtualFiles {
+    files: Vec<PathBuf>,
+    dirs: Vec<PathBuf>,
+}
+
+impl VirtualFiles {
+    fn materialize(files: &[(PathBuf, Vec<u8>)]) -> io::Result<Self> {
+        let mut vfiles = VirtualFiles {
+            files: Vec::with_capacity(files.len()),
+            dirs: Vec::new(),
+        };
+
+        for (path, bytes) in files {
+            if path.exists() {
+                return Err(io::Error::new(
+                    io::ErrorKind::AlreadyExists,
+                    format!("`{}` already exists", path.display()),
+                ));
+            }
+
+            // record directories that need creating, the outer most first
+            let mut missing = path
+                .ancestors()
+                .skip(1)
+                .filter(|p| !p.as_os_str().is_empty())
+                .take_while(|p| !p.exists())
+                .map(Path::to_path_buf)
+                .collect::<Vec<_>>();
+            missing.reverse();
+            for dir in missing {
+                fs::create_dir(&dir)?;
+                vfiles.dirs.push(dir);
+            }
+
+            fs::write(path, bytes)?;
+            vfiles.files.push(path.clone());
+        }
+
+        Ok(vfiles)
+    }
+}
+
+impl Drop for VirtualFiles {
+    fn drop(&mut self) {
+        for file in &self.files {
+            fs::remove_file(file).ok();
+        }
+        // inner most directories first, only removed if empty
+        for dir in self.dirs.iter().rev() {
+            fs::remove_dir(dir).ok();
+        }
+    }
+}
+
 fn add_to_limit_vec<T>(store: &mut VecDeque<T>, item: T, limit: usize) {
     match (limit, store.len()) {
         (0, 0) => (),             // do nothing, lib will drop after this
@@ -422,6 +1078,51 @@ fn add_to_limit_vec<T>(store: &mut VecDeque<T>, item: T, limit: usize) {
     }
 }
 
+/// Removes the result from the statement group at `idx`, so it is not bound to `out#`.
+///
+/// The group is removed if no statements remain, otherwise it is marked as released.
+pub(crate) fn release_result(src: &mut SourceCode, idx: usize) {
+    let grp = match src.stmts.get_mut(idx) {
+        Some(grp) if grp.has_result() => grp,
+        _ => return,
+    };
+    grp.0.pop();
+    grp.1 = true;
+
+    if grp.0.is_empty() {
+        src.stmts.remove(idx);
+    }
+}
+
+#[test]
+fn release_result_test() {
+    let stmt = |expr: &str, semi| code::Statement {
+        expr: expr.to_string(),
+        semi,
+    };
+
+    let mut src = SourceCode::default();
+    src.stmts.push(StmtGrp(vec![stmt("2 + 2", false)], false));
+    src.stmts.push(StmtGrp(
+        vec![stmt("let a = 1", true), stmt("a", false)],
+        false,
+    ));
+    src.stmts
+        .push(StmtGrp(vec![stmt("f()", true), stmt("a", false)], false));
+
+    release_result(&mut src, 2);
+    assert_eq!(src.stmts[2].0, vec![stmt("f()", true)]);
+    release_result(&mut src, 1);
+    assert_eq!(src.stmts[1].0, vec![stmt("let a = 1", true)]);
+    release_result(&mut src, 0);
+    assert_eq!(src.stmts.len(), 2);
+    assert!(src.stmts.iter().all(|x| !x.has_result()));
+
+    // released groups are not released again
+    release_result(&mut src, 1);
+    assert_eq!(src.stmts[1].0, vec![stmt("f()", true)]);
+}
+
 #[test]
 fn vec_limited_testing() {
     let mut vec: VecDeque<i32> = VecDeque::new();
@@ -444,3 +1145,64 @@ fn vec_limited_testing() {
     add_to_limit_vec(&mut vec, 2, 1);
     assert_eq!(&vec, &[2]);
 }
+
+#[test]
+fn virtual_files_test() {
+    let dir = PathBuf::from("target/testing/virtual_files_test");
+    fs::remove_dir_all(&dir).ok();
+    fs::create_dir_all(&dir).unwrap();
+
+    let files = vec![
+        (dir.join("a.txt"), b"a".to_vec()),
+        (dir.join("b/c/d.txt"), b"d".to_vec()),
+    ];
+
+    let vfiles = VirtualFiles::materialize(&files).unwrap();
+    assert_eq!(fs::read_to_string(dir.join("a.txt")).unwrap(), "a");
+    assert_eq!(fs::read_to_string(dir.join("b/c/d.txt")).unwrap(), "d");
+    drop(vfiles);
+    assert!(!dir.join("a.txt").exists());
+    assert!(!dir.join("b").exists());
+
+    // existing files are not overwritten
+    fs::write(dir.join("a.txt"), "existing").unwrap();
+    assert!(VirtualFiles::materialize(&files).is_err());
+    assert_eq!(fs::read_to_string(dir.join("a.txt")).unwrap(), "existing");
+}
+
+#[test]
+fn std_usage_test() {
+    assert_eq!(std_usage("let a = 1;"), None);
+    assert_eq!(
+        std_usage("std::mem::swap(&mut a, &mut b)"),
+        Some("std".to_string())
+    );
+    assert_eq!(std_usage("use std :: fmt ;"), Some("std".to_string()));
+    assert_eq!(
+        std_usage("println ! (\"{}\", a)"),
+        Some("println!".to_string())
+    );
+    assert_eq!(std_usage("core::mem::swap(&mut a, &mut b)"), None);
+    assert_eq!(std_usage("my_std::a()"), None);
+    assert_eq!(std_usage("let std = 1; std + 1"), None);
+    assert_eq!(std_usage("\"std::a println!\""), None);
+    assert_eq!(std_usage("('\"', \"std::\", '\\'')"), None);
+    assert_eq!(
+        std_usage("fn a<'a>(x: &'a str) { dbg!(x) }"),
+        Some("dbg!".to_string())
+    );
+    assert_eq!(std_usage("print != 1"), None);
+}
+
+#[test]
+fn unresolved_names_test() {
+    let error = "error[E0425]: cannot find value `vlaue` in this scope
+ --> src/lib.rs:4:1
+error[E0425]: cannot find function `fo` in this scope
+error[E0412]: cannot find type `Strng` in this scope
+error[E0425]: cannot find value `vlaue` in this scope
+error[E0425]: cannot find function `f` in module `m`
+error[E0308]: mismatched types";
+    assert_eq!(unresolved_names(error), vec!["vlaue", "fo", "Strng"]);
+    assert!(unresolved_names("").is_empty());
+}
@@ -1,11 +1,24 @@
 //! The REPL API.
 //!
 //! The REPL uses a state machine to control what methods can be applied to it.
+//!
+//! # Global State
+//! Each evaluation compiles the module into a new library which is loaded into the process, and the
+//! statements of the module are run again in order. Statics, such as a `static` counter inside a
+//! function or the statics of a linked crate, belong to the library they are compiled into, so
+//! every evaluation starts with them freshly initialised. Mutations to a static do not carry over
+//! to the next evaluation, other than by the statements being run again.
+//!
+//! State which belongs to the process is shared by all evaluations and the host application.
+//! Environment variables, the current directory, files, and threads spawned by evaluated code
+//! persist until changed or the process ends. Data which should persist, and be shared with the
+//! host, is best passed through the linked `app_data`.
 mod any_state;
 mod data;
 mod eval;
 mod print;
 mod read;
+mod session;
 
 use crate::{
     cmds::CommandResult,
@@ -24,8 +37,12 @@ use std::{
     fmt, fs, io,
     marker::PhantomData,
     path::{Path, PathBuf},
+    time::{Duration, Instant},
 };
 
+pub(crate) use self::session::{escape_line, unescape_line};
+pub use self::session::{Divergence, Replay};
+
 /// The repl structure. Stored as a state machine.
 /// See the [module level documentation] for more information.
 ///
@@ -56,11 +73,18 @@ pub struct ReplData<Data> {
     pub prompt_colour: Color,
     /// The colour of the out component. ie `[out0]`.
     pub out_colour: Color,
+    /// Colour the out component of `Result` and `Option` results by their variant, instead of
+    /// `out_colour`: green for `Ok` and `Some`, red for `Err`, and yellow for `None`. The value is
+    /// not altered.
+    pub variant_colours: bool,
 
     /// The directory for which compilation is done within.
     /// Defaults to `$HOME/.papyrus/`.
     compilation_dir: PathBuf,
 
+    /// The instance name claimed by [`ReplData::with_instance_name`].
+    instance_name: Option<InstanceName>,
+
     /// The external crate linking configuration,
     linking: LinkingConfiguration,
 
@@ -98,7 +122,96 @@ pub struct ReplData<Data> {
     ///
     /// The default is to keep the size limit at zero, thus ensuring no libraries are kept in
     /// memory. This is recommended unless issues are arising from esoteric use cases.
+    ///
+    /// Threads spawned by evaluated code, such as `std::thread::spawn(|| loop {})`, are not
+    /// joined, the evaluation returns while they keep running. Such a thread runs code from the
+    /// library, so once the library is dropped the thread **crashes the host process**. With the
+    /// default limit the library is dropped as soon as the evaluation returns. Only spawn threads
+    /// which finish before their library is dropped, or keep enough libraries loaded.
     pub loaded_libs_size_limit: usize,
+
+    /// Transcript file that inputs and outputs are appended to.
+    transcript: Option<Transcript>,
+
+    /// The type names of evaluated results, keyed by module and statement index.
+    out_types: fxhash::FxHashMap<(PathBuf, usize), String>,
+
+    /// The type name of the last evaluated result, whether or not it is kept as `out#`.
+    last_out_type: Option<String>,
+
+    /// Cached toolchain information.
+    toolchain_info: Option<crate::compile::ToolchainInfo>,
+
+    /// Run evaluated code. If `false`, code is only type checked.
+    run: bool,
+
+    /// Report the outcome of assertion inputs, such as `assert_eq!(a, 1)`.
+    assertion_reporting: bool,
+
+    /// Output the value of `let name = expr;` inputs.
+    echo_bindings: bool,
+
+    /// Keep the results of inputs as `out#` bindings.
+    out_capture: bool,
+
+    /// The width results are wrapped to, if any.
+    result_width: Option<usize>,
+
+    /// The number of decimal places floating point results are formatted to, if any.
+    float_precision: Option<usize>,
+
+    /// The disk space, in bytes, the session is capped to, if any.
+    max_disk_usage: Option<u64>,
+
+    /// Suggest close matches for names a compilation error could not find.
+    name_suggestions: bool,
+
+    /// The encoding of bytes written to the output.
+    output_encoding: output::Encoding,
+
+    /// Complete input when tab is pressed in the terminal REPL.
+    completion: bool,
+
+    /// Message shown before the first prompt of an interactive session.
+    banner: Option<String>,
+
+    /// Lines emitted by commands or host code, written to the output by the next evaluation.
+    emitted: Vec<String>,
+
+    /// The text of the last program input.
+    last_input: Option<String>,
+
+    /// Where the last program input is kept, if it is a single statement group or item which
+    /// `:edit last` can replace.
+    last_input_idx: Option<(PathBuf, EditingIndex)>,
+
+    /// Transforms applied to raw input, in the order they were added.
+    input_transforms: Vec<Box<dyn Fn(&str) -> String + Send>>,
+
+    /// Transforms applied to the generated source code, in the order they were added.
+    source_transforms: Vec<Box<dyn Fn(String) -> String + Send>>,
+
+    /// Filter applied to captured panic messages before they are output.
+    panic_message_filter: Option<Box<dyn Fn(&str) -> String + Send>>,
+
+    /// Session recording that inputs and outputs are appended to.
+    session: Option<session::SessionRecording>,
+
+    /// Files written to disk for the duration of each execution.
+    virtual_files: Vec<(PathBuf, Vec<u8>)>,
+
+    /// Stage timings of the last program evaluation.
+    eval_metrics: EvalMetrics,
+
+    /// The command line of the last compilation.
+    last_compile_command: Option<String>,
+}
+
+/// A transcript of the REPL session, written to disk after each evaluation.
+struct Transcript {
+    path: PathBuf,
+    /// The prompt and input which is being evaluated.
+    input: String,
 }
 
 /// Repl read state.
@@ -131,7 +244,49 @@ pub struct Print {
 enum EvalOutput {
     /// If there is data, then it should be prefixed with `[out#]`.
     Data(Kserd<'static>),
+    /// The value of an echoed `let` binding, it should be prefixed with the binding name.
+    Binding(String, Kserd<'static>),
+    /// A message from a successful input, such as the output of a command.
     Print(Cow<'static, str>),
+    /// The input failed, such as a compilation error, with the message to print.
+    Error(Cow<'static, str>),
+    /// The input type checked without being run, it is printed as `OK`.
+    Checked,
+}
+
+/// Timings of the stages of a program evaluation, see [`ReplData::eval_metrics`].
+///
+/// Stages which are not reached, such as running when compilation fails, are zero. Compiling the
+/// library includes linking it, as both are done by a single `cargo` invocation.
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+pub struct EvalMetrics {
+    /// Generating the source code and writing the compilation directory.
+    pub codegen: Duration,
+    /// Compiling and linking the library, or type checking it if code is not run.
+    pub compile: Duration,
+    /// Loading the library and running the evaluated code.
+    pub run: Duration,
+}
+
+/// The failure of an input in [`ReplData::eval_batch`].
+#[derive(Debug, PartialEq)]
+pub struct BatchError {
+    /// The index of the failing input in the batch.
+    pub index: usize,
+    /// The error message of the failing input.
+    pub error: String,
+}
+
+impl std::error::Error for BatchError {}
+
+impl fmt::Display for BatchError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "input {} of the batch failed: {}",
+            self.index, self.error
+        )
+    }
 }
 
 /// Represents an evaluating result. Signal should be checked and handled.
@@ -192,11 +347,188 @@ pub enum Editing {
     Crate,
 }
 
+/// The name of the binding if the input ends with a `let` statement which can be echoed.
+fn echoed_binding(input: &crate::code::Input) -> Option<String> {
+    input
+        .stmts
+        .last()
+        .filter(|x| x.semi)
+        .and_then(|x| crate::input::let_binding_name(&x.expr))
+}
+
+/// Formats the floating point numbers in the result `value` of type `ty` to `precision` decimal
+/// places.
+///
+/// A result of type `f32` or `f64` is formatted as a whole. Otherwise, if the type contains
+/// floats, such as `Vec<f64>` or a struct output through `Debug`, the decimal numbers in the value
+/// are formatted. Integers, `NaN`, and `inf` are left as is.
+fn format_floats(ty: &str, value: &str, precision: usize) -> String {
+    if ty == "f32" || ty == "f64" {
+        return value
+            .parse::<f64>()
+            .map(|x| format!("{:.*}", precision, x))
+            .unwrap_or_else(|_| value.to_string());
+    }
+
+    if !ty.contains("f32") && !ty.contains("f64") {
+        return value.to_string();
+    }
+
+    let mut s = String::with_capacity(value.len());
+    let mut chars = value.char_indices().peekable();
+    while let Some((start, first)) = chars.next() {
+        if first == '"' {
+            // string literals are left as is
+            s.push(first);
+            let mut escaped = false;
+            for (_, c) in &mut chars {
+                s.push(c);
+                match c {
+                    _ if escaped => escaped = false,
+                    '\\' => escaped = true,
+                    '"' => break,
+                    _ => (),
+                }
+            }
+            continue;
+        }
+
+        if first == '\'' {
+            // so are char literals
+            s.push(first);
+            let rest = &value[start + 1..];
+            let len = if rest.starts_with('\\') {
+                rest.get(2..).and_then(|x| x.find('\'')).map(|x| x + 3)
+            } else if rest.chars().nth(1) == Some('\'') {
+                rest.chars().next().map(|c| c.len_utf8() + 1)
+            } else {
+                None
+            };
+            if let Some(len) = len {
+                s.push_str(&rest[..len]);
+                while chars.peek().map_or(false, |x| x.0 <= start + len) {
+                    chars.next();
+                }
+            }
+            continue;
+        }
+
+        if !(first.is_alphanumeric() || first == '_') {
+            s.push(first);
+            continue;
+        }
+
+        // take the whole word, including the sign of an exponent such as `1e-7`
+        let mut end = start + first.len_utf8();
+        let mut prev = first;
+        while let Some(&(i, c)) = chars.peek() {
+            let exp_sign =
+                first.is_ascii_digit() && (c == '+' || c == '-') && (prev == 'e' || prev == 'E');
+            if c.is_alphanumeric() || c == '_' || c == '.' || exp_sign {
+                end = i + c.len_utf8();
+                prev = c;
+                chars.next();
+            } else {
+                break;
+            }
+        }
+
+        let word = &value[start..end];
+        let is_float =
+            first.is_ascii_digit() && word.contains(|c| c == '.' || c == 'e' || c == 'E');
+        match word.parse::<f64>() {
+            Ok(x) if is_float => s.push_str(&format!("{:.*}", precision, x)),
+            _ => s.push_str(word),
+        }
+    }
+
+    s
+}
+
 /// `$HOME/.papyrus`
 fn default_compile_dir() -> PathBuf {
     dirs::home_dir().unwrap_or_default().join(".papyrus/")
 }
 
+/// `$TMPDIR/papyrus/NAME`
+fn instance_compile_dir(name: &str) -> PathBuf {
+    std::env::temp_dir().join("papyrus").join(name)
+}
+
+/// Instance names claimed by live REPLs in this process.
+fn instance_names() -> &'static std::sync::Mutex<Vec<String>> {
+    static INIT: std::sync::Once = std::sync::Once::new();
+    static mut NAMES: *const std::sync::Mutex<Vec<String>> = std::ptr::null();
+
+    unsafe {
+        INIT.call_once(|| NAMES = Box::leak(Box::new(std::sync::Mutex::new(Vec::new()))));
+        &*NAMES
+    }
+}
+
+/// A claimed instance name, released when dropped.
+struct InstanceName(String);
+
+impl InstanceName {
+    /// Claim `name`, failing if another instance has claimed it.
+    fn claim(name: &str) -> io::Result<Self> {
+        let valid = !name.is_empty()
+            && name
+                .chars()
+                .all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '-');
+        if !valid {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "instance name must only contain alphanumeric characters, `_`, or `-`",
+            ));
+        }
+
+        let mut names = instance_names().lock().unwrap_or_else(|e| e.into_inner());
+        if names.iter().any(|x| x == name) {
+            Err(io::Error::new(
+                io::ErrorKind::AlreadyExists,
+                format!("instance name `{}` is in use by another repl", name),
+            ))
+        } else {
+            names.push(name.to_string());
+            Ok(Self(name.to_string()))
+        }
+    }
+}
+
+impl Drop for InstanceName {
+    fn drop(&mut self) {
+        let mut names = instance_names().lock().unwrap_or_else(|e| e.into_inner());
+        names.retain(|x| x != &self.0);
+    }
+}
+
+/// A banner showing the papyrus version and how to get help, see [`ReplData::with_banner`].
+///
+/// `papyrus VERSION -- type :help for help`
+pub fn default_banner() -> String {
+    format!(
+        "papyrus {} -- type {}help for help",
+        env!("CARGO_PKG_VERSION"),
+        crate::CMD_PREFIX
+    )
+}
+
+#[test]
+fn instance_name_test() {
+    let a = InstanceName::claim("instance_name_test").unwrap();
+    let e = InstanceName::claim("instance_name_test").err().unwrap();
+    assert_eq!(e.kind(), io::ErrorKind::AlreadyExists);
+    drop(a);
+    assert!(InstanceName::claim("instance_name_test").is_ok());
+
+    let e = InstanceName::claim("a/b").err().unwrap();
+    assert_eq!(e.kind(), io::ErrorKind::InvalidInput);
+    assert!(InstanceName::claim("").is_err());
+
+    assert!(instance_compile_dir("test").ends_with("papyrus/test"));
+}
+
 #[test]
 fn test_default_compile_dir() {
     let dir = default_compile_dir();
@@ -210,3 +542,54 @@ fn test_default_compile_dir() {
         assert!(dir.starts_with("/home/"));
     }
 }
+
+#[test]
+fn format_floats_test() {
+    assert_eq!(format_floats("f64", "0.3333333333333333", 4), "0.3333");
+    assert_eq!(format_floats("f32", "2", 2), "2.00");
+    assert_eq!(format_floats("f64", "NaN", 4), "NaN");
+    assert_eq!(format_floats("f64", "inf", 4), "inf");
+    assert_eq!(format_floats("f64", "-inf", 4), "-inf");
+    assert_eq!(format_floats("i32", "3", 4), "3");
+    assert_eq!(
+        format_floats(
+            "alloc::vec::Vec<f64>",
+            "[\n    1.5,\n    -0.126,\n    1e-7,\n]",
+            2
+        ),
+        "[\n    1.50,\n    -0.13,\n    0.00,\n]"
+    );
+    assert_eq!(
+        format_floats(
+            "(alloc::string::String, f64, usize)",
+            "(\"1.2.3\", 0.3333, 10)",
+            1
+        ),
+        "(\"1.2.3\", 0.3, 10)"
+    );
+    // string and char literals are left as is
+    assert_eq!(
+        format_floats("(&str, f64)", "(\"0.5\", 1.0)", 3),
+        "(\"0.5\", 1.000)"
+    );
+    assert_eq!(
+        format_floats("(&str, f64)", "(\"a \\\" 0.5\", 1.0)", 1),
+        "(\"a \\\" 0.5\", 1.0)"
+    );
+    assert_eq!(
+        format_floats("(char, char, f64)", "('1', '\\'', 0.3333)", 1),
+        "('1', '\\'', 0.3)"
+    );
+    // the type does not show it contains floats
+    assert_eq!(
+        format_floats("my::Point", "Point { x: 0.3333 }", 1),
+        "Point { x: 0.3333 }"
+    );
+}
+
+#[test]
+fn test_default_banner() {
+    let banner = default_banner();
+    assert!(banner.contains(env!("CARGO_PKG_VERSION")));
+    assert!(banner.ends_with(":help for help"));
+}
@@ -23,34 +23,74 @@ impl<D> Repl<Print, D> {
             data_mrker,
         } = self;
 
-        let repl_data = data;
+        let mut repl_data = data;
 
         let Print { mut output, data } = state;
 
         let mut kserd = None;
 
-        match data {
-            EvalOutput::Data(k) => {
-                let num = repl_data.current_src().stmts.len().saturating_sub(1);
+        repl_data.session_output(&data);
 
-                let out_stmt = format!("[out{}]", num);
+        match data {
+            EvalOutput::Data(_) | EvalOutput::Binding(..) => {
+                let num = repl_data.current_src().out_count().saturating_sub(1);
+
+                let (out_stmt, k) = match data {
+                    EvalOutput::Binding(name, k) => (name, k),
+                    EvalOutput::Data(k) => {
+                        (format!("[{}{}]", repl_data.linking.out_prefix, num), k)
+                    }
+                    _ => unreachable!("matched above"),
+                };
+
+                let value = repl_data.apply_float_precision(k.as_str_with_config(config));
+
+                let variant = if repl_data.variant_colours {
+                    repl_data
+                        .last_out_type
+                        .as_deref()
+                        .and_then(|ty| variant_colour(ty, &value))
+                } else {
+                    None
+                };
+                let out_colour = variant.unwrap_or(repl_data.out_colour);
+
+                let laid_out = repl_data.result_width.map(|width| {
+                    // the label is `path [out#]: `
+                    let indent =
+                        repl_data.cmdtree.path().chars().count() + out_stmt.chars().count();
+                    layout_result(&value, indent + 3, width)
+                });
 
                 let line = format!(
                     "{} {}: {}",
                     repl_data.cmdtree.path().color(repl_data.prompt_colour),
-                    out_stmt.color(repl_data.out_colour),
-                    k.as_str_with_config(config)
+                    out_stmt.color(out_colour),
+                    laid_out.as_ref().unwrap_or(&value)
                 );
 
                 output.write_line(&line);
 
+                repl_data.transcript_output(&format!(
+                    "{} {}: {}",
+                    repl_data.cmdtree.path(),
+                    out_stmt,
+                    value
+                ));
+
                 kserd = Some((num, k));
             }
-            EvalOutput::Print(print) => {
+            EvalOutput::Checked => {
+                output.write_line("OK");
+                repl_data.transcript_output("OK");
+            }
+            EvalOutput::Print(print) | EvalOutput::Error(print) => {
                 if print.len() > 0 {
                     // only write if there is something to write.
                     output.write_line(&print);
                 }
+
+                repl_data.transcript_output(&print);
             }
         }
 
@@ -69,10 +109,80 @@ impl<D> Repl<Print, D> {
     }
 }
 
+/// The colour of a `Result` or `Option` result, given its type name and formatted value.
+fn variant_colour(ty: &str, value: &str) -> Option<Color> {
+    let value = value.trim_start_matches('"');
+    let is = |variant: &str| {
+        value.starts_with(variant)
+            && !value[variant.len()..].starts_with(|c: char| c.is_alphanumeric() || c == '_')
+    };
+
+    if ty.starts_with("core::result::Result<") {
+        if is("Ok") {
+            Some(Color::Green)
+        } else if is("Err") {
+            Some(Color::Red)
+        } else {
+            None
+        }
+    } else if ty.starts_with("core::option::Option<") {
+        if is("Some") {
+            Some(Color::Green)
+        } else if is("None") {
+            Some(Color::Yellow)
+        } else {
+            None
+        }
+    } else {
+        None
+    }
+}
+
+/// Lays out a result `value` which follows a label `indent` columns wide.
+///
+/// Continuation lines are indented to line up under the first line. Lines are wrapped so they do
+/// not go past `width` columns, breaking at whitespace where possible. If the label leaves no
+/// room, lines are only indented.
+fn layout_result(value: &str, indent: usize, width: usize) -> String {
+    let avail = width.saturating_sub(indent);
+    let mut lines = Vec::new();
+
+    for mut line in value.lines() {
+        while avail > 0 && line.chars().count() > avail {
+            let split = line
+                .char_indices()
+                .nth(avail)
+                .map(|x| x.0)
+                .unwrap_or(line.len());
+            let (head, tail) = line.split_at(split);
+            let (head, tail) = if tail.starts_with(char::is_whitespace) {
+                (head, tail)
+            } else {
+                match head.rfind(char::is_whitespace) {
+                    Some(i) if !head[..i].trim().is_empty() => line.split_at(i),
+                    _ => (head, tail),
+                }
+            };
+            lines.push(head.trim_end());
+            line = tail.trim_start();
+        }
+        if !line.is_empty() || lines.is_empty() {
+            lines.push(line);
+        }
+    }
+
+    lines.join(&format!("\n{:1$}", "", indent))
+}
+
 fn prepare_read<D>(repl: &mut Repl<Read, D>) {
     repl.draw_prompt();
 
-    let editing_src = repl.data.editing.and_then(|ei| {
+    // keep source set by a command, such as `edit last`
+    if repl.data.editing_src.is_some() {
+        return;
+    }
+
+    repl.data.editing_src = repl.data.editing.and_then(|ei| {
         let src = repl.data.current_src();
 
         match ei.editing {
@@ -81,5 +191,41 @@ fn prepare_read<D>(repl: &mut Repl<Read, D>) {
             Editing::Stmt => src.stmts.get(ei.index).map(|x| x.src_line()),
         }
     });
-    repl.data.editing_src = editing_src;
+}
+
+#[test]
+fn variant_colour_test() {
+    let result = "core::result::Result<i32, alloc::string::String>";
+    assert_eq!(variant_colour(result, "Ok(1)"), Some(Color::Green));
+    assert_eq!(
+        variant_colour(result, "\"Err(\\\"no\\\")\""),
+        Some(Color::Red)
+    );
+    assert_eq!(variant_colour(result, "Okay"), None);
+
+    let option = "core::option::Option<i32>";
+    assert_eq!(variant_colour(option, "Some(1)"), Some(Color::Green));
+    assert_eq!(variant_colour(option, "None"), Some(Color::Yellow));
+
+    assert_eq!(variant_colour("i32", "1"), None);
+    assert_eq!(variant_colour("my::Option<i32>", "None"), None);
+}
+
+#[test]
+fn layout_result_test() {
+    assert_eq!(layout_result("short", 4, 20), "short");
+    assert_eq!(layout_result("a {\n  b\n}", 4, 20), "a {\n      b\n    }");
+    // breaks at whitespace
+    assert_eq!(
+        layout_result("one two three four", 4, 14),
+        "one two\n    three four"
+    );
+    assert_eq!(
+        layout_result("one two three four", 4, 11),
+        "one two\n    three\n    four"
+    );
+    // breaks within a word if there is no whitespace
+    assert_eq!(layout_result("abcdefghijkl", 4, 10), "abcdef\n    ghijkl");
+    // no room after the label
+    assert_eq!(layout_result("abc\ndef", 12, 10), "abc\n            def");
 }
@@ -49,11 +49,33 @@ impl<D> Repl<Read, D> {
     pub fn read(mut self) -> ReadResult<D> {
         let treat_as_cmd = !self.data.cmdtree.at_root();
 
-        let result = crate::input::determine_result(
-            self.state.output.input_buffer(),
-            self.state.output.input_buf_line(),
-            treat_as_cmd,
-        );
+        // transform once, the same text is parsed, recorded, and kept as the last input
+        let input = self
+            .data
+            .transform_input(self.state.output.input_buffer())
+            .into_owned();
+
+        let result = {
+            // the line is the whole input unless previous lines are stacked
+            let line = if self.more {
+                self.data
+                    .transform_input(self.state.output.input_buf_line())
+            } else {
+                Cow::Borrowed(input.as_str())
+            };
+            match crate::input::determine_result(&input, &line, treat_as_cmd) {
+                // an echoed binding is evaluated straight away
+                InputResult::More if self.data.echo_bindings => {
+                    match crate::input::parse_program(&input) {
+                        InputResult::Program(p) if echoed_binding(&p).is_some() => {
+                            InputResult::Program(p)
+                        }
+                        _ => InputResult::More,
+                    }
+                }
+                x => x,
+            }
+        };
 
         // have to push after as can't take mutable brw and last line
         // if done before will not register cmds
@@ -65,9 +87,21 @@ impl<D> Repl<Read, D> {
             ReadResult::Read(self)
         } else {
             self.more = false;
-            ReadResult::Eval(self.move_state(|s| Evaluate {
-                output: s.output.into_write(),
-                result,
+            if self.data.transcript.is_some() && result != InputResult::Empty {
+                let prompt = self.prompt(false);
+                self.data.transcript_input(&prompt, &input);
+            }
+            if self.data.session.is_some() && result != InputResult::Empty {
+                self.data.session_input(input.trim_end());
+            }
+            if let InputResult::Program(_) = &result {
+                self.data.last_input = Some(input.trim_end().to_string());
+            }
+            let encoding = self.data.output_encoding;
+            ReadResult::Eval(self.move_state(|s| {
+                let mut output = s.output.into_write();
+                output.set_encoding(encoding);
+                Evaluate { output, result }
             }))
         }
     }
@@ -83,11 +117,31 @@ impl<D> Repl<Read, D> {
         self.state.output.buffer()
     }
 
+    /// The current output as plain text, with ANSI escape codes removed.
+    ///
+    /// As with [`output`](Repl::output) this contains the prompt and all input, it is suitable
+    /// for copying or saving the whole scrollback.
+    pub fn plain_output(&self) -> String {
+        self.state.output.plain_buffer()
+    }
+
+    /// The OSC 8 hyperlinks in the output, such as for a frontend to show as clickable links.
+    /// See [`Output::hyperlinks`](output::Output::hyperlinks).
+    pub fn output_hyperlinks(&self) -> Vec<output::Hyperlink> {
+        self.state.output.hyperlinks()
+    }
+
     /// Begin listening to line change events on the output.
     pub fn output_listen(&mut self) -> output::Receiver {
         self.state.output.listen()
     }
 
+    /// A sender to inject line changes to the output listener, see
+    /// [`Output::sender`](output::Output::sender).
+    pub fn output_sender(&self) -> Option<output::Sender> {
+        self.state.output.sender()
+    }
+
     /// Close the sender side of the output channel.
     pub fn close_channel(&mut self) {
         self.state.output.close()
@@ -0,0 +1,633 @@
+//! Recording a session and replaying it.
+//!
+//! A session file contains the toolchain and linking configuration, followed by each input and
+//! its output. Each entry takes a single line, `key value`, with line breaks in the value
+//! escaped.
+use super::*;
+use crate::linking::{NativeLibKind, PanicStrategy, StdMode};
+use ::kserd::fmt::FormattingConfig;
+use std::io::Write;
+
+const SESSION_HEADER: &str = "papyrus-session 1";
+
+/// A session being recorded to a file.
+pub(super) struct SessionRecording {
+    path: PathBuf,
+}
+
+impl SessionRecording {
+    fn append(&self, key: &str, value: &str) -> io::Result<()> {
+        let mut file = fs::OpenOptions::new().append(true).open(&self.path)?;
+        writeln!(file, "{} {}", key, escape_line(value))
+    }
+}
+
+/// The outcome of [replaying](Repl::replay) a recorded session.
+#[derive(Debug)]
+pub struct Replay {
+    /// Each replayed input and its output.
+    pub entries: Vec<(String, String)>,
+    /// The first input whose output differs from the recorded output, if any.
+    pub divergence: Option<Divergence>,
+    /// Parts of the recorded session which could not be reproduced, such as a different
+    /// toolchain or a linked external library.
+    pub warnings: Vec<String>,
+}
+
+/// An input whose replayed output differs from the recorded output.
+#[derive(Debug, PartialEq)]
+pub struct Divergence {
+    /// The index of the input in the session.
+    pub index: usize,
+    /// The input.
+    pub input: String,
+    /// The output when the session was recorded.
+    pub recorded: String,
+    /// The output when the session was replayed.
+    pub replayed: String,
+}
+
+impl fmt::Display for Replay {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        for warning in &self.warnings {
+            writeln!(f, "warning: {}", warning)?;
+        }
+
+        for (input, output) in &self.entries {
+            writeln!(f, "> {}", input.trim_end())?;
+            if !output.is_empty() {
+                writeln!(f, "{}", output)?;
+            }
+        }
+
+        match &self.divergence {
+            Some(d) => write!(
+                f,
+                "input {} diverged\n  input: {}\n  recorded: {}\n  replayed: {}",
+                d.index,
+                d.input.trim_end(),
+                d.recorded,
+                d.replayed
+            ),
+            None => write!(f, "all outputs match the recording"),
+        }
+    }
+}
+
+impl<D> ReplData<D> {
+    /// Record the session to the file at `path`, so it can be [replayed](Repl::replay).
+    ///
+    /// The toolchain and linking configuration are written when recording starts, so configure
+    /// the REPL beforehand. Each input read by the REPL is then recorded with its output. The file
+    /// is overwritten if it exists. Input transforms are applied before an input is recorded.
+    pub fn with_session_recording(&mut self, path: PathBuf) -> io::Result<&mut Self> {
+        let mut file = fs::File::create(&path)?;
+        writeln!(file, "{}", SESSION_HEADER)?;
+
+        let info = self.toolchain_info()?.clone();
+        let mut lines = vec![
+            ("rustc", info.rustc_version),
+            ("host", info.host_triple),
+            ("edition", info.edition.to_string()),
+            (
+                "compilation_dir",
+                self.compilation_dir.display().to_string(),
+            ),
+        ];
+
+        let linking = &self.linking;
+        lines.push((
+            "panic_strategy",
+            panic_strategy_str(linking.panic_strategy).into(),
+        ));
+        lines.push(("std_mode", std_mode_str(linking.std_mode).into()));
+        lines.push(("auto_extern", linking.auto_extern.to_string()));
+        lines.push(("auto_std_imports", linking.auto_std_imports.to_string()));
+        lines.push(("eval_fn_name", linking.eval_fn_name.clone()));
+        lines.push(("eval_fn_prefix", linking.eval_fn_prefix.clone()));
+        lines.push(("out_prefix", linking.out_prefix.clone()));
+        lines.push(("debuginfo", linking.debuginfo.to_string()));
+        lines.push(("offline", linking.offline.to_string()));
+        if let Some(target) = &linking.target {
+            lines.push(("target", target.clone()));
+        }
+        for (name, kind) in &linking.native_libs {
+            lines.push(("native_lib", format!("{}={}", kind.as_str(), name)));
+        }
+        for arg in &linking.link_args {
+            lines.push(("link_arg", arg.clone()));
+        }
+        if let Some(linker) = &linking.linker {
+            lines.push(("linker", linker.display().to_string()));
+        }
+        if !linking.persistent_module_code.is_empty() {
+            lines.push((
+                "persistent_module_code",
+                linking.persistent_module_code.clone(),
+            ));
+        }
+        if let Some(data_type) = &linking.data_type {
+            lines.push(("data_type", data_type.clone()));
+            lines.push(("data_name", linking.data_name.clone()));
+        }
+        for external in &linking.external_libs {
+            lines.push(("external_lib", external.lib_name().to_string()));
+        }
+
+        for (key, value) in lines {
+            writeln!(file, "{} {}", key, escape_line(&value))?;
+        }
+        file.flush()?;
+
+        self.session = Some(SessionRecording { path });
+        Ok(self)
+    }
+
+    /// The path of the session recording, if one is set.
+    pub fn session_recording(&self) -> Option<&Path> {
+        self.session.as_ref().map(|x| x.path.as_path())
+    }
+
+    pub(super) fn session_input(&mut self, input: &str) {
+        self.session_append("input", input);
+    }
+
+    pub(super) fn session_output(&mut self, output: &EvalOutput) {
+        if self.session.is_some() {
+            let output = output.session_str();
+            self.session_append("output", &output);
+        }
+    }
+
+    fn session_append(&self, key: &str, value: &str) {
+        if let Some(s) = self.session.as_ref() {
+            if let Err(e) = s.append(key, value) {
+                error!(
+                    "writing to session recording '{}' failed: {}",
+                    s.path.display(),
+                    e
+                );
+            }
+        }
+    }
+}
+
+impl EvalOutput {
+    /// The output as recorded in a session, data uses the default formatting.
+    fn session_str(&self) -> Cow<str> {
+        match self {
+            EvalOutput::Data(k) | EvalOutput::Binding(_, k) => {
+                Cow::Owned(k.as_str_with_config(FormattingConfig::default()))
+            }
+            EvalOutput::Print(s) | EvalOutput::Error(s) => Cow::Borrowed(s),
+            EvalOutput::Checked => Cow::Borrowed("OK"),
+        }
+    }
+}
+
+impl Repl<Read, ()> {
+    /// Replay the session recorded at `path`.
+    ///
+    /// A REPL is set up with the recorded configuration and each input is evaluated in order.
+    /// The replayed outputs are compared to the recorded outputs, and the first input which
+    /// diverges is reported. The returned [`Replay`] can be displayed to print the outputs.
+    ///
+    /// Linked external libraries and data types can not be reproduced, these are reported as
+    /// warnings, as is a change in the `rustc` version. Since the session may have been recorded
+    /// elsewhere, the recorded compilation directory is not reused, and a recorded linker or link
+    /// arguments are not applied but reported as warnings.
+    pub fn replay<P: AsRef<Path>>(path: P) -> io::Result<Replay> {
+        let session = Session::parse(&fs::read_to_string(path)?)?;
+
+        let mut repl: Repl<Read, ()> = Repl::default();
+        let mut warnings = session.apply(&mut repl.data, true)?;
+
+        let rustc = repl.data.toolchain_info()?.rustc_version.clone();
+        if let Some(recorded) = session.get("rustc") {
+            if recorded != rustc {
+                warnings.push(format!(
+                    "session was recorded with `{}`, replaying with `{}`",
+                    recorded, rustc
+                ));
+            }
+        }
+
+        let mut entries = Vec::with_capacity(session.entries.len());
+        let mut divergence = None;
+
+        for (index, (input, recorded)) in session.entries.into_iter().enumerate() {
+            let recorded = recorded.unwrap_or_default();
+            repl.line_input(&input);
+
+            let (r, replayed, exit) = match repl.read() {
+                ReadResult::Read(r) => (r, "input is incomplete".to_string(), true),
+                ReadResult::Eval(r) => {
+                    let EvalResult { repl: r, signal } = r.eval(&mut ());
+                    let replayed = r.state.data.session_str().into_owned();
+                    (r.print().0, replayed, signal == Signal::Exit)
+                }
+            };
+            repl = r;
+
+            if divergence.is_none() && replayed != recorded {
+                divergence = Some(Divergence {
+                    index,
+                    input: input.clone(),
+                    recorded,
+                    replayed: replayed.clone(),
+                });
+            }
+
+            entries.push((input, replayed));
+
+            if exit {
+                break;
+            }
+        }
+
+        Ok(Replay {
+            entries,
+            divergence,
+            warnings,
+        })
+    }
+}
+
+impl<D> Repl<Read, D> {
+    /// Resume the session recorded at `path`, such as after the application was interrupted.
+    ///
+    /// The recorded configuration is applied and each recorded input is evaluated again to
+    /// rebuild the REPL state, after which recording continues by appending to the same file.
+    /// The recorded compilation directory is reused, so dependencies which are still built are
+    /// not compiled again. Cargo checks each artifact against the toolchain and configuration,
+    /// anything stale or incompatible is rebuilt rather than trusted.
+    ///
+    /// An input recorded without an output did not finish, likely because the application was
+    /// interrupted while evaluating it. It is _not_ evaluated again, since it may be what brought
+    /// the application down, and is removed from the recording. It is reported in the returned
+    /// warnings, along with the parts of the recording which can not be reproduced, as with
+    /// [`replay`](Repl::replay).
+    pub fn resume_from<P: AsRef<Path>>(
+        mut self,
+        path: P,
+        app_data: &mut D,
+    ) -> io::Result<(Self, Vec<String>)> {
+        let path = path.as_ref();
+        let mut recording = fs::read_to_string(path)?;
+        let mut session = Session::parse(&recording)?;
+
+        let mut warnings = session.apply(&mut self.data, false)?;
+
+        let rustc = &self.data.toolchain_info()?.rustc_version;
+        if let Some(recorded) = session.get("rustc") {
+            if recorded != rustc {
+                warnings.push(format!(
+                    "session was recorded with `{}`, resuming with `{}`, artifacts are rebuilt",
+                    recorded, rustc
+                ));
+            }
+        }
+
+        if let Some((input, None)) = session.entries.last() {
+            warnings.push(format!(
+                "input did not finish and is not evaluated: {}",
+                input.trim_end()
+            ));
+            session.entries.pop();
+
+            // remove it from the recording so it is not replayed either
+            if let Some(idx) = recording.rfind("\ninput ") {
+                recording.truncate(idx + 1);
+                fs::write(path, &recording)?;
+            }
+        }
+
+        // do not record the inputs again
+        self.data.session = None;
+
+        for (input, _) in session.entries {
+            self.line_input(&input);
+
+            let (repl, exit) = match self.read() {
+                ReadResult::Read(r) => (r, false),
+                ReadResult::Eval(r) => {
+                    let EvalResult { repl, signal } = r.eval(app_data);
+                    (repl.print().0, signal == Signal::Exit)
+                }
+            };
+            self = repl;
+
+            if exit {
+                break;
+            }
+        }
+
+        self.data.session = Some(SessionRecording {
+            path: path.to_path_buf(),
+        });
+
+        Ok((self, warnings))
+    }
+}
+
+/// A parsed session recording.
+struct Session {
+    config: Vec<(String, String)>,
+    /// Each input and its output, `None` if the output was not recorded.
+    entries: Vec<(String, Option<String>)>,
+}
+
+impl Session {
+    fn parse(s: &str) -> io::Result<Self> {
+        let invalid = |msg: String| io::Error::new(io::ErrorKind::InvalidData, msg);
+
+        let mut lines = s.lines();
+        if lines.next() != Some(SESSION_HEADER) {
+            return Err(invalid("not a papyrus session recording".to_string()));
+        }
+
+        let mut config = Vec::new();
+        let mut entries: Vec<(String, Option<String>)> = Vec::new();
+        for line in lines.filter(|x| !x.is_empty()) {
+            let mut split = line.splitn(2, ' ');
+            let key = split.next().unwrap_or_default();
+            let value = unescape_line(split.next().unwrap_or_default());
+
+            match key {
+                "input" => entries.push((value, None)),
+                "output" => match entries.last_mut() {
+                    Some(entry) => entry.1 = Some(value),
+                    None => return Err(invalid("output recorded without an input".to_string())),
+                },
+                _ => config.push((key.to_string(), value)),
+            }
+        }
+
+        Ok(Self { config, entries })
+    }
+
+    fn get(&self, key: &str) -> Option<&str> {
+        self.config
+            .iter()
+            .find(|x| x.0 == key)
+            .map(|x| x.1.as_str())
+    }
+
+    /// Apply the recorded configuration, returning warnings for the parts which can not be
+    /// reproduced.
+    ///
+    /// A `replay` may be of a session recorded elsewhere, so the compilation directory is not
+    /// reused, and the linker and link arguments, which could run any program, are not applied
+    /// and reported as warnings instead.
+    fn apply<D>(&self, data: &mut ReplData<D>, replay: bool) -> io::Result<Vec<String>> {
+        let invalid = |key: &str, value: &str| {
+            io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!("invalid `{}` in session recording: {}", key, value),
+            )
+        };
+
+        let mut warnings = Vec::new();
+
+        for (key, value) in &self.config {
+            let linking = &mut data.linking;
+            match key.as_str() {
+                "compilation_dir" if replay => (),
+                "compilation_dir" => {
+                    data.with_compilation_dir(value)?;
+                }
+                "panic_strategy" => {
+                    linking.panic_strategy =
+                        parse_panic_strategy(value).ok_or_else(|| invalid(key, value))?
+                }
+                "std_mode" => {
+                    linking.std_mode = parse_std_mode(value).ok_or_else(|| invalid(key, value))?
+                }
+                "auto_extern" => {
+                    linking.auto_extern = value.parse().map_err(|_| invalid(key, value))?
+                }
+                "auto_std_imports" => {
+                    linking.auto_std_imports = value.parse().map_err(|_| invalid(key, value))?
+                }
+                "eval_fn_name" => {
+                    linking::validate_eval_fn_name(value).map_err(|_| invalid(key, value))?;
+                    linking.eval_fn_name = value.clone();
+                }
+                "eval_fn_prefix" => {
+                    linking::validate_eval_fn_prefix(value).map_err(|_| invalid(key, value))?;
+                    linking.eval_fn_prefix = value.clone();
+                }
+                "out_prefix" => {
+                    linking::validate_out_prefix(value).map_err(|_| invalid(key, value))?;
+                    linking.out_prefix = value.clone();
+                }
+                "debuginfo" => {
+                    let level = value.parse().map_err(|_| invalid(key, value))?;
+                    linking::validate_debuginfo(level).map_err(|_| invalid(key, value))?;
+                    linking.debuginfo = level;
+                }
+                "offline" => linking.offline = value.parse().map_err(|_| invalid(key, value))?,
+                "target" => linking.target = Some(value.clone()),
+                "native_lib" => {
+                    let (kind, name) =
+                        parse_native_lib(value).ok_or_else(|| invalid(key, value))?;
+                    linking.native_libs.push((name.to_string(), kind));
+                }
+                "link_arg" if replay => {
+                    warnings.push(format!("link argument `{}` is not replayed", value))
+                }
+                "link_arg" => linking.link_args.push(value.clone()),
+                "linker" if replay => warnings.push(format!("linker `{}` is not replayed", value)),
+                "linker" => {
+                    let linker = PathBuf::from(value);
+                    linking::validate_linker(&linker).map_err(|_| invalid(key, value))?;
+                    linking.linker = Some(linker);
+                }
+                "persistent_module_code" => linking.persistent_module_code = value.clone(),
+                "data_type" => warnings.push(format!("data type `{}` is not replayed", value)),
+                "data_name" => {
+                    linking::validate_data_name(value).map_err(|_| invalid(key, value))?;
+                    linking.data_name = value.clone();
+                }
+                "external_lib" => {
+                    warnings.push(format!("external library `{}` is not linked", value))
+                }
+                _ => (),
+            }
+        }
+
+        Ok(warnings)
+    }
+}
+
+fn panic_strategy_str(strategy: PanicStrategy) -> &'static str {
+    match strategy {
+        PanicStrategy::Catch => "catch",
+        PanicStrategy::Propagate => "propagate",
+        PanicStrategy::Abort => "abort",
+    }
+}
+
+fn parse_panic_strategy(s: &str) -> Option<PanicStrategy> {
+    match s {
+        "catch" => Some(PanicStrategy::Catch),
+        "propagate" => Some(PanicStrategy::Propagate),
+        "abort" => Some(PanicStrategy::Abort),
+        _ => None,
+    }
+}
+
+fn std_mode_str(mode: StdMode) -> &'static str {
+    match mode {
+        StdMode::Std => "std",
+        StdMode::NoStd => "no_std",
+    }
+}
+
+fn parse_std_mode(s: &str) -> Option<StdMode> {
+    match s {
+        "std" => Some(StdMode::Std),
+        "no_std" => Some(StdMode::NoStd),
+        _ => None,
+    }
+}
+
+fn parse_native_lib(s: &str) -> Option<(NativeLibKind, &str)> {
+    let mut split = s.splitn(2, '=');
+    let kind = match split.next()? {
+        "dylib" => NativeLibKind::Dylib,
+        "static" => NativeLibKind::Static,
+        "framework" => NativeLibKind::Framework,
+        _ => return None,
+    };
+    split.next().map(|name| (kind, name))
+}
+
+/// Escape line breaks (and backslashes) so `line` takes up a single line.
+pub(crate) fn escape_line(line: &str) -> String {
+    let mut s = String::with_capacity(line.len());
+    for ch in line.chars() {
+        match ch {
+            '\\' => s.push_str("\\\\"),
+            '\n' => s.push_str("\\n"),
+            '\r' => s.push_str("\\r"),
+            c => s.push(c),
+        }
+    }
+    s
+}
+
+/// Reverses [`escape_line`].
+pub(crate) fn unescape_line(line: &str) -> String {
+    let mut s = String::with_capacity(line.len());
+    let mut chars = line.chars();
+    while let Some(ch) = chars.next() {
+        match (ch, chars.clone().next()) {
+            ('\\', Some('\\')) => {
+                chars.next();
+                s.push('\\');
+            }
+            ('\\', Some('n')) => {
+                chars.next();
+                s.push('\n');
+            }
+            ('\\', Some('r')) => {
+                chars.next();
+                s.push('\r');
+            }
+            (c, _) => s.push(c),
+        }
+    }
+    s
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn escape_test() {
+        let line = "let a = \"\\n\";\nfn b() {\r\n}";
+        let escaped = escape_line(line);
+        assert!(!escaped.contains('\n'));
+        assert_eq!(unescape_line(&escaped), line);
+        assert_eq!(unescape_line("a\\"), "a\\");
+    }
+
+    #[test]
+    fn session_parse_test() {
+        let s = "papyrus-session 1
+rustc rustc 1.50.0
+panic_strategy abort
+std_mode no_std
+auto_extern true
+native_lib static=foo
+persistent_module_code use a;\\nuse b;
+input let a = 1;\\na
+output 1
+input :help
+
+input fn a() {}
+";
+        let session = Session::parse(s).unwrap();
+        assert_eq!(session.get("rustc"), Some("rustc 1.50.0"));
+        assert_eq!(
+            session.entries,
+            vec![
+                ("let a = 1;\na".to_string(), Some("1".to_string())),
+                (":help".to_string(), Some(String::new())),
+                ("fn a() {}".to_string(), None),
+            ]
+        );
+
+        let mut data: ReplData<()> = ReplData::default();
+        let warnings = session.apply(&mut data, true).unwrap();
+        assert!(warnings.is_empty());
+        let linking = data.linking();
+        assert_eq!(linking.panic_strategy, PanicStrategy::Abort);
+        assert_eq!(linking.std_mode, StdMode::NoStd);
+        assert!(linking.auto_extern);
+        assert_eq!(
+            linking.native_libs,
+            vec![("foo".to_string(), NativeLibKind::Static)]
+        );
+        assert_eq!(linking.persistent_module_code, "use a;\nuse b;");
+
+        assert!(Session::parse("input 2+2").is_err());
+        assert!(Session::parse("papyrus-session 1\noutput 4").is_err());
+        assert!(Session::parse("papyrus-session 1\nstd_mode none")
+            .and_then(|s| s.apply(&mut ReplData::<()>::default(), true))
+            .is_err());
+    }
+
+    #[test]
+    fn session_replay_build_config_test() {
+        let s = "papyrus-session 1
+compilation_dir target/testing/session-replay-build-config
+link_arg -Wl,--foo
+linker /usr/bin/foo-ld
+";
+        let session = Session::parse(s).unwrap();
+
+        // a replay does not apply build configuration from the recording
+        let mut data: ReplData<()> = ReplData::default();
+        let dir = data.compilation_dir.clone();
+        let warnings = session.apply(&mut data, true).unwrap();
+        assert_eq!(
+            warnings,
+            vec![
+                "link argument `-Wl,--foo` is not replayed".to_string(),
+                "linker `/usr/bin/foo-ld` is not replayed".to_string(),
+            ]
+        );
+        assert_eq!(data.compilation_dir, dir);
+        assert!(data.linking().link_args.is_empty());
+        assert_eq!(data.linking().linker, None);
+
+        // resuming a session does
+        let session = Session::parse("papyrus-session 1\nlink_arg -Wl,--foo").unwrap();
+        let mut data: ReplData<()> = ReplData::default();
+        assert!(session.apply(&mut data, false).unwrap().is_empty());
+        assert_eq!(data.linking().link_args, vec!["-Wl,--foo".to_string()]);
+    }
+}
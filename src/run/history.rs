@@ -0,0 +1,121 @@
+//! Storage of the input history.
+//!
+//! The terminal REPL loads previous inputs when it starts and appends each input as it is
+//! entered. By default the history is only kept in memory ([`MemoryHistory`]) for the session. It
+//! can be persisted to a file with [`FileHistory`], or any store implementing [`HistoryStore`],
+//! passed through [`RunCallbacks::with_history`].
+//!
+//! [`RunCallbacks::with_history`]: super::RunCallbacks::with_history
+use crate::repl::{escape_line, unescape_line};
+use std::fs;
+use std::io::{self, Write};
+use std::path::PathBuf;
+
+/// A store of input history.
+pub trait HistoryStore {
+    /// Load the history, oldest entry first.
+    fn load(&mut self) -> io::Result<Vec<String>>;
+
+    /// Append an input to the history.
+    fn append(&mut self, line: &str) -> io::Result<()>;
+}
+
+/// History kept in memory, it is lost when the REPL exits.
+#[derive(Default)]
+pub struct MemoryHistory {
+    lines: Vec<String>,
+}
+
+impl HistoryStore for MemoryHistory {
+    fn load(&mut self) -> io::Result<Vec<String>> {
+        Ok(self.lines.clone())
+    }
+
+    fn append(&mut self, line: &str) -> io::Result<()> {
+        self.lines.push(line.to_string());
+        Ok(())
+    }
+}
+
+/// History stored in a file, one input per line.
+///
+/// Inputs spanning multiple lines are escaped so each takes up a single line in the file.
+pub struct FileHistory {
+    path: PathBuf,
+}
+
+impl FileHistory {
+    /// Store history in the file at `path`. The file is created when the first input is
+    /// appended.
+    pub fn new<P: Into<PathBuf>>(path: P) -> Self {
+        Self { path: path.into() }
+    }
+}
+
+impl Default for FileHistory {
+    /// `$HOME/.papyrus/history`
+    fn default() -> Self {
+        Self::new(
+            dirs::home_dir()
+                .unwrap_or_default()
+                .join(".papyrus/history"),
+        )
+    }
+}
+
+impl HistoryStore for FileHistory {
+    fn load(&mut self) -> io::Result<Vec<String>> {
+        match fs::read_to_string(&self.path) {
+            Ok(s) => Ok(s.lines().map(unescape_line).collect()),
+            Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(Vec::new()),
+            Err(e) => Err(e),
+        }
+    }
+
+    fn append(&mut self, line: &str) -> io::Result<()> {
+        if let Some(parent) = self.path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+
+        let mut file = fs::OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&self.path)?;
+
+        writeln!(file, "{}", escape_line(line))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn memory_history_test() {
+        let mut history = MemoryHistory::default();
+        assert_eq!(history.load().unwrap(), Vec::<String>::new());
+
+        history.append("2+2").unwrap();
+        history.append("fn a() {\n}").unwrap();
+        assert_eq!(
+            history.load().unwrap(),
+            vec!["2+2".to_string(), "fn a() {\n}".to_string()]
+        );
+    }
+
+    #[test]
+    fn file_history_test() {
+        let path = "target/testing/file_history_test";
+        fs::remove_file(path).ok();
+
+        let mut history = FileHistory::new(path);
+        assert_eq!(history.load().unwrap(), Vec::<String>::new());
+
+        history.append("2+2").unwrap();
+        history.append("fn a() {\n}").unwrap();
+        assert_eq!(
+            history.load().unwrap(),
+            vec!["2+2".to_string(), "fn a() {\n}".to_string()]
+        );
+    }
+}
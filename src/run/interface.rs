@@ -1,12 +1,14 @@
 use super::map_xterm_err;
 use crate::output::OutputChange;
-use crossbeam_channel::{unbounded, Receiver};
+use crossbeam_channel::{unbounded, Receiver, RecvTimeoutError};
 use crossterm as xterm;
 use std::{
     collections::VecDeque,
     fmt,
     io::{self, stdout, Stdout, Write},
+    time::Duration,
 };
+use unicode_width::{UnicodeWidthChar, UnicodeWidthStr};
 use xterm::{
     cursor::*,
     event::{
@@ -25,6 +27,12 @@ pub struct Screen(pub(super) Receiver<Event>);
 
 impl Screen {
     pub fn new() -> io::Result<Self> {
+        // probe the terminal up front, without a tty (such as on a headless server) this fails
+        // with an opaque error once the first input is read.
+        enable_raw_mode()
+            .and_then(|_| disable_raw_mode())
+            .map_err(no_terminal_err)?;
+
         let (tx, rx) = unbounded();
         std::thread::Builder::new()
             .name("terminal-event-buffer".into())
@@ -66,6 +74,18 @@ impl Screen {
     }
 }
 
+fn no_terminal_err(err: xterm::ErrorKind) -> io::Error {
+    let err = map_xterm_err(err, "terminal does not support raw mode");
+    io::Error::new(
+        err.kind(),
+        format!(
+            "could not open the terminal: {}. Running the REPL requires an interactive terminal, \
+             use `ReplData::eval_value` or the `Repl` read/eval/print API to evaluate without one",
+            err
+        ),
+    )
+}
+
 pub struct Interface<'a> {
     screen: &'a mut Screen,
     stdout: Stdout,
@@ -132,6 +152,16 @@ impl<'a> Interface<'a> {
     }
 
     pub fn read_until(&mut self, events: &[Event]) -> XResult<Event> {
+        self.read_until_idle(events, None)
+    }
+
+    /// As with [`read_until`](Interface::read_until), but calls `idle` once if no event has been
+    /// received for its duration. The timer restarts after each event.
+    pub fn read_until_idle(
+        &mut self,
+        events: &[Event],
+        mut idle: Option<(Duration, &mut dyn FnMut())>,
+    ) -> XResult<Event> {
         const NOMOD: KeyModifiers = KeyModifiers::empty();
         macro_rules! nomod {
             ($code:ident) => {
@@ -146,7 +176,25 @@ impl<'a> Interface<'a> {
             code: xterm::event::KeyCode::Char('c'),
         });
 
-        while let Ok(ev) = self.screen.0.recv() {
+        let mut idled = false;
+
+        loop {
+            let ev = match &mut idle {
+                Some((after, f)) if !idled => match self.screen.0.recv_timeout(*after) {
+                    Ok(ev) => ev,
+                    Err(RecvTimeoutError::Timeout) => {
+                        f();
+                        idled = true;
+                        continue;
+                    }
+                    Err(RecvTimeoutError::Disconnected) => break,
+                },
+                _ => match self.screen.0.recv() {
+                    Ok(ev) => ev,
+                    Err(_) => break,
+                },
+            };
+            idled = false;
             last = ev;
             if events.contains(&ev) {
                 break;
@@ -350,7 +398,8 @@ impl InputBuffer {
 
     /// Starting for column 0, calculates the cursor movement given the current buffer to `ch_pos`.
     /// Returns _(column, row)_.
-    /// Ignores escape sequences.
+    /// Ignores escape sequences. Wide characters (such as CJK) take up two columns, and wrap to
+    /// the next row if they do not fit.
     pub fn cursor_delta(&self, ch_pos: usize, width: usize) -> (usize, usize) {
         let mut rows = 0;
         let mut columns = 0;
@@ -369,7 +418,14 @@ impl InputBuffer {
                     let r = columns % TAB_WIDTH;
                     columns += TAB_WIDTH - r;
                 }
-                _ => columns += 1,
+                _ => {
+                    let w = ch.width().unwrap_or(0);
+                    if columns + w > width {
+                        rows += 1;
+                        columns = 0;
+                    }
+                    columns += w;
+                }
             }
 
             if columns >= width {
@@ -432,6 +488,12 @@ impl CompletionWriter {
         self.completion_idx = 0;
     }
 
+    /// Add completions to the end, keeping the current completion index.
+    #[cfg(feature = "racer-completion")]
+    pub fn extend_completions<I: Iterator<Item = CItem>>(&mut self, completions: I) {
+        self.completions.extend(completions);
+    }
+
     pub fn overwrite_completion(&mut self, interface: &mut Interface) -> XResult<()> {
         let completion = self.completions.get(self.completion_idx);
 
@@ -473,12 +535,26 @@ fn overwrite_text<T: fmt::Display + Clone>(
     Ok(())
 }
 
+/// Drops `CurrentLine` changes which are directly followed by another `CurrentLine` change, as
+/// the latter overwrites the line.
+pub fn coalesce_output_chgs<I: IntoIterator<Item = OutputChange>>(changes: I) -> Vec<OutputChange> {
+    let mut chgs = Vec::new();
+    for chg in changes {
+        if let (Some(OutputChange::CurrentLine(_)), OutputChange::CurrentLine(_)) =
+            (chgs.last(), &chg)
+        {
+            chgs.pop();
+        }
+        chgs.push(chg);
+    }
+    chgs
+}
+
 /// Returns the number of lines the written text accounts for
 pub fn write_output_chg(current_lines_covered: u16, change: OutputChange) -> io::Result<u16> {
-    use OutputChange::*;
     let mut stdout = stdout();
     match change {
-        CurrentLine(line) => {
+        OutputChange::CurrentLine(line) => {
             for _ in 1..current_lines_covered {
                 queue!(stdout, Clear(ClearType::CurrentLine), MoveUp(1))
                     .map_err(|e| map_xterm_err(e, "Clear a line"))?;
@@ -486,9 +562,17 @@ pub fn write_output_chg(current_lines_covered: u16, change: OutputChange) -> io:
             let mut stdout = erase_current_line(stdout)?;
             queue!(stdout, Print(&line)).map_err(|e| map_xterm_err(e, "printing a line"))?;
             stdout.flush()?;
-            Ok(lines_covered(0, term_width_nofail(), line.chars().count()) as u16)
+            // escape sequences, such as colours or the URL of a hyperlink, take up no columns
+            let width = crate::output::strip_ansi(&line).width();
+            Ok(lines_covered(0, term_width_nofail(), width) as u16)
+        }
+        OutputChange::NewLine => writeln!(&mut stdout).map(|_| 1),
+        OutputChange::Clear => {
+            queue!(stdout, Clear(ClearType::All), MoveTo(0, 0))
+                .map_err(|e| map_xterm_err(e, "clearing the terminal"))?;
+            stdout.flush()?;
+            Ok(0)
         }
-        NewLine => writeln!(&mut stdout).map(|_| 1),
     }
 }
 
@@ -604,6 +688,14 @@ mod tests {
         assert_eq!(inputbuf.cursor_delta(inputbuf.pos, 2), (1, 1));
         assert_eq!(inputbuf.cursor_delta(inputbuf.len(), 3), (0, 1));
         assert_eq!(inputbuf.cursor_delta(inputbuf.pos, 4), (3, 0));
+
+        // wide characters
+        inputbuf.clear();
+        inputbuf.insert_str("a你好");
+        assert_eq!(inputbuf.cursor_delta(inputbuf.len(), 10), (5, 0));
+        assert_eq!(inputbuf.cursor_delta(2, 10), (3, 0));
+        assert_eq!(inputbuf.cursor_delta(inputbuf.len(), 4), (2, 1)); // 好 wraps
+        assert_eq!(inputbuf.cursor_delta(inputbuf.len(), 5), (0, 1));
     }
 
     #[test]
@@ -1,6 +1,5 @@
 #[cfg(feature = "racer-completion")]
 use crate::complete::code::{CodeCache, CodeCompleter};
-use crate::complete::{cmdr::TreeCompleter, modules::ModulesCompleter};
 use crate::prelude::*;
 use crossterm as xterm;
 use crossterm::event::Event;
@@ -8,24 +7,121 @@ use kserd::{fmt::FormattingConfig, Kserd};
 use repl::{EvalResult, Evaluate, Print, Read, ReadResult};
 use std::io::{self, prelude::*};
 use std::sync::{Arc, Mutex};
+use std::time::Duration;
 
+pub mod history;
 mod interface;
 #[cfg(test)]
 mod tests;
 
+use history::{HistoryStore, MemoryHistory};
 use interface::{CItem, Interface, Screen};
 
+/// The number of history entries kept for the interface.
+const HISTORY_LEN: usize = 100;
+
 #[cfg(feature = "racer-completion")]
 const CODE_COMPLETIONS: Option<usize> = Some(10);
 
+/// The longest a new completion waits on code completions before displaying what is available.
+#[cfg(feature = "racer-completion")]
+const CODE_COMPLETIONS_WAIT: std::time::Duration = std::time::Duration::from_millis(100);
+
+#[cfg(feature = "racer-completion")]
+type CodeRequest = (u64, Arc<CodeCompleter>, String, usize);
+
+/// Code completion runs on a background thread so that slow completions do not stall input.
+///
+/// Each request is tagged with an increasing id. The worker only services the newest pending
+/// request and results from superseded requests are discarded.
 #[cfg(feature = "racer-completion")]
-struct CacheWrapper(CodeCache);
+struct CodeCompletion {
+    id: u64,
+    tx: crossbeam_channel::Sender<CodeRequest>,
+    rx: crossbeam_channel::Receiver<(u64, Vec<CItem>)>,
+}
 #[cfg(not(feature = "racer-completion"))]
-struct CacheWrapper;
+struct CodeCompletion;
+
+#[cfg(feature = "racer-completion")]
+impl CodeCompletion {
+    fn spawn() -> io::Result<Self> {
+        let (tx, req_rx) = crossbeam_channel::unbounded::<CodeRequest>();
+        let (res_tx, rx) = crossbeam_channel::unbounded();
+        let (warn_tx, warn_rx) = crossbeam_channel::bounded(1);
+
+        std::thread::Builder::new()
+            .name("code-completion".into())
+            .spawn(move || {
+                // the racer cache is not `Send`, so it is built and lives on this thread
+                let cache = match CodeCache::new() {
+                    Ok(c) => c,
+                    Err((c, msg)) => {
+                        warn_tx.send(msg).ok();
+                        c
+                    }
+                };
+                drop(warn_tx);
+
+                while let Ok(req) = req_rx.recv() {
+                    // only complete the newest request, older ones are stale
+                    let (id, cmpltr, injection, chpos) = req_rx.try_iter().last().unwrap_or(req);
+                    let completions = complete_code(&cmpltr, &cache, &injection, chpos).collect();
+                    if res_tx.send((id, completions)).is_err() {
+                        break;
+                    }
+                }
+            })?;
+
+        if let Ok(msg) = warn_rx.recv() {
+            println!("warning: could not find rust src code: {}", msg);
+        }
+
+        Ok(CodeCompletion { id: 0, tx, rx })
+    }
+
+    /// A completion which never runs, used when completion is disabled.
+    fn disabled() -> Self {
+        let (tx, _) = crossbeam_channel::unbounded();
+        let (_, rx) = crossbeam_channel::unbounded();
+        CodeCompletion { id: 0, tx, rx }
+    }
+
+    /// Request completions, superseding any previous requests.
+    fn request(&mut self, cmpltr: &Arc<CodeCompleter>, injection: String, chpos: usize) {
+        self.id += 1;
+        self.tx
+            .send((self.id, Arc::clone(cmpltr), injection, chpos))
+            .ok();
+    }
+
+    /// Discard the results of any outstanding requests.
+    fn cancel(&mut self) {
+        self.id += 1;
+    }
+
+    /// The completions of the latest request, waiting up to `timeout` for them to arrive.
+    fn latest(&self, timeout: std::time::Duration) -> Option<Vec<CItem>> {
+        let deadline = std::time::Instant::now() + timeout;
+        loop {
+            let remaining = deadline.saturating_duration_since(std::time::Instant::now());
+            match self.rx.recv_timeout(remaining) {
+                Ok((id, completions)) if id == self.id => break Some(completions),
+                Ok(_) => (), // stale, discard
+                Err(_) => break None,
+            }
+        }
+    }
+}
 
 /// Get the terminal width, if possible.
+///
+/// Falls back to the `COLUMNS` environment variable if the terminal can not be queried.
 pub fn terminal_width() -> Option<usize> {
-    crossterm::terminal::size().map(|x| x.0 as usize).ok()
+    crossterm::terminal::size()
+        .map(|x| x.0 as usize)
+        .ok()
+        .or_else(|| std::env::var("COLUMNS").ok()?.parse().ok())
 }
 
 /// Creates a `FormattingConfig` that has a width based on the terminal width.
@@ -43,7 +139,9 @@ pub fn fmt_based_on_terminal_width<D>(repl: &Repl<Print, D>) -> FormattingConfig
             } else {
                 std::cmp::max((width * 4) / 5, 120)
             }
-            .saturating_sub(repl.prompt(false).chars().count());
+            .saturating_sub(unicode_width::UnicodeWidthStr::width(
+                repl.prompt(false).as_str(),
+            ));
             fmt.width_limit = Some(width);
             fmt
         })
@@ -75,8 +173,14 @@ pub struct RunCallbacks<'a, D, T, U, V> {
     fmtrfn: Option<T>,
     resultfn: Option<U>,
     exitfn: Option<V>,
+    history: Option<Box<dyn HistoryStore>>,
+    coalesce_output: bool,
+    wrap_results: bool,
+    idlefn: Option<(Duration, IdleFn<'a, D>)>,
 }
 
+type IdleFn<'a, D> = Box<dyn FnMut(&mut ReplData<D>) + 'a>;
+
 impl<'a, D>
     RunCallbacks<
         'a,
@@ -93,6 +197,10 @@ impl<'a, D>
             fmtrfn: None,
             resultfn: None,
             exitfn: None,
+            history: None,
+            coalesce_output: true,
+            wrap_results: true,
+            idlefn: None,
         }
     }
 
@@ -109,6 +217,10 @@ impl<'a, D>
             fmtrfn: None,
             resultfn: None,
             exitfn: None,
+            history: None,
+            coalesce_output: true,
+            wrap_results: true,
+            idlefn: None,
         }
     }
 }
@@ -123,6 +235,10 @@ impl<'a, D, T, U, V> RunCallbacks<'a, D, T, U, V> {
             data,
             resultfn,
             exitfn,
+            history,
+            coalesce_output,
+            wrap_results,
+            idlefn,
             ..
         } = self;
         RunCallbacks {
@@ -130,6 +246,10 @@ impl<'a, D, T, U, V> RunCallbacks<'a, D, T, U, V> {
             fmtrfn: Some(f),
             resultfn,
             exitfn,
+            history,
+            coalesce_output,
+            wrap_results,
+            idlefn,
         }
     }
 
@@ -145,6 +265,10 @@ impl<'a, D, T, U, V> RunCallbacks<'a, D, T, U, V> {
             data,
             fmtrfn,
             exitfn,
+            history,
+            coalesce_output,
+            wrap_results,
+            idlefn,
             ..
         } = self;
         RunCallbacks {
@@ -152,6 +276,10 @@ impl<'a, D, T, U, V> RunCallbacks<'a, D, T, U, V> {
             fmtrfn,
             resultfn: Some(f),
             exitfn,
+            history,
+            coalesce_output,
+            wrap_results,
+            idlefn,
         }
     }
 
@@ -166,6 +294,10 @@ impl<'a, D, T, U, V> RunCallbacks<'a, D, T, U, V> {
             data,
             fmtrfn,
             resultfn,
+            history,
+            coalesce_output,
+            wrap_results,
+            idlefn,
             ..
         } = self;
         RunCallbacks {
@@ -173,8 +305,56 @@ impl<'a, D, T, U, V> RunCallbacks<'a, D, T, U, V> {
             fmtrfn,
             resultfn,
             exitfn: Some(f),
+            history,
+            coalesce_output,
+            wrap_results,
+            idlefn,
         }
     }
+
+    /// Specify where input history is loaded from and appended to.
+    ///
+    /// The default is a [`MemoryHistory`], which is not persisted. Use a
+    /// [`FileHistory`](history::FileHistory) to keep the history between sessions, such as
+    /// `FileHistory::default()` at `$HOME/.papyrus/history`.
+    pub fn with_history<H: HistoryStore + 'static>(mut self, history: H) -> Self {
+        self.history = Some(Box::new(history));
+        self
+    }
+
+    /// Only draw the last of consecutive updates to the current line which arrive together. The
+    /// default is `true`.
+    ///
+    /// Output which rewrites a line many times, such as compilation progress, is drawn once per
+    /// batch rather than once per update. The final output is the same either way.
+    pub fn with_output_coalescing(mut self, coalesce: bool) -> Self {
+        self.coalesce_output = coalesce;
+        self
+    }
+
+    /// Wrap results to the terminal width, indenting continuation lines under the `[out#]:`
+    /// label. The default is `true`.
+    ///
+    /// The terminal width is queried each time a result is printed, so resizing the terminal is
+    /// followed. A width set with [`ReplData::with_result_width`] takes precedence. Set to `false`
+    /// to output results raw.
+    pub fn with_result_wrapping(mut self, wrap: bool) -> Self {
+        self.wrap_results = wrap;
+        self
+    }
+
+    /// Specify code to be run when the REPL has been idle for `after`.
+    ///
+    /// The REPL is idle when waiting on input and no input event has been received. The closure
+    /// is run once per idle period, the timer is reset by any input event or evaluation. This can
+    /// be used to release resources in long running sessions, such as unloading libraries.
+    pub fn with_idlefn<F>(mut self, after: Duration, f: F) -> Self
+    where
+        F: FnMut(&mut ReplData<D>) + 'a,
+    {
+        self.idlefn = Some((after, Box::new(f)));
+        self
+    }
 }
 
 /// Available with the `runnable` feature and when the REPL is in the `Read` state.
@@ -188,6 +368,30 @@ impl<D> Repl<Read, D> {
     {
         run(self, run_callbacks, Screen::new).map_err(|e| map_xterm_err(e, "running REPL failed"))
     }
+
+    /// Run the repl inside the terminal, reading input events from `events` rather than the
+    /// terminal's event stream. Returns the output of the REPL.
+    ///
+    /// This lets an embedder drive the interactive loop from any source that can produce
+    /// [`crossterm` events](crossterm::event::Event), such as a socket bridge or a scripted test.
+    /// Only the _input_ side is replaced, the interface is still drawn to `stdout` and uses the
+    /// terminal for cursor positioning.
+    ///
+    /// The loop finishes when an exit signal is evaluated or the sending side of `events` is
+    /// dropped.
+    pub fn run_with_events<T, U, V>(
+        self,
+        run_callbacks: RunCallbacks<D, T, U, V>,
+        events: crossbeam_channel::Receiver<Event>,
+    ) -> io::Result<String>
+    where
+        T: FnMut(&Repl<Print, D>) -> kserd::fmt::FormattingConfig,
+        U: FnMut(usize, kserd::Kserd<'static>, &Repl<Read, D>),
+        V: FnOnce(&mut ReplData<D>, &mut D),
+    {
+        run(self, run_callbacks, || Ok(Screen(events)))
+            .map_err(|e| map_xterm_err(e, "running REPL failed"))
+    }
 }
 
 fn run<D, FmtrFn, ResultFn, ExitFn>(
@@ -222,24 +426,35 @@ where
     }));
 
     let mut screen = screen_fn()?;
+
+    if let Some(banner) = read.data.banner() {
+        println!("{}", banner);
+    }
+
     let mut inputbuf = interface::InputBuffer::new();
     #[cfg(feature = "racer-completion")]
-    let cache = {
-        let cache = match CodeCache::new() {
-            Ok(c) => c,
-            Err((c, msg)) => {
-                println!("warning: could not find rust src code: {}", msg);
-                c
-            }
-        };
-        CacheWrapper(cache)
+    let mut code = if read.data.completion() {
+        CodeCompletion::spawn()?
+    } else {
+        CodeCompletion::disabled()
     };
     #[cfg(not(feature = "racer-completion"))]
-    let cache = CacheWrapper;
+    let mut code = CodeCompletion;
     let mut reevaluate: Option<String> = None;
 
+    let mut history_store = runcb
+        .history
+        .take()
+        .unwrap_or_else(|| Box::new(MemoryHistory::default()));
+    let history = history_store.load().unwrap_or_else(|e| {
+        warn!("failed to load history: {}", e);
+        Vec::new()
+    });
     // must seed the history size, this is maintained as constant.
-    let mut history = std::collections::VecDeque::from(vec![String::default(); 100]); // 100 history
+    let mut history = std::iter::repeat(String::default())
+        .take(HISTORY_LEN.saturating_sub(history.len()))
+        .chain(history.into_iter().rev().take(HISTORY_LEN).rev())
+        .collect::<std::collections::VecDeque<_>>();
 
     let output = loop {
         let mut interface = screen.begin_interface_input(&mut inputbuf, &mut history)?;
@@ -255,7 +470,13 @@ where
 
         if let Some(val) = reevaluate.take() {
             read.line_input(&val);
-        } else if do_read(&mut read, &mut interface, &cache)? {
+        } else if do_read(
+            &mut read,
+            &mut interface,
+            &mut code,
+            &mut *history_store,
+            &mut runcb.idlefn,
+        )? {
             break read.output().to_owned();
         }
 
@@ -336,10 +557,12 @@ fn construct_crash_report(
 fn do_read<D>(
     repl: &mut Repl<Read, D>,
     interface: &mut Interface,
-    cache: &CacheWrapper,
+    code: &mut CodeCompletion,
+    history: &mut dyn HistoryStore,
+    idlefn: &mut Option<(Duration, IdleFn<'_, D>)>,
 ) -> xterm::Result<bool> {
     #[cfg(not(feature = "racer-completion"))]
-    let _ = cache;
+    let _ = code;
 
     use crossterm::event::{Event::*, KeyCode::*, KeyEvent, KeyModifiers};
     const ENTER: Event = Key(KeyEvent {
@@ -366,11 +589,8 @@ fn do_read<D>(
 
     let mut completion_writer = interface::CompletionWriter::new();
     let mut verbatim_mode = false;
-    let rdata = &repl.data;
-    let treecmpltr = TreeCompleter::build(&rdata.cmdtree);
-    let modscmpltr = ModulesCompleter::build(&rdata.cmdtree, rdata.mods_map());
     #[cfg(feature = "racer-completion")]
-    let codecmpltr = CodeCompleter::build(rdata);
+    let mut codecmpltr = None;
     let prompt = repl.prompt(true);
     let verbatim_prompt = format!("{}\u{1b}[44m ", &prompt[..prompt.len() - 1]);
 
@@ -382,21 +602,38 @@ fn do_read<D>(
         }
         interface.flush_buffer()?;
 
-        let ev = interface.read_until(STOPEVENTS)?;
+        let ev = match idlefn {
+            Some((after, f)) => {
+                interface.read_until_idle(STOPEVENTS, Some((*after, &mut || f(&mut repl.data))))?
+            }
+            None => interface.read_until(STOPEVENTS)?,
+        };
 
         match (ev, verbatim_mode) {
             (ENTER, false) | (STOP_VERBATIM_MODE, true) => {
                 let line = interface.buffer();
                 repl.line_input(&line);
+                if !line.trim().is_empty() {
+                    if let Err(e) = history.append(&line) {
+                        warn!("failed to append to history: {}", e);
+                    }
+                }
                 interface.add_history(line);
                 interface.mv_bufpos_end();
                 interface.writeln("");
                 interface.flush_buffer()?;
                 break Ok(false);
             }
-            (TAB, false) => {
+            (TAB, false) if repl.data.completion() => {
                 let line = interface.buffer();
                 if completion_writer.is_same_input(&line) {
+                    // code completions might have arrived since the last tab
+                    #[cfg(feature = "racer-completion")]
+                    {
+                        if let Some(c) = code.latest(std::time::Duration::default()) {
+                            completion_writer.extend_completions(c.into_iter());
+                        }
+                    }
                     completion_writer.next_completion();
                 } else {
                     let f = |start| {
@@ -405,29 +642,38 @@ fn do_read<D>(
                             .saturating_sub(line[start..].chars().count())
                     };
 
-                    let tree_chpos = f(TreeCompleter::word_break(&line));
-                    let mods_chpos = f(ModulesCompleter::word_break(&line));
                     #[cfg(feature = "racer-completion")]
                     let code_chpos = f(CodeCompleter::word_break(&line));
 
                     let completions = if line.starts_with(crate::CMD_PREFIX) {
-                        Box::new(std::iter::empty()) as Box<dyn Iterator<Item = CItem>>
+                        #[cfg(feature = "racer-completion")]
+                        code.cancel();
+                        Vec::new()
                     } else {
                         #[cfg(feature = "racer-completion")]
                         let c = {
+                            let codecmpltr = codecmpltr
+                                .get_or_insert_with(|| Arc::new(CodeCompleter::build(&repl.data)));
                             let injection = format!("{}\n{}", repl.input_buffer(), line);
-                            complete_code(&codecmpltr, &cache.0, &injection, code_chpos)
+                            code.request(codecmpltr, injection, code_chpos);
+                            code.latest(CODE_COMPLETIONS_WAIT).unwrap_or_default()
                         };
 
                         #[cfg(not(feature = "racer-completion"))]
-                        let c = std::iter::empty();
+                        let c = Vec::new();
 
-                        Box::new(c) as Box<dyn Iterator<Item = CItem>>
+                        c
                     };
 
-                    let completions = completions
-                        .chain(complete_cmdtree(&treecmpltr, &line, tree_chpos))
-                        .chain(complete_mods(&modscmpltr, &line, mods_chpos));
+                    let completions = completions.into_iter().chain(
+                        repl.data
+                            .complete(&line, line.len())
+                            .into_iter()
+                            .map(|x| CItem {
+                                input_chpos: f(x.start),
+                                matchstr: x.replacement,
+                            }),
+                    );
 
                     completion_writer.new_completions(completions);
                 }
@@ -440,7 +686,7 @@ fn do_read<D>(
                 interface.writeln("");
                 interface.flush_buffer()?;
             }
-            (TAB, true) => {
+            (TAB, _) => {
                 interface.write("\t");
                 interface.flush_buffer()?;
             }
@@ -449,28 +695,6 @@ fn do_read<D>(
     }
 }
 
-fn complete_cmdtree<'a>(
-    tree: &'a TreeCompleter,
-    line: &'a str,
-    chpos: usize,
-) -> impl Iterator<Item = CItem> + 'a {
-    tree.complete(line).map(move |x| CItem {
-        matchstr: x.0.to_owned(),
-        input_chpos: chpos,
-    })
-}
-
-fn complete_mods<'a>(
-    mods: &'a ModulesCompleter,
-    line: &'a str,
-    chpos: usize,
-) -> impl Iterator<Item = CItem> + 'a {
-    mods.complete(line).map(move |x| CItem {
-        matchstr: x,
-        input_chpos: chpos,
-    })
-}
-
 #[cfg(feature = "racer-completion")]
 fn complete_code(
     code: &CodeCompleter,
@@ -495,11 +719,19 @@ where
     ResultFn: FnMut(usize, kserd::Kserd<'static>, &Repl<Read, D>),
 {
     let rx = repl.output_listen();
+    let coalesce = runcb.coalesce_output;
 
     let jh = std::thread::spawn(move || {
         let mut covered_lines = 0;
         for chg in rx.iter() {
-            covered_lines = interface::write_output_chg(covered_lines, chg).unwrap_or(0);
+            let chgs = if coalesce {
+                interface::coalesce_output_chgs(std::iter::once(chg).chain(rx.try_iter()))
+            } else {
+                vec![chg]
+            };
+            for chg in chgs {
+                covered_lines = interface::write_output_chg(covered_lines, chg).unwrap_or(0);
+            }
         }
     });
 
@@ -516,13 +748,21 @@ where
         .unwrap_or_default();
 
     let (mut read, signal) = {
-        let (repl, signal) = (r.repl, r.signal);
-        let (repl, result) = repl.print_with_formatting(fmt);
+        let (mut repl, signal) = (r.repl, r.signal);
+        // a configured width is kept, otherwise the terminal width is used for this result only
+        let follow_terminal = runcb.wrap_results && repl.data.result_width().is_none();
+        if follow_terminal {
+            repl.data.with_result_width(terminal_width());
+        }
+        let (mut repl, result) = repl.print_with_formatting(fmt);
         if let Some((idx, kserd)) = result {
             if let Some(f) = &mut runcb.resultfn {
                 f(idx, kserd, &repl);
             }
         }
+        if follow_terminal {
+            repl.data.with_result_width(None);
+        }
         (repl, signal)
     };
 
@@ -1,4 +1,4 @@
-use super::{interface::InputBuffer, run, Screen};
+use super::{interface::InputBuffer, Screen};
 use crate::run::RunCallbacks;
 use crossbeam_channel::{unbounded, Receiver, Sender};
 use crossterm as xterm;
@@ -6,7 +6,7 @@ use std::{
     io::{self, Write},
     thread::JoinHandle,
 };
-use xterm::{event::*, Result};
+use xterm::event::*;
 
 macro_rules! assert_eq {
     ($lhs:expr, $rhs:expr) => {{
@@ -93,6 +93,68 @@ fn verbatim_mode_tab_input() {
     assert_eq!(result, expected);
 }
 
+#[test]
+#[cfg(feature = "test-runnable")]
+fn completion_disabled_tab_input() {
+    colour_off();
+    let (tx, rx) = unbounded();
+    let tx = Tx(tx);
+    let jh = std::thread::spawn(|| {
+        let mut repl = crate::repl::Repl::<_, ()>::default();
+        repl.data.with_completion(false);
+        repl.run_with_events(RunCallbacks::new(&mut ()), rx)
+    });
+
+    tx.text(":mo").tab();
+    slp();
+    assert_eq!(col(), 24, "tab should be inserted rather than completing");
+
+    tx.backspace(4).enter();
+
+    let result = finish_repl(jh, tx);
+    println!("{}", result);
+    let expected = "[lib] papyrus=> 
+[lib] papyrus=> :exit
+[lib] papyrus=> ";
+    assert_eq!(result, expected);
+}
+
+#[test]
+#[cfg(feature = "test-runnable")]
+fn idle_callback() {
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::sync::Arc;
+
+    let (tx, rx) = unbounded();
+    let tx = Tx(tx);
+    let idles = Arc::new(AtomicUsize::new(0));
+    let count = Arc::clone(&idles);
+    let jh = std::thread::spawn(move || {
+        let repl = crate::repl::Repl::<_, ()>::default();
+        let callbacks = RunCallbacks::new(&mut ()).with_idlefn(
+            std::time::Duration::from_millis(100),
+            move |_| {
+                count.fetch_add(1, Ordering::SeqCst);
+            },
+        );
+        repl.run_with_events(callbacks, rx)
+    });
+
+    slp();
+    assert_eq!(
+        idles.load(Ordering::SeqCst),
+        1,
+        "fires once per idle period"
+    );
+
+    tx.text("a");
+    slp();
+    assert_eq!(idles.load(Ordering::SeqCst), 2, "input resets the timer");
+
+    tx.backspace(1);
+    finish_repl(jh, tx);
+}
+
 #[test]
 #[cfg(feature = "test-runnable")]
 fn backspace_past_start() {
@@ -191,6 +253,40 @@ fn test_input_inside_line() {
     assert_eq!(result, expected);
 }
 
+#[test]
+fn coalesce_output_chgs_test() {
+    use super::interface::coalesce_output_chgs;
+    use crate::output::OutputChange::*;
+
+    let line = |s: &str| CurrentLine(s.to_string());
+
+    assert_eq!(
+        coalesce_output_chgs(vec![
+            line("Compiling"),
+            line("Compiling a"),
+            line("Compiling b"),
+            NewLine,
+            line("[out0]"),
+            line("[out0]: 4"),
+            NewLine,
+            Clear,
+            line("a"),
+        ]),
+        vec![
+            line("Compiling b"),
+            NewLine,
+            line("[out0]: 4"),
+            NewLine,
+            Clear,
+            line("a")
+        ]
+    );
+    assert_eq!(
+        coalesce_output_chgs(vec![NewLine, NewLine]),
+        vec![NewLine, NewLine]
+    );
+}
+
 // INTERFACE INTEGRATION TESTS ------------------------------------------------
 #[test]
 #[cfg(feature = "test-runnable")]
@@ -360,15 +456,14 @@ fn colour_off() {
     colored::control::set_override(false);
 }
 
-fn fire_off_run(rx: Receiver<Event>) -> JoinHandle<Result<String>> {
+fn fire_off_run(rx: Receiver<Event>) -> JoinHandle<io::Result<String>> {
     std::thread::spawn(|| {
-        let screen = Screen(rx);
         let repl = crate::repl::Repl::<_, ()>::default();
-        run(repl, RunCallbacks::new(&mut ()), || Ok(screen))
+        repl.run_with_events(RunCallbacks::new(&mut ()), rx)
     })
 }
 
-fn finish_repl(jh: JoinHandle<Result<String>>, tx: Tx) -> String {
+fn finish_repl(jh: JoinHandle<io::Result<String>>, tx: Tx) -> String {
     drop(tx);
     jh.join().unwrap().unwrap()
 }
@@ -0,0 +1,129 @@
+//! Serve a REPL session over a TCP socket.
+//!
+//! Each line received is evaluated as a complete input against the single REPL session, and a
+//! line of JSON is written back. Clients are handled one at a time, so only a single evaluation
+//! ever runs. A client disconnecting does not end the session, the next connection continues
+//! with the same state.
+//!
+//! The reply is one of:
+//! ```text
+//! {"out":0,"value":"4"}
+//! {"value":""}
+//! {"error":"expected `;`"}
+//! ```
+//! `out` is only present if the input produced a result, `value` is empty if no statements were
+//! evaluated (such as defining an item).
+use crate::prelude::*;
+use std::io::{self, BufRead, BufReader, Write};
+use std::net::{TcpListener, TcpStream, ToSocketAddrs};
+
+/// Available with the `server` feature and when the REPL is in the `Read` state.
+impl<D> Repl<repl::Read, D> {
+    /// Serve the REPL session on `addr`, accepting newline delimited input and replying with JSON.
+    ///
+    /// This blocks and only returns if binding to the address fails or accepting a connection
+    /// errors. See the [`serve` module](crate::serve) for the reply format.
+    pub fn serve<A: ToSocketAddrs>(mut self, addr: A, app_data: &mut D) -> io::Result<()> {
+        let listener = TcpListener::bind(addr)?;
+
+        for stream in listener.incoming() {
+            if let Err(e) = handle_client(&mut self.data, stream?, app_data) {
+                // client errors are not fatal to the session
+                warn!("client connection ended with an error: {}", e);
+            }
+        }
+
+        Ok(())
+    }
+}
+
+fn handle_client<D>(data: &mut ReplData<D>, stream: TcpStream, app_data: &mut D) -> io::Result<()> {
+    let mut writer = stream.try_clone()?;
+    let reader = BufReader::new(stream);
+
+    for line in reader.lines() {
+        let line = line?;
+        if line.trim().is_empty() {
+            continue;
+        }
+
+        let outs = data.current_src().out_count();
+        let result = data.eval_value(&line, app_data);
+        // only a kept result has an `out#`
+        let out = Some(data.current_src().out_count())
+            .filter(|&n| n > outs)
+            .map(|n| n - 1);
+        let reply = response(result, out);
+
+        writer.write_all(reply.as_bytes())?;
+        writer.write_all(b"\n")?;
+        writer.flush()?;
+    }
+
+    Ok(()) // client disconnected
+}
+
+fn response(result: Result<String, String>, out: Option<usize>) -> String {
+    let mut s = String::from("{");
+    match result {
+        Ok(value) if value.is_empty() => {
+            s.push_str("\"value\":\"\"");
+        }
+        Ok(value) => {
+            if let Some(n) = out {
+                s.push_str("\"out\":");
+                s.push_str(&n.to_string());
+                s.push(',');
+            }
+            s.push_str("\"value\":");
+            push_json_str(&value, &mut s);
+        }
+        Err(e) => {
+            s.push_str("\"error\":");
+            push_json_str(&e, &mut s);
+        }
+    }
+    s.push('}');
+    s
+}
+
+fn push_json_str(value: &str, buf: &mut String) {
+    buf.push('"');
+    for ch in value.chars() {
+        match ch {
+            '"' => buf.push_str("\\\""),
+            '\\' => buf.push_str("\\\\"),
+            '\n' => buf.push_str("\\n"),
+            '\r' => buf.push_str("\\r"),
+            '\t' => buf.push_str("\\t"),
+            c if (c as u32) < 0x20 => buf.push_str(&format!("\\u{:04x}", c as u32)),
+            c => buf.push(c),
+        }
+    }
+    buf.push('"');
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn response_test() {
+        assert_eq!(
+            response(Ok("4".to_string()), Some(0)),
+            r#"{"out":0,"value":"4"}"#
+        );
+        assert_eq!(response(Ok(String::new()), Some(0)), r#"{"value":""}"#);
+        assert_eq!(
+            response(Err("expected `;`".to_string()), None),
+            r#"{"error":"expected `;`"}"#
+        );
+    }
+
+    #[test]
+    fn json_str_test() {
+        let mut s = String::new();
+        push_json_str("a \"quoted\"\nline\\\t\u{1}", &mut s);
+        assert_eq!(s, r#""a \"quoted\"\nline\\\t\u0001""#);
+    }
+}
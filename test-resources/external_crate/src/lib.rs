@@ -4,6 +4,8 @@ pub struct MyStruct {
 }
 
 impl MyStruct {
+    pub const ORIGIN: MyStruct = MyStruct { a: 0, b: 0 };
+
     pub fn new(a: i32, b: i32) -> Self {
         MyStruct { a, b }
     }
@@ -23,6 +23,23 @@ fn chg_compile_dir<T, U>(mut repl: Repl<T, U>) -> Repl<T, U> {
     repl
 }
 
+/// Reads, evaluates, and prints `input`, returning the repl ready for the next input along with
+/// the printed result.
+fn eval_print(
+    mut repl: Repl<repl::Read, ()>,
+    input: &str,
+) -> (Repl<repl::Read, ()>, Option<(usize, Kserd<'static>)>) {
+    repl.line_input(input);
+    match repl.read() {
+        ReadResult::Read(_) => panic!("should be at Eval state!"),
+        ReadResult::Eval(repl) => {
+            let repl::EvalResult { repl, signal } = repl.eval(&mut ());
+            assert_eq!(signal, Signal::None);
+            repl.print()
+        }
+    }
+}
+
 #[test]
 #[cfg(feature = "test-runnable")]
 fn multiline_literal_inputs() {
@@ -75,3 +92,1178 @@ a
         }
     };
 }
+
+#[test]
+#[cfg(feature = "test-runnable")]
+fn reference_results() {
+    let repl = chg_compile_dir(repl!());
+
+    // &str
+    let (repl, kserd) = eval_print(repl, "let s = String::from(\"Hello\");\n&s[..]\n");
+    assert_eq!(kserd, Some((0, Kserd::new_str("Hello"))));
+
+    // &[i32]
+    let (repl, kserd) = eval_print(repl, "let v = vec![1, 2, 3];\n&v[1..]\n");
+    assert_eq!(kserd, Some((1, vec![2, 3].into_kserd().unwrap())));
+
+    // leading borrows which are not the whole expression
+    let (repl, kserd) = eval_print(repl, "let a = 1;\nlet b = 1;\n&a == &b\n");
+    assert_eq!(kserd, Some((2, Kserd::new_bool(true))));
+
+    // &Struct which is not Clone falls back to Debug
+    let (repl, _) = eval_print(repl, "#[derive(Debug)]\nstruct Point { x: i32 }\n");
+    let (_, kserd) = eval_print(repl, "let p = Point { x: 5 };\n&p\n");
+    assert_eq!(
+        kserd,
+        Some((3, Kserd::new_string("Point {\n    x: 5,\n}".to_string())))
+    );
+}
+
+#[test]
+#[cfg(feature = "test-runnable")]
+fn eval_value_test() {
+    let mut repl = chg_compile_dir(repl!());
+    let output = repl.output().to_string();
+
+    let r = repl
+        .data
+        .eval_value("let a = vec![1, 2];\na.len()", &mut ());
+    assert_eq!(r, Ok("2".to_string()));
+
+    let r = repl.data.eval_value("fn foo() -> u8 { 3 }", &mut ());
+    assert_eq!(r, Ok(String::new()));
+    assert_eq!(repl.data.current_src().items.len(), 1);
+
+    let r = repl.data.eval_value("foo()", &mut ());
+    assert_eq!(r, Ok("3".to_string()));
+
+    let r = repl.data.eval_value("let b = 1", &mut ());
+    assert!(r.is_err());
+
+    // nothing was written to the output
+    assert_eq!(repl.output(), output);
+}
+
+#[test]
+#[cfg(feature = "test-runnable")]
+#[cfg(target_os = "linux")]
+fn native_lib_linking() {
+    let mut repl = chg_compile_dir(repl!());
+    repl.data
+        .with_native_lib("m", papyrus::linking::NativeLibKind::Dylib)
+        .unwrap();
+    repl.data
+        .persistent_module_code()
+        .push_str("extern \"C\" { fn cbrt(x: f64) -> f64; }");
+
+    let (_, result_kserd) = eval_print(repl, "let r = unsafe { cbrt(27.0) };\nr");
+    assert_eq!(result_kserd, Some((0, Kserd::new_num(3.0))));
+}
+
+#[test]
+#[cfg(feature = "test-runnable")]
+fn out_type_test() {
+    let mut repl = chg_compile_dir(repl!());
+
+    assert_eq!(repl.data.out_type(0), None);
+
+    let r = repl.data.eval_value("vec![1, 2]", &mut ());
+    assert!(r.is_ok());
+    let r = repl.data.eval_value("let s = \"hello\";\ns", &mut ());
+    assert!(r.is_ok());
+
+    assert_eq!(repl.data.out_type(0), Some("alloc::vec::Vec<i32>"));
+    assert_eq!(repl.data.out_type(1), Some("&str"));
+    assert_eq!(repl.data.out_type(2), None);
+}
+
+#[test]
+#[cfg(feature = "test-runnable")]
+fn mutable_binding_across_evals() {
+    let mut repl = chg_compile_dir(repl!());
+
+    let r = repl
+        .data
+        .eval_value("let mut v: Vec<i32> = Vec::new();\nv", &mut ());
+    assert!(r.is_ok());
+    let r = repl.data.eval_value("v.push(1);\nv.len()", &mut ());
+    assert_eq!(r, Ok("1".to_string()));
+    let r = repl.data.eval_value("v == vec![1]", &mut ());
+    assert_eq!(r, Ok("true".to_string()));
+}
+
+#[test]
+#[cfg(feature = "test-runnable")]
+fn identifier_result_type_test() {
+    let mut repl = chg_compile_dir(repl!());
+
+    let r = repl.data.eval_value("let a = 2;\na", &mut ());
+    assert_eq!(r, Ok("2".to_string()));
+    assert_eq!(repl.data.out_type(0), Some("i32"));
+
+    // `a` is not used by a later input, so it is moved and out0 is `i32`
+    let r = repl.data.eval_value("let b: i32 = out0;\nb + 1", &mut ());
+    assert_eq!(r, Ok("3".to_string()));
+    assert_eq!(repl.data.out_type(0), Some("i32"));
+
+    // a moved result which is `ToKserd` but not `Clone` is still converted with `ToKserd`
+    let r = repl.data.eval_value(
+        "struct S(i32);
+impl kserd::ToKserd<'static> for S {
+    fn into_kserd(self) -> Result<kserd::Kserd<'static>, kserd::ToKserdErr> {
+        Ok(kserd::Kserd::new_num(self.0))
+    }
+}
+let s = S(4);
+s",
+        &mut (),
+    );
+    assert_eq!(r, Ok("4".to_string()));
+    assert!(repl.data.out_type(2).unwrap().ends_with("::S"));
+}
+
+#[test]
+#[cfg(feature = "test-runnable")]
+fn eval_check_test() {
+    let mut repl = chg_compile_dir(repl!());
+
+    assert_eq!(repl.data.eval_check("let a: u8 = 1;\na + 1"), Ok(()));
+    assert!(repl.data.eval_check("let a: u8 = \"no\";\na").is_err());
+
+    // checked input is not stored
+    assert_eq!(repl.data.current_src().stmts.len(), 0);
+
+    repl.data.with_run(false);
+    let (mut repl, result_kserd) = eval_print(repl, "std::process::exit(1)"); // checked, not run
+    assert_eq!(result_kserd, None);
+    assert!(repl.output().contains("OK"));
+    // not kept, so it is not run once running is enabled
+    assert_eq!(repl.data.current_src().stmts.len(), 0);
+
+    repl.data.with_run(true);
+    assert_eq!(repl.data.eval_value("2 + 2", &mut ()), Ok("4".to_string()));
+}
+
+#[test]
+#[cfg(feature = "test-runnable")]
+fn unicode_results() {
+    let mut repl = chg_compile_dir(repl!());
+
+    let r = repl.data.eval_value("let s = \"你好，世界\";\ns", &mut ());
+    assert_eq!(r, Ok("\"你好，世界\"".to_string()));
+
+    let r = repl.data.eval_value("let 数 = 3;\n数 + 1", &mut ());
+    assert_eq!(r, Ok("4".to_string()));
+}
+
+#[test]
+#[cfg(feature = "test-runnable")]
+fn derived_debug_results() {
+    let mut repl = chg_compile_dir(repl!());
+
+    let r = repl
+        .data
+        .eval_value("#[derive(Debug, Clone)]\nstruct S { x: i32 }", &mut ());
+    assert_eq!(r, Ok(String::new()));
+
+    // S is not ToKserd, so the derived Debug output is used
+    let r = repl
+        .data
+        .eval_value("let s = S { x: 1 };\ns", &mut ())
+        .unwrap();
+    assert!(r.contains("S {"));
+    assert!(r.contains("x: 1"));
+
+    // ToKserd types still use the Kserd output
+    let r = repl
+        .data
+        .eval_value("let s = S { x: 2 };\nstd::convert::identity(s.x)", &mut ());
+    assert_eq!(r, Ok("2".to_string()));
+}
+
+#[test]
+#[cfg(feature = "test-runnable")]
+fn input_transform_test() {
+    let transcript = PathBuf::from("target/input-transform-transcript.txt");
+    std::fs::remove_file(&transcript).ok();
+
+    let mut repl = chg_compile_dir(repl!());
+    repl.data
+        .with_input_transform(Box::new(|s| s.replace('×', "*")));
+    repl.data.with_transcript(transcript.clone()).unwrap();
+
+    let r = repl.data.eval_value("2 × 3", &mut ());
+    assert_eq!(r, Ok("6".to_string()));
+
+    let (repl, kserd) = eval_print(repl, "out0 × 2");
+    assert_eq!(kserd, Some((1, Kserd::new_num(12))));
+    // the transformed input is kept and recorded
+    assert_eq!(repl.data.last_input(), Some("out0 * 2"));
+    let s = std::fs::read_to_string(&transcript).unwrap();
+    assert!(s.contains("out0 * 2"));
+    assert!(!s.contains('×'));
+}
+
+#[test]
+#[cfg(feature = "test-runnable")]
+fn source_transform_test() {
+    let mut repl = chg_compile_dir(repl!());
+    repl.data.with_source_transform(Box::new(|src| {
+        format!("{}\nconst INJECTED: i32 = 40;\n", src)
+    }));
+
+    let r = repl.data.eval_value("crate::INJECTED + 2", &mut ());
+    assert_eq!(r, Ok("42".to_string()));
+}
+
+#[test]
+#[cfg(feature = "test-runnable")]
+fn name_suggestions_test() {
+    let mut repl = chg_compile_dir(repl!());
+
+    repl.data
+        .eval_value("let value = 1;\nvalue", &mut ())
+        .unwrap();
+
+    let r = repl.data.eval_value("vlaue + 1", &mut ()).unwrap_err();
+    assert!(!r.contains("did you mean"));
+
+    repl.data.with_name_suggestions(true);
+    let r = repl.data.eval_value("vlaue + 1", &mut ()).unwrap_err();
+    assert!(r.ends_with("`vlaue` was not found, did you mean: value?"));
+}
+
+#[test]
+#[cfg(feature = "test-runnable")]
+fn inline_module_test() {
+    let mut repl = chg_compile_dir(repl!());
+
+    let r = repl.data.eval_value(
+        "mod utils { pub fn f() -> u8 { 1 } pub mod inner { pub fn g() -> u8 { 2 } } }",
+        &mut (),
+    );
+    assert_eq!(r, Ok(String::new()));
+
+    let r = repl
+        .data
+        .eval_value("utils::f() + utils::inner::g()", &mut ());
+    assert_eq!(r, Ok("3".to_string()));
+
+    // redefining the module replaces it
+    let r = repl.data.eval_value(
+        "mod utils { pub fn f() -> u8 { 10 } pub mod inner { pub fn g() -> u8 { 20 } } }",
+        &mut (),
+    );
+    assert_eq!(r, Ok(String::new()));
+    let r = repl.data.eval_value("utils::f()", &mut ());
+    assert_eq!(r, Ok("10".to_string()));
+
+    // a failed redefinition keeps the previous module
+    assert!(repl
+        .data
+        .eval_value("mod utils { pub fn f() -> u8 { \"a\" } }", &mut ())
+        .is_err());
+    let r = repl.data.eval_value("utils::f()", &mut ());
+    assert_eq!(r, Ok("10".to_string()));
+}
+
+#[test]
+#[cfg(feature = "test-runnable")]
+fn plain_output_test() {
+    let repl = chg_compile_dir(repl!());
+    let repl = eval_print(repl, "2+2").0;
+
+    let plain = repl.plain_output();
+    assert!(!plain.contains('\u{1b}'));
+    assert!(plain.contains("papyrus [out0]: 4"));
+}
+
+#[test]
+#[cfg(feature = "test-runnable")]
+fn auto_std_imports_test() {
+    let mut repl = chg_compile_dir(repl!());
+
+    let r = repl.data.eval_value("HashMap::<u8, u8>::new()", &mut ());
+    assert!(r.is_err());
+
+    repl.data.with_auto_std_imports(true);
+    let r = repl.data.eval_value(
+        "let mut m = HashMap::new();\nm.insert(1, Rc::new(2));\nm.len()",
+        &mut (),
+    );
+    assert_eq!(r, Ok("1".to_string()));
+
+    // an explicit import does not collide
+    let r = repl
+        .data
+        .eval_value("use std::collections::HashMap;", &mut ());
+    assert_eq!(r, Ok(String::new()));
+    let r = repl.data.eval_value("HashSet::<u8>::new().len()", &mut ());
+    assert_eq!(r, Ok("0".to_string()));
+}
+
+#[test]
+#[cfg(feature = "test-runnable")]
+fn no_std_test() {
+    let mut repl = chg_compile_dir(repl!());
+    repl.data.with_std_mode(papyrus::linking::StdMode::NoStd);
+
+    let r = repl.data.eval_value("core::cmp::max(1, 2)", &mut ());
+    assert_eq!(r, Ok("2".to_string()));
+
+    let r = repl.data.eval_value(
+        "let v: alloc::vec::Vec<u8> = alloc::vec![1, 2, 3];\nv.len()",
+        &mut (),
+    );
+    assert_eq!(r, Ok("3".to_string()));
+
+    let r = repl.data.eval_value("println!(\"hello\")", &mut ());
+    assert!(r
+        .unwrap_err()
+        .contains("`println!` is not available in no_std mode"));
+
+    let r = repl.data.eval_value("std::mem::size_of::<u8>()", &mut ());
+    assert!(r
+        .unwrap_err()
+        .contains("`std` is not available in no_std mode"));
+
+    // the prelude is the core prelude
+    let r = repl.data.eval_value("String::new()", &mut ());
+    assert!(r.is_err());
+}
+
+#[test]
+#[cfg(feature = "test-runnable")]
+fn session_replay_test() {
+    let path = PathBuf::from("target/testing/session-replay-test");
+    let mut repl = chg_compile_dir(repl!());
+    repl.data.with_session_recording(path.clone()).unwrap();
+
+    for input in &["let a = 2;\na * 3", "fn b() -> u8 { 4 }", "b() + 1"] {
+        repl = eval_print(repl, input).0;
+    }
+
+    let replay = Repl::replay(&path).unwrap();
+    assert_eq!(replay.entries.len(), 3);
+    assert_eq!(
+        replay.entries[0],
+        ("let a = 2;\na * 3".to_string(), "6".to_string())
+    );
+    assert_eq!(replay.entries[2], ("b() + 1".to_string(), "5".to_string()));
+    assert_eq!(replay.divergence, None);
+    assert!(replay.warnings.is_empty());
+
+    // alter a recorded output
+    let s = std::fs::read_to_string(&path).unwrap();
+    std::fs::write(&path, s.replace("output 5", "output 6")).unwrap();
+
+    let replay = Repl::replay(&path).unwrap();
+    let divergence = replay.divergence.unwrap();
+    assert_eq!(divergence.index, 2);
+    assert_eq!(divergence.recorded, "6");
+    assert_eq!(divergence.replayed, "5");
+}
+
+#[test]
+#[cfg(feature = "test-runnable")]
+fn session_resume_test() {
+    let path = PathBuf::from("target/testing/session-resume-test");
+    let mut repl = chg_compile_dir(repl!());
+    repl.data.with_session_recording(path.clone()).unwrap();
+
+    for input in &["let a = 2;\na * 3", "fn b() -> u8 { 4 }"] {
+        repl = eval_print(repl, input).0;
+    }
+    drop(repl);
+
+    // an input which was interrupted has no output
+    let mut s = std::fs::read_to_string(&path).unwrap();
+    s.push_str("input loop {}\n");
+    std::fs::write(&path, s).unwrap();
+
+    let (repl, warnings) = repl!().resume_from(&path, &mut ()).unwrap();
+    assert_eq!(
+        warnings,
+        vec!["input did not finish and is not evaluated: loop {}".to_string()]
+    );
+
+    let (_, kserd) = eval_print(repl, "b() + 1");
+    assert_eq!(kserd, Some((1, Kserd::new_num(5))));
+
+    // recording continued in the same file, without the interrupted input
+    let replay = Repl::replay(&path).unwrap();
+    assert_eq!(replay.entries.len(), 3);
+    assert_eq!(replay.entries[2], ("b() + 1".to_string(), "5".to_string()));
+}
+
+#[test]
+#[cfg(feature = "test-runnable")]
+fn echo_bindings_test() {
+    let mut repl = chg_compile_dir(repl!());
+    repl.data.with_echo_bindings(true);
+
+    let (mut repl, kserd) = eval_print(repl, "let x = 2 + 2;");
+    assert_eq!(kserd, Some((0, Kserd::new_num(4))));
+    assert!(repl.output().contains("x: 4"));
+
+    // binding is kept
+    let r = repl.data.eval_value("x * 2", &mut ());
+    assert_eq!(r, Ok("8".to_string()));
+
+    // destructuring is not echoed
+    repl.line_input("let (a, b) = (1, 2);");
+    assert!(matches!(repl.read(), ReadResult::Read(_)));
+}
+
+#[test]
+#[cfg(feature = "test-runnable")]
+fn initial_items_test() {
+    let mut repl = chg_compile_dir(repl!());
+
+    let r = repl
+        .data
+        .with_initial_items(&["fn double(x: i32) -> i32 { x * 2 }", "use std::fmt;"]);
+    assert!(r.is_ok());
+    assert_eq!(repl.data.current_src().items.len(), 2);
+
+    let r = repl.data.eval_value("double(2)", &mut ());
+    assert_eq!(r, Ok("4".to_string()));
+
+    // failing items are not installed
+    assert!(repl
+        .data
+        .with_initial_items(&["fn a() -> u8 { \"no\" }"])
+        .is_err());
+    assert!(repl.data.with_initial_items(&["2 + 2"]).is_err());
+    assert!(repl.data.with_initial_items(&["fn b() {"]).is_err());
+    assert_eq!(repl.data.current_src().items.len(), 2);
+}
+
+#[test]
+#[cfg(feature = "test-runnable")]
+fn assertion_reporting_test() {
+    let mut repl = chg_compile_dir(repl!());
+    repl.data.with_assertion_reporting(true);
+
+    let (repl, kserd) = eval_print(repl, "fn add(a: i32, b: i32) -> i32 { a + b }");
+    assert_eq!(kserd, None);
+
+    let (repl, kserd) = eval_print(repl, "assert_eq!(add(2, 3), 5);");
+    assert_eq!(kserd, None);
+    assert!(repl.output().contains("✓ assertion passed"));
+
+    let (repl, kserd) = eval_print(repl, "assert_eq!(add(2, 3), 6);");
+    assert_eq!(kserd, None);
+    assert!(repl.output().contains("✗ assertion"));
+
+    // assertions are not kept
+    assert_eq!(repl.data.current_src().stmts.len(), 0);
+
+    // only an input which is a single assertion is reported
+    let (repl, kserd) = eval_print(repl, "assert_eq!(add(1, 1), 2);\nadd(2, 2)");
+    assert_eq!(kserd, Some((0, Kserd::new_num(4))));
+    assert_eq!(repl.data.current_src().stmts.len(), 1);
+}
+
+#[test]
+#[cfg(feature = "test-runnable")]
+fn panic_message_filter_test() {
+    let mut repl = chg_compile_dir(repl!());
+    repl.data.with_assertion_reporting(true);
+
+    let r = repl
+        .data
+        .eval_value("assert!(false, \"token 1234\");", &mut ());
+    assert_eq!(r, Err("✗ token 1234".to_string()));
+
+    repl.data
+        .with_panic_message_filter(Box::new(|msg| msg.replace(char::is_numeric, "*")));
+    let r = repl
+        .data
+        .eval_value("assert!(false, \"token 1234\");", &mut ());
+    assert_eq!(r, Err("✗ token ****".to_string()));
+}
+
+#[test]
+#[cfg(feature = "test-runnable")]
+fn propagate_panic_test() {
+    let mut repl = chg_compile_dir(repl!());
+    repl.data
+        .with_panic_strategy(papyrus::linking::PanicStrategy::Propagate);
+
+    // the panic unwinds out of the evaluation function into this thread
+    let r = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| {
+        repl.data.eval_value("panic!(\"propagated\")", &mut ())
+    }));
+    let payload = r.expect_err("the panic should propagate");
+    assert_eq!(payload.downcast_ref::<&str>(), Some(&"propagated"));
+}
+
+#[test]
+#[cfg(feature = "test-runnable")]
+fn float_precision_test() {
+    let mut repl = chg_compile_dir(repl!());
+    repl.data.with_float_precision(Some(4));
+
+    let r = repl.data.eval_value("1.0 / 3.0", &mut ());
+    assert_eq!(r, Ok("0.3333".to_string()));
+    let r = repl.data.eval_value("f64::NAN", &mut ());
+    assert_eq!(r, Ok("NaN".to_string()));
+    let r = repl.data.eval_value("1.0f32 / 0.0", &mut ());
+    assert_eq!(r, Ok("inf".to_string()));
+    let r = repl.data.eval_value("7 / 2", &mut ());
+    assert_eq!(r, Ok("3".to_string()));
+}
+
+#[test]
+#[cfg(feature = "test-runnable")]
+fn global_state_test() {
+    let mut repl = chg_compile_dir(repl!());
+
+    let r = repl.data.eval_value(
+        "fn next() -> usize { \
+         static N: std::sync::atomic::AtomicUsize = std::sync::atomic::AtomicUsize::new(0); \
+         N.fetch_add(1, std::sync::atomic::Ordering::SeqCst) }",
+        &mut (),
+    );
+    assert_eq!(r, Ok(String::new()));
+
+    // each evaluation starts with a fresh static, `out0` runs again before `out1`
+    let r = repl.data.eval_value("next()", &mut ());
+    assert_eq!(r, Ok("0".to_string()));
+    let r = repl.data.eval_value("next()", &mut ());
+    assert_eq!(r, Ok("1".to_string()));
+
+    // process state is shared with the host
+    let r = repl.data.eval_value(
+        "{ std::env::set_var(\"PAPYRUS_GLOBAL_STATE_TEST\", \"set\"); 1 }",
+        &mut (),
+    );
+    assert_eq!(r, Ok("1".to_string()));
+    assert_eq!(
+        std::env::var("PAPYRUS_GLOBAL_STATE_TEST").ok().as_deref(),
+        Some("set")
+    );
+}
+
+#[test]
+#[cfg(feature = "test-runnable")]
+fn generic_fns_test() {
+    let mut repl = chg_compile_dir(repl!());
+
+    let r = repl.data.eval_value("fn id<T>(x: T) -> T { x }", &mut ());
+    assert_eq!(r, Ok(String::new()));
+    let r = repl.data.eval_value("id::<i32>(5)", &mut ());
+    assert_eq!(r, Ok("5".to_string()));
+    let r = repl.data.eval_value("id::<&str>(\"a\")", &mut ());
+    assert_eq!(r, Ok("\"a\"".to_string()));
+
+    let r = repl.data.eval_value(
+        "fn show<T: std::fmt::Display>(x: T) -> String { format!(\"<{}>\", x) }",
+        &mut (),
+    );
+    assert_eq!(r, Ok(String::new()));
+    let r = repl.data.eval_value("show::<u8>(1)", &mut ());
+    assert_eq!(r, Ok("\"<1>\"".to_string()));
+
+    // instantiation that does not satisfy the bound fails without persisting the input
+    let r = repl.data.eval_value("show(vec![1])", &mut ());
+    assert!(r.unwrap_err().contains("Display"));
+    assert_eq!(repl.data.current_src().items.len(), 2);
+    assert_eq!(repl.data.current_src().stmts.len(), 0);
+}
+
+#[test]
+#[cfg(feature = "test-runnable")]
+fn clear_output_test() {
+    let repl = chg_compile_dir(repl!());
+
+    let repl = eval_print(repl, "2+2").0;
+    assert!(repl.output().contains("[out0]"));
+
+    let repl = eval_print(repl, ":clear").0;
+    assert!(!repl.output().contains("[out0]"));
+
+    // only the output is cleared, previous results are kept
+    assert_eq!(repl.data.current_src().stmts.len(), 1);
+}
+
+#[test]
+#[cfg(feature = "test-runnable")]
+fn virtual_file_test() {
+    let mut repl = chg_compile_dir(repl!());
+    let path = "target/testing/virtual-file-test/data.txt";
+
+    repl.data.with_virtual_file(path, "Hello");
+
+    let r = repl.data.eval_value(
+        &format!("std::fs::read_to_string({:?}).unwrap()", path),
+        &mut (),
+    );
+    assert_eq!(r, Ok("\"Hello\"".to_string()));
+
+    // removed after execution
+    assert!(!std::path::Path::new(path).exists());
+}
+
+#[test]
+#[cfg(feature = "test-runnable")]
+fn eval_metrics_test() {
+    let mut repl = chg_compile_dir(repl!());
+
+    assert_eq!(repl.data.eval_metrics(), repl::EvalMetrics::default());
+
+    let r = repl.data.eval_value("2+2", &mut ());
+    assert_eq!(r, Ok("4".to_string()));
+    let metrics = repl.data.eval_metrics();
+    assert!(metrics.compile > std::time::Duration::default());
+    assert!(metrics.run > std::time::Duration::default());
+
+    // compilation fails so nothing is run
+    assert!(repl
+        .data
+        .eval_value("let a: u8 = \"no\";\na", &mut ())
+        .is_err());
+    let metrics = repl.data.eval_metrics();
+    assert_eq!(metrics.run, std::time::Duration::default());
+}
+
+#[test]
+#[cfg(feature = "test-runnable")]
+fn item_only_input_test() {
+    let mut repl = chg_compile_dir(repl!());
+
+    // items are compiled, but there is nothing to run
+    let r = repl
+        .data
+        .eval_value("struct Point { x: i32, y: i32 }", &mut ());
+    assert_eq!(r, Ok(String::new()));
+    let metrics = repl.data.eval_metrics();
+    assert!(metrics.compile > std::time::Duration::default());
+    assert_eq!(metrics.run, std::time::Duration::default());
+    assert_eq!(repl.data.current_src().items.len(), 1);
+    assert!(repl.data.current_src().stmts.is_empty());
+
+    let r = repl
+        .data
+        .eval_value("let p = Point { x: 1, y: 2 };\np.x + p.y", &mut ());
+    assert_eq!(r, Ok("3".to_string()));
+}
+
+#[test]
+#[cfg(feature = "test-runnable")]
+fn closure_results() {
+    let mut repl = chg_compile_dir(repl!());
+
+    // closures are neither ToKserd nor Debug, so the type name is output
+    let r = repl.data.eval_value("|x: i32| x + 1", &mut ()).unwrap();
+    assert!(r.contains("{{closure}}"));
+
+    // the closure is kept and can be called
+    let r = repl.data.eval_value("out0(2)", &mut ());
+    assert_eq!(r, Ok("3".to_string()));
+}
+
+#[test]
+#[cfg(feature = "test-runnable")]
+fn trait_object_results() {
+    let mut repl = chg_compile_dir(repl!());
+
+    // a boxed `Display` trait object is not `Debug`, so the `Display` output is used
+    let r = repl
+        .data
+        .eval_value("Box::new(5) as Box<dyn std::fmt::Display>", &mut ())
+        .unwrap();
+    assert!(r.contains('5'));
+    assert!(!r.contains("Box<dyn"));
+
+    // the boxed value is kept and usable through the trait
+    let r = repl.data.eval_value("out0.to_string() == \"5\"", &mut ());
+    assert_eq!(r, Ok("true".to_string()));
+}
+
+#[test]
+#[cfg(feature = "test-runnable")]
+fn last_compile_command_test() {
+    let mut repl = chg_compile_dir(repl!());
+
+    assert_eq!(repl.data.last_compile_command(), None);
+
+    let r = repl.data.eval_value("2+2", &mut ());
+    assert_eq!(r, Ok("4".to_string()));
+
+    let cmd = repl.data.last_compile_command().unwrap();
+    assert!(cmd.contains("cargo rustc"));
+    assert!(!cmd.contains("--emit=metadata"));
+
+    repl.data.with_run(false);
+    let r = repl.data.eval_value("2+2", &mut ());
+    assert_eq!(r, Ok("OK".to_string())); // only type checked
+    let cmd = repl.data.last_compile_command().unwrap();
+    assert!(cmd.contains("--emit=metadata"));
+}
+
+#[test]
+#[cfg(feature = "test-runnable")]
+fn eval_batch_test() {
+    let mut repl = chg_compile_dir(repl!());
+
+    let r = repl.data.eval_batch(
+        &["fn a() -> i32 { 1 }", "a() + 1", "let b = a();\nb * 3"],
+        &mut (),
+    );
+    assert_eq!(r, Ok(vec![String::new(), "2".to_string(), "3".to_string()]));
+    assert_eq!(repl.data.current_src().items.len(), 1);
+    assert_eq!(repl.data.current_src().stmts.len(), 2);
+
+    // the last input is restored with the source code
+    let (repl, _) = eval_print(repl, "a() * 5");
+    let mut repl = repl;
+    assert_eq!(repl.data.last_input(), Some("a() * 5"));
+
+    // the third input fails, so the first two are rolled back
+    let r = repl.data.eval_batch(
+        &["fn c() -> i32 { 2 }", "c().to_string()", "c() + \"no\""],
+        &mut (),
+    );
+    assert_eq!(r.unwrap_err().index, 2);
+    assert_eq!(repl.data.current_src().items.len(), 1);
+    assert_eq!(repl.data.current_src().stmts.len(), 3);
+    assert_eq!(repl.data.last_input(), Some("a() * 5"));
+    assert_eq!(repl.data.out_type(2), Some("i32"));
+    assert_eq!(repl.data.out_type(3), None);
+
+    // messages from successful inputs are not failures
+    repl.data.with_assertion_reporting(true);
+    let r = repl
+        .data
+        .eval_batch(&["fn d() -> i32 { 3 }", "assert_eq!(d(), 3)"], &mut ());
+    assert_eq!(r, Ok(vec![String::new(), "✓ assertion passed".to_string()]));
+    assert_eq!(repl.data.current_src().items.len(), 2);
+}
+
+#[test]
+#[cfg(feature = "test-runnable")]
+fn shadowed_data_name_test() {
+    let mut repl = chg_compile_dir(repl!(String));
+
+    // a warning is written, the input is still evaluated
+    repl.line_input("let app_data = 5;\napp_data + 1");
+    let repl = match repl.read() {
+        ReadResult::Read(_) => panic!("should be at Eval state!"),
+        ReadResult::Eval(repl) => repl.eval(&mut String::new()).repl,
+    };
+    let (repl, out) = repl.print();
+    assert_eq!(out, Some((0, Kserd::new_num(6))));
+    assert!(repl
+        .output()
+        .contains("warning: `app_data` is the name of the linked data"));
+    assert!(repl.output().contains("with_data_name"));
+    assert_eq!(repl.data.current_src().stmts.len(), 1);
+
+    // closure parameters shadow it too
+    let mut repl = repl;
+    repl.line_input("[1, 2].iter().map(|app_data| app_data * 2).sum::<i32>()");
+    let repl = match repl.read() {
+        ReadResult::Read(_) => panic!("should be at Eval state!"),
+        ReadResult::Eval(repl) => repl.eval(&mut String::new()).repl,
+    };
+    let (repl, out) = repl.print();
+    assert_eq!(out, Some((1, Kserd::new_num(6))));
+    assert_eq!(repl.output().matches("warning: `app_data`").count(), 2);
+}
+
+#[test]
+#[cfg(feature = "test-runnable")]
+fn impl_trait_test() {
+    let mut repl = chg_compile_dir(repl!());
+
+    let r = repl
+        .data
+        .eval_value("fn make() -> impl Iterator<Item = i32> { 0..3 }", &mut ());
+    assert_eq!(r, Ok(String::new()));
+
+    let r = repl.data.eval_value("make().collect::<Vec<_>>()", &mut ());
+    let s = r.unwrap();
+    assert!(s.contains('0') && s.contains('1') && s.contains('2'));
+    assert!(repl.data.out_type(0).unwrap().contains("Vec<i32>"));
+
+    let r = repl.data.eval_value("make().sum::<i32>()", &mut ());
+    assert_eq!(r, Ok("3".to_string()));
+
+    // the concrete type of an opaque result is captured
+    let r = repl.data.eval_value("make()", &mut ());
+    assert!(r.is_ok());
+    assert!(repl.data.out_type(2).unwrap().contains("Range<i32>"));
+}
+
+#[test]
+#[cfg(feature = "test-runnable")]
+fn type_alias_and_const_generics_test() {
+    let mut repl = chg_compile_dir(repl!());
+
+    let r = repl.data.eval_value("type Mat = Vec<Vec<f64>>;", &mut ());
+    assert_eq!(r, Ok(String::new()));
+
+    let r = repl.data.eval_value(
+        "let m: Mat = vec![vec![1.0, 2.0], vec![3.0]];\nm.len()",
+        &mut (),
+    );
+    assert_eq!(r, Ok("2".to_string()));
+
+    let r = repl
+        .data
+        .eval_value("fn zeros<const N: usize>() -> [u8; N] { [0; N] }", &mut ());
+    assert_eq!(r, Ok(String::new()));
+
+    let r = repl.data.eval_value("zeros::<3>().len()", &mut ());
+    assert_eq!(r, Ok("3".to_string()));
+
+    // both items persist
+    assert_eq!(repl.data.current_src().items.len(), 2);
+}
+
+#[test]
+#[cfg(feature = "test-runnable")]
+fn out_capture_test() {
+    let mut repl = chg_compile_dir(repl!());
+    repl.data.with_out_capture(false);
+
+    let r = repl.data.eval_value("2 + 2", &mut ());
+    assert_eq!(r, Ok("4".to_string()));
+    assert!(repl.data.current_src().stmts.is_empty());
+
+    let r = repl.data.eval_value("out0 + 1", &mut ());
+    assert!(r.unwrap_err().contains("cannot find value `out0`"));
+
+    // bindings are kept
+    let r = repl.data.eval_value("let a = 1;\na * 2", &mut ());
+    assert_eq!(r, Ok("2".to_string()));
+    let r = repl.data.eval_value("a + 1", &mut ());
+    assert_eq!(r, Ok("2".to_string()));
+    assert_eq!(repl.data.current_src().stmts.len(), 1);
+
+    // an input ending in a binding is not mistaken for a released result
+    let r = repl.data.eval_value("let b = a;", &mut ());
+    assert!(r.is_err());
+    assert_eq!(repl.data.current_src().stmts.len(), 1);
+
+    // results which are not kept still use the float precision
+    repl.data.with_float_precision(Some(2));
+    let r = repl.data.eval_value("1.0 / 3.0", &mut ());
+    assert_eq!(r, Ok("0.33".to_string()));
+}
+
+#[test]
+#[cfg(feature = "test-runnable")]
+fn linked_crate_paths_test() {
+    let mut repl = chg_compile_dir(repl!());
+    repl.data.with_external_lib(
+        papyrus::linking::Extern::new(
+            "test-resources/external_crate/target/debug/libexternal_crate.rlib",
+        )
+        .unwrap(),
+    );
+
+    let r = repl.data.eval_value(
+        "external_crate::MyStruct::new(1, 2).add_contents()",
+        &mut (),
+    );
+    assert_eq!(r, Ok("3".to_string()));
+
+    // associated consts and fns resolve through the crate path
+    let r = repl
+        .data
+        .eval_value("external_crate::MyStruct::ORIGIN.add_contents()", &mut ());
+    assert_eq!(r, Ok("0".to_string()));
+    let r = repl.data.eval_value(
+        "let new = external_crate::MyStruct::new;\nnew(3, 4).add_contents()",
+        &mut (),
+    );
+    assert_eq!(r, Ok("7".to_string()));
+}
+
+#[test]
+#[cfg(feature = "test-runnable")]
+fn unlink_crate_test() {
+    let mut repl = chg_compile_dir(repl!());
+    assert!(repl.data.linked_crates().is_empty());
+
+    repl.data.with_external_lib(
+        papyrus::linking::Extern::new(
+            "test-resources/external_crate/target/debug/libexternal_crate.rlib",
+        )
+        .unwrap(),
+    );
+    assert_eq!(repl.data.linked_crates(), vec!["external_crate"]);
+
+    let r = repl.data.unlink_crate("other_crate");
+    assert_eq!(r.err(), Some("no linked crate has this name"));
+
+    assert!(repl.data.unlink_crate("external_crate").is_ok());
+    assert!(repl.data.linked_crates().is_empty());
+    assert!(repl.data.linking().external_libs.is_empty());
+
+    let r = repl.data.eval_value(
+        "external_crate::MyStruct::new(1, 2).add_contents()",
+        &mut (),
+    );
+    assert!(r.is_err());
+}
+
+#[test]
+#[cfg(feature = "test-runnable")]
+fn instance_name_test() {
+    let name = "repl_api_instance_name_test";
+    let dir = std::env::temp_dir().join("papyrus").join(name);
+
+    let mut repl = repl!();
+    repl.data.with_instance_name(name).unwrap();
+    assert_eq!(repl.data.instance_name(), Some(name));
+    let r = repl.data.eval_value("2+2", &mut ());
+    assert_eq!(r, Ok("4".to_string()));
+    assert!(dir.join("src/lib.rs").exists());
+
+    // a live instance holds the name
+    let mut other = repl!();
+    assert!(other.data.with_instance_name(name).is_err());
+    drop(repl);
+    assert!(other.data.with_instance_name(name).is_ok());
+
+    // changing the directory releases the name
+    other.data.with_compilation_dir(unqiue_build_dir()).unwrap();
+    assert_eq!(other.data.instance_name(), None);
+    let mut repl = repl!();
+    assert!(repl.data.with_instance_name(name).is_ok());
+}
+
+#[test]
+#[cfg(feature = "test-runnable")]
+fn dbg_macro_test() {
+    let mut repl = chg_compile_dir(repl!());
+
+    // `dbg!` writes to stderr, the result is unaffected
+    let r = repl.data.eval_value("dbg!(2 + 2) * 10", &mut ());
+    assert_eq!(r, Ok("40".to_string()));
+    let r = repl.data.eval_value("let a = dbg!(3);\na + out0", &mut ());
+    assert_eq!(r, Ok("43".to_string()));
+}
+
+#[test]
+#[cfg(feature = "test-runnable")]
+fn emit_test() {
+    let mut repl = chg_compile_dir(repl!());
+    repl.data.emit("status: ready");
+
+    let repl = eval_print(repl, "2+2").0;
+
+    let output = repl.output();
+    let status = output.find("status: ready").unwrap();
+    assert!(status < output.find("[out0]").unwrap());
+    assert_eq!(output.matches("status: ready").count(), 1);
+}
+
+#[test]
+#[cfg(feature = "test-runnable")]
+fn out_prefix_test() {
+    let mut repl = chg_compile_dir(repl!());
+
+    // an item named like a result is rejected
+    let r = repl.data.eval_value("const out0: i32 = 1;", &mut ());
+    assert!(r.unwrap_err().contains("with_out_prefix"));
+    assert!(repl.data.current_src().items.is_empty());
+
+    repl.data.with_out_prefix("res").unwrap();
+    let r = repl.data.eval_value("const out0: i32 = 1;", &mut ());
+    assert_eq!(r, Ok(String::new()));
+    let r = repl.data.eval_value("out0 + 1", &mut ());
+    assert_eq!(r, Ok("2".to_string()));
+    let r = repl.data.eval_value("res0 * 2", &mut ());
+    assert_eq!(r, Ok("4".to_string()));
+
+    // so is a `let` binding, also when only type checking
+    let r = repl.data.eval_value("let res5 = 1;", &mut ());
+    assert!(r.unwrap_err().contains("with_out_prefix"));
+    let r = repl.data.eval_check("let res5 = 1;");
+    assert!(r.unwrap_err().contains("with_out_prefix"));
+}
+
+#[test]
+#[cfg(feature = "test-runnable")]
+fn reset_outputs_test() {
+    let mut repl = chg_compile_dir(repl!());
+
+    let r = repl.data.eval_value("let a = 1;\na + 1", &mut ());
+    assert_eq!(r, Ok("2".to_string()));
+    let r = repl.data.eval_value("a * 10", &mut ());
+    assert_eq!(r, Ok("10".to_string()));
+
+    assert_eq!(repl.data.reset_outputs(), 2);
+    assert_eq!(repl.data.current_src().stmts.len(), 1);
+
+    // the `let` binding is kept, results start again at out0
+    let r = repl.data.eval_value("a + 5", &mut ());
+    assert_eq!(r, Ok("6".to_string()));
+    let r = repl.data.eval_value("out0 * 2", &mut ());
+    assert_eq!(r, Ok("12".to_string()));
+    assert_eq!(repl.data.out_type(1), Some("i32"));
+    assert_eq!(repl.data.out_type(2), None);
+}
+
+#[test]
+#[cfg(feature = "test-runnable")]
+fn eval_fn_prefix_test() {
+    let mut repl = chg_compile_dir(repl!());
+
+    repl.data
+        .with_eval_fn_prefix("__papyrus_")
+        .unwrap()
+        .with_eval_fn_name("eval")
+        .unwrap();
+
+    // a user function named like the default does not collide
+    let r = repl
+        .data
+        .eval_value("fn _lib_intern_eval() -> i32 { 3 }", &mut ());
+    assert_eq!(r, Ok(String::new()));
+    let r = repl.data.eval_value("_lib_intern_eval() + 1", &mut ());
+    assert_eq!(r, Ok("4".to_string()));
+
+    assert!(repl.data.with_eval_fn_prefix("1_").is_err());
+}
+
+#[test]
+#[cfg(feature = "test-runnable")]
+fn edit_last_test() {
+    let repl = chg_compile_dir(repl!());
+
+    let repl = eval_print(repl, ":edit last").0;
+    assert!(repl.output().contains("there is no previous input"));
+
+    let mut repl = eval_print(repl, "2 + \"2\"").0;
+    assert_eq!(repl.data.last_input(), Some("2 + \"2\""));
+    repl.data.editing_src = None;
+
+    let repl = eval_print(repl, ":edit last").0;
+    assert_eq!(repl.data.editing_src.as_deref(), Some("2 + \"2\""));
+    // the failed input was not kept, the fixed input is a new input
+    let mut repl = eval_print(repl, "2 + 2").0;
+    assert!(repl.output().contains("[out0]"));
+    assert_eq!(repl.data.current_src().stmts.len(), 1);
+    repl.data.editing_src = None;
+
+    // a kept input is replaced
+    let repl = eval_print(repl, ":edit last").0;
+    assert_eq!(repl.data.editing_src.as_deref(), Some("2 + 2"));
+    let (mut repl, out) = eval_print(repl, "2 + 3");
+    assert_eq!(out, Some((0, Kserd::new_num(5))));
+    assert_eq!(repl.data.current_src().stmts.len(), 1);
+    repl.data.editing_src = None;
+
+    // so is a single item
+    let repl = eval_print(repl, "fn f() -> i32 { 1 }").0;
+    let repl = eval_print(repl, ":edit last").0;
+    let repl = eval_print(repl, "fn f() -> i32 { 2 }").0;
+    assert_eq!(repl.data.current_src().items.len(), 1);
+    let (_, out) = eval_print(repl, "f()");
+    assert_eq!(out, Some((1, Kserd::new_num(2))));
+}
+
+#[test]
+#[cfg(feature = "test-runnable")]
+fn variant_cmd_test() {
+    let repl = chg_compile_dir(repl!());
+
+    let repl = eval_print(repl, ":variant \"x\".parse::<u8>()").0;
+    assert!(repl.plain_output().lines().any(|l| l == "Err"));
+
+    let repl = eval_print(repl, ":variant 1 + 1").0;
+    assert!(repl.output().contains("`1 + 1` is not an enum variant"));
+
+    // structs format like variants, but are not
+    let repl = eval_print(
+        repl,
+        "#[derive(Debug)] struct Running { ticks: u8 }\n#[derive(Debug)] enum State { Running { ticks: u8 } }",
+    )
+    .0;
+    let repl = eval_print(repl, ":variant State::Running { ticks: 2 }").0;
+    assert!(repl.plain_output().lines().any(|l| l == "Running"));
+    let repl = eval_print(repl, ":variant Running { ticks: 2 }").0;
+    assert!(repl
+        .output()
+        .contains("`Running { ticks: 2 }` is not an enum variant"));
+    let repl = eval_print(repl, ":variant String::from(\"Some\")").0;
+    assert!(repl.output().contains("is not an enum variant"));
+
+    // the expressions are not kept
+    assert_eq!(repl.data.current_src().stmts.len(), 0);
+}
+
+#[test]
+#[cfg(feature = "test-runnable")]
+fn disk_usage_test() {
+    let mut repl = chg_compile_dir(repl!());
+    repl.data.with_max_disk_usage(Some(0));
+
+    let r = repl.data.eval_value("2 + 2", &mut ());
+    assert_eq!(r, Ok("4".to_string()));
+    assert!(repl.data.session_disk_usage().unwrap() > 0);
+
+    let repl = eval_print(repl, ":disk").0;
+    assert!(repl.plain_output().contains(" used, capped to 0 B"));
+}
+
+#[test]
+#[cfg(feature = "test-runnable")]
+fn block_expr_with_print_test() {
+    // evaluated code prints to the process's stdout, so the test is run again in a child process
+    // to capture it
+    if std::env::var_os("PAPYRUS_CAPTURE_STDOUT").is_none() {
+        let out = std::process::Command::new(std::env::current_exe().unwrap())
+            .args(&["block_expr_with_print_test", "--exact", "--nocapture"])
+            .env("PAPYRUS_CAPTURE_STDOUT", "1")
+            .output()
+            .unwrap();
+        assert!(out.status.success());
+
+        // the print goes to stdout before the result is written
+        let stdout = String::from_utf8_lossy(&out.stdout);
+        let print = stdout
+            .find("[1, 2, 3]\n")
+            .expect("the print should be captured");
+        let result = stdout.find("[out0]").expect("the result should be written");
+        assert!(print < result);
+        return;
+    }
+
+    let repl = chg_compile_dir(repl!());
+
+    let mut repl = eval_print(repl, "{ let v = vec![1, 2, 3]; println!(\"{:?}\", v); v }").0;
+    assert!(repl.output().contains("[out0]"));
+    println!("{}", repl.output());
+    assert!(repl.data.out_type(0).unwrap().contains("Vec<i32>"));
+
+    let r = repl.data.eval_value("out0.len()", &mut ());
+    assert_eq!(r, Ok("3".to_string()));
+}
+
+#[test]
+#[cfg(feature = "test-runnable")]
+fn blank_input_test() {
+    let mut repl = chg_compile_dir(repl!());
+
+    repl.line_input("   ");
+    let mut repl = match repl.read() {
+        ReadResult::Read(_) => panic!("should be at Eval state!"),
+        ReadResult::Eval(repl) => repl,
+    };
+    let rx = repl.output_listen();
+    let repl = repl.eval(&mut ()).repl;
+    assert_eq!(rx.try_iter().count(), 0);
+    assert_eq!(repl.data.last_compile_command(), None);
+    assert_eq!(repl.data.last_input(), None);
+
+    let mut repl = repl.print().0;
+    assert_eq!(repl.data.eval_value(" \n ", &mut ()), Ok(String::new()));
+    assert_eq!(repl.data.last_compile_command(), None);
+}